@@ -33,14 +33,128 @@ pub struct PivotConfig {
     pub row_fields: Vec<String>,
     pub column_fields: Vec<String>,
     pub value_fields: Vec<String>,
-    pub aggregation: String, // "sum", "count", "average", "max", "min"
+    /// "sum", "count", "count_numeric", "average", "max", "min".
+    /// `count` counts matching rows even when the value field is missing or
+    /// non-numeric; `count_numeric` counts only values that parse as numbers.
+    /// All other aggregations ignore missing/non-numeric values.
+    pub aggregation: String,
+    #[serde(default)]
+    pub decimals: Option<usize>, // Decimal places for formatted cells (default 2)
+    #[serde(default)]
+    pub filters: Vec<PivotFilter>, // Applied to raw rows before aggregation
+    #[serde(default)]
+    pub nan_placeholder: Option<String>, // Shown instead of NaN/infinite values (default empty)
+    #[serde(default)]
+    pub thousands_separator: Option<String>, // e.g. "," (US) or "." (European); default none
+    #[serde(default)]
+    pub decimal_separator: Option<String>, // e.g. "," (European); default "."
 }
 
+/// Row filter applied before aggregation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PivotFilter {
+    pub field: String,
+    pub op: String, // "eq", "neq", "gt", "lt", "contains"
+    pub value: serde_json::Value,
+}
+
+impl PivotFilter {
+    const OPS: [&'static str; 5] = ["eq", "neq", "gt", "lt", "contains"];
+
+    fn matches(&self, row: &HashMap<String, String>) -> bool {
+        let expected = match &self.value {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+
+        let actual = match row.get(&self.field) {
+            Some(actual) => actual,
+            // A row without the field is "not equal" to anything
+            None => return self.op == "neq",
+        };
+
+        match self.op.as_str() {
+            "eq" => match (parse_number(actual), parse_number(&expected)) {
+                (Some(a), Some(b)) => a == b,
+                _ => actual == &expected,
+            },
+            "neq" => match (parse_number(actual), parse_number(&expected)) {
+                (Some(a), Some(b)) => a != b,
+                _ => actual != &expected,
+            },
+            "gt" => matches!((parse_number(actual), parse_number(&expected)), (Some(a), Some(b)) if a > b),
+            "lt" => matches!((parse_number(actual), parse_number(&expected)), (Some(a), Some(b)) if a < b),
+            "contains" => actual.contains(expected.as_str()),
+            _ => true,
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+/// Format a number with fixed decimals: NaN and infinities become the placeholder and
+/// negative zero (including values that round to zero) prints without a sign
+fn format_number(value: f64, decimals: usize, placeholder: &str) -> String {
+    if !value.is_finite() {
+        return placeholder.to_string();
+    }
+    let formatted = format!("{:.*}", decimals, value);
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_string(),
+        _ => formatted,
+    }
+}
+
+/// Apply locale separators to a number formatted by `format_number`
+fn localize_number(formatted: &str, thousands_separator: &str, decimal_separator: &str) -> String {
+    let (sign, digits) = match formatted.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", formatted),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let mut localized = sign.to_string();
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            localized.push_str(thousands_separator);
+        }
+        localized.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        localized.push_str(decimal_separator);
+        localized.push_str(fraction);
+    }
+    localized
+}
+
+/// Tolerant number parser: trims whitespace and ignores thousands separators
+fn parse_number(value: &str) -> Option<f64> {
+    let cleaned: String = value.trim().chars().filter(|c| *c != ',').collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    cleaned.parse::<f64>().ok()
+}
+
+/// Joins the values of a composite column header, e.g. `2023 > Q1`
+pub const COLUMN_HEADER_SEPARATOR: &str = " > ";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PivotResult {
-    pub headers: Vec<String>,
+    pub headers: Vec<String>, // Several column fields give composite headers joined by COLUMN_HEADER_SEPARATOR
     pub rows: Vec<Vec<String>>,
     pub totals: Vec<f64>,
+    #[serde(skip)]
+    column_keys: Vec<Vec<String>>, // One value per column field for each data column
 }
 
 // Raw data structure
@@ -66,6 +180,11 @@ impl PivotTable {
                 column_fields: Vec::new(),
                 value_fields: Vec::new(),
                 aggregation: "sum".to_string(),
+                decimals: None,
+                filters: Vec::new(),
+                nan_placeholder: None,
+                thousands_separator: None,
+                decimal_separator: None,
             },
         }
     }
@@ -79,10 +198,51 @@ impl PivotTable {
         Ok(())
     }
 
+    /// Upsert rows by a composite key: rows whose key fields match an existing
+    /// row replace it, everything else is appended
+    #[wasm_bindgen]
+    pub fn add_data_keyed(&mut self, data_json: &str, key_fields_json: &str) -> Result<(), JsValue> {
+        let data: Vec<HashMap<String, String>> = serde_json::from_str(data_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse data: {}", e)))?;
+        let key_fields: Vec<String> = serde_json::from_str(key_fields_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse key fields: {}", e)))?;
+
+        let row_key = |row: &HashMap<String, String>| -> Option<Vec<String>> {
+            key_fields.iter().map(|field| row.get(field).cloned()).collect()
+        };
+
+        let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+        for (i, row) in self.raw_data.data.iter().enumerate() {
+            if let Some(key) = row_key(row) {
+                index.insert(key, i);
+            }
+        }
+
+        for row in data {
+            match row_key(&row) {
+                Some(key) => {
+                    if let Some(&i) = index.get(&key) {
+                        self.raw_data.data[i] = row;
+                    } else {
+                        index.insert(key, self.raw_data.data.len());
+                        self.raw_data.data.push(row);
+                    }
+                }
+                // Rows missing a key field can't be matched, so they are appended
+                None => self.raw_data.data.push(row),
+            }
+        }
+        Ok(())
+    }
+
     #[wasm_bindgen]
     pub fn set_config(&mut self, config_json: &str) -> Result<(), JsValue> {
         let config: PivotConfig = serde_json::from_str(config_json)
             .map_err(|e| JsValue::from_str(&format!("Failed to parse config: {}", e)))?;
+
+        if let Some(filter) = config.filters.iter().find(|f| !PivotFilter::OPS.contains(&f.op.as_str())) {
+            return Err(JsValue::from_str(&format!("Unknown filter op: {}", filter.op)));
+        }
         
         self.config = config;
         Ok(())
@@ -95,14 +255,84 @@ impl PivotTable {
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
     }
 
+    /// Render the pivot result as an HTML table, styled like the html-builder's `element-table`
+    #[wasm_bindgen]
+    pub fn generate_pivot_html(&self) -> Result<String, JsValue> {
+        let result = self.calculate_pivot()?;
+        let cell_style = "border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top;";
+        let header_style = format!("{} font-weight: bold; background-color: #f3f4f6;", cell_style);
+
+        let mut html = String::new();
+        html.push_str("<table class=\"element-table pivot-table\" style=\"border-collapse: collapse; table-layout: auto;\">\n");
+
+        html.push_str("  <thead>\n");
+        let levels = self.config.column_fields.len();
+        if levels > 1 {
+            // One header row per column field; a value spans the columns sharing it and every level above
+            for level in 0..levels {
+                html.push_str("    <tr>\n");
+                if level == 0 {
+                    html.push_str(&format!("      <th rowspan=\"{}\" style=\"{}\"></th>\n", levels, header_style));
+                }
+                let mut start = 0;
+                while start < result.column_keys.len() {
+                    let prefix = &result.column_keys[start][..=level];
+                    let span = result.column_keys[start..].iter().take_while(|key| &key[..=level] == prefix).count();
+                    html.push_str(&format!(
+                        "      <th colspan=\"{}\" style=\"{}\">{}</th>\n",
+                        span, header_style, escape_html(&prefix[level])
+                    ));
+                    start += span;
+                }
+                if level == 0 {
+                    html.push_str(&format!("      <th rowspan=\"{}\" style=\"{}\">Total</th>\n", levels, header_style));
+                }
+                html.push_str("    </tr>\n");
+            }
+        } else {
+            html.push_str("    <tr>\n");
+            for header in &result.headers {
+                html.push_str(&format!("      <th style=\"{}\">{}</th>\n", header_style, escape_html(header)));
+            }
+            html.push_str("    </tr>\n");
+        }
+        html.push_str("  </thead>\n");
+
+        html.push_str("  <tbody>\n");
+        let last_index = result.rows.len().saturating_sub(1);
+        for (i, row) in result.rows.iter().enumerate() {
+            // The last row is always the totals row
+            if i == last_index {
+                html.push_str("    <tr class=\"pivot-total-row\">\n");
+                for value in row {
+                    html.push_str(&format!("      <td style=\"{}\">{}</td>\n", header_style, escape_html(value)));
+                }
+            } else {
+                html.push_str("    <tr>\n");
+                for value in row {
+                    html.push_str(&format!("      <td style=\"{}\">{}</td>\n", cell_style, escape_html(value)));
+                }
+            }
+            html.push_str("    </tr>\n");
+        }
+        html.push_str("  </tbody>\n</table>\n");
+
+        Ok(html)
+    }
+
     fn calculate_pivot(&self) -> Result<PivotResult, JsValue> {
         if self.raw_data.data.is_empty() {
             return Err(JsValue::from_str("No data available"));
         }
 
+        // Apply filters before aggregation
+        let data: Vec<&HashMap<String, String>> = self.raw_data.data.iter()
+            .filter(|row| self.config.filters.iter().all(|filter| filter.matches(row)))
+            .collect();
+
         // Get unique row values
         let mut row_values: Vec<String> = Vec::new();
-        for row in &self.raw_data.data {
+        for row in &data {
             for field in &self.config.row_fields {
                 if let Some(value) = row.get(field) {
                     if !row_values.contains(value) {
@@ -112,21 +342,11 @@ impl PivotTable {
             }
         }
 
-        // Get unique column values
-        let mut column_values: Vec<String> = Vec::new();
-        for row in &self.raw_data.data {
-            for field in &self.config.column_fields {
-                if let Some(value) = row.get(field) {
-                    if !column_values.contains(value) {
-                        column_values.push(value.clone());
-                    }
-                }
-            }
-        }
+        let column_values = self.column_keys(&data);
 
         // Create headers
         let mut headers = vec!["".to_string()]; // Empty cell for row labels
-        headers.extend(column_values.clone());
+        headers.extend(column_values.iter().map(|key| key.join(COLUMN_HEADER_SEPARATOR)));
         headers.push("Total".to_string());
 
         // Calculate pivot data
@@ -138,18 +358,23 @@ impl PivotTable {
             let mut row_total = 0.0;
 
             for col_value in &column_values {
-                let cell_value = self.calculate_cell_value(row_value, col_value);
-                pivot_row.push(format!("{:.2}", cell_value));
-                row_total += cell_value;
+                let cell_value = self.calculate_cell_value(&data, row_value, col_value);
+                pivot_row.push(self.format_value(cell_value));
+                // Cells without a value (e.g. an average of nothing) are left out of totals
+                if cell_value.is_finite() {
+                    row_total += cell_value;
+                }
             }
 
-            pivot_row.push(format!("{:.2}", row_total));
+            pivot_row.push(self.format_value(row_total));
             pivot_rows.push(pivot_row);
 
             // Update totals
             for (i, col_value) in column_values.iter().enumerate() {
-                let cell_value = self.calculate_cell_value(row_value, col_value);
-                totals[i] += cell_value;
+                let cell_value = self.calculate_cell_value(&data, row_value, col_value);
+                if cell_value.is_finite() {
+                    totals[i] += cell_value;
+                }
             }
             let last_index = totals.len() - 1;
             totals[last_index] += row_total;
@@ -158,7 +383,7 @@ impl PivotTable {
         // Add totals row
         let mut totals_row = vec!["Total".to_string()];
         for total in &totals {
-            totals_row.push(format!("{:.2}", total));
+            totals_row.push(self.format_value(*total));
         }
         pivot_rows.push(totals_row);
 
@@ -166,13 +391,68 @@ impl PivotTable {
             headers,
             rows: pivot_rows,
             totals,
+            column_keys: column_values,
         })
     }
 
-    fn calculate_cell_value(&self, row_value: &str, col_value: &str) -> f64 {
+    /// Distinct combinations of the column fields' values, one per data column. Rows missing a
+    /// column field are left out. Columns are grouped by their first field's value, each level
+    /// in first-seen order, so a composite header's shared values are adjacent.
+    fn column_keys(&self, data: &[&HashMap<String, String>]) -> Vec<Vec<String>> {
+        if self.config.column_fields.is_empty() {
+            return Vec::new();
+        }
+        let mut keys: Vec<Vec<String>> = Vec::new();
+        let mut seen: Vec<Vec<&String>> = vec![Vec::new(); self.config.column_fields.len()];
+        for row in data {
+            let Some(key) = self.config.column_fields.iter().map(|field| row.get(field)).collect::<Option<Vec<&String>>>() else {
+                continue;
+            };
+            for (level, value) in key.iter().enumerate() {
+                if !seen[level].contains(value) {
+                    seen[level].push(value);
+                }
+            }
+            let key: Vec<String> = key.into_iter().cloned().collect();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        let order = |key: &Vec<String>| -> Vec<usize> {
+            key.iter()
+                .enumerate()
+                .map(|(level, value)| seen[level].iter().position(|seen_value| *seen_value == value).unwrap_or(usize::MAX))
+                .collect()
+        };
+        keys.sort_by_key(order);
+        keys
+    }
+
+    /// Format an aggregated value with the configured separators; counts are always whole numbers.
+    /// Totals are calculated from the raw values, never from formatted text
+    fn format_value(&self, value: f64) -> String {
+        let decimals = if matches!(self.config.aggregation.as_str(), "count" | "count_numeric") {
+            0
+        } else {
+            self.config.decimals.unwrap_or(2)
+        };
+        let formatted = format_number(value, decimals, self.config.nan_placeholder.as_deref().unwrap_or(""));
+        if !value.is_finite() {
+            return formatted; // The placeholder is shown as given
+        }
+        localize_number(
+            &formatted,
+            self.config.thousands_separator.as_deref().unwrap_or(""),
+            self.config.decimal_separator.as_deref().unwrap_or("."),
+        )
+    }
+
+    fn calculate_cell_value(&self, data: &[&HashMap<String, String>], row_value: &str, col_key: &[String]) -> f64 {
         let mut values: Vec<f64> = Vec::new();
+        let mut matched_rows = 0usize;
 
-        for data_row in &self.raw_data.data {
+        for data_row in data {
             let mut matches_row = true;
             let mut matches_col = true;
 
@@ -190,7 +470,7 @@ impl PivotTable {
             }
 
             // Check if column matches
-            for field in &self.config.column_fields {
+            for (field, col_value) in self.config.column_fields.iter().zip(col_key) {
                 if let Some(value) = data_row.get(field) {
                     if value != col_value {
                         matches_col = false;
@@ -203,6 +483,7 @@ impl PivotTable {
             }
 
             if matches_row && matches_col {
+                matched_rows += 1;
                 for field in &self.config.value_fields {
                     if let Some(value) = data_row.get(field) {
                         if let Ok(num) = value.parse::<f64>() {
@@ -215,10 +496,11 @@ impl PivotTable {
 
         match self.config.aggregation.as_str() {
             "sum" => values.iter().sum(),
-            "count" => values.len() as f64,
+            "count" => matched_rows as f64,
+            "count_numeric" => values.len() as f64,
             "average" => {
                 if values.is_empty() {
-                    0.0
+                    f64::NAN // Nothing to average: rendered as a blank cell
                 } else {
                     values.iter().sum::<f64>() / values.len() as f64
                 }
@@ -264,6 +546,11 @@ impl PivotTable {
             column_fields: vec!["Region".to_string()],
             value_fields: vec!["Sales".to_string()],
             aggregation: "sum".to_string(),
+            decimals: None,
+            filters: Vec::new(),
+            nan_placeholder: None,
+            thousands_separator: None,
+            decimal_separator: None,
         };
 
         serde_json::to_string(&config).unwrap_or_else(|_| "{}".to_string())
@@ -286,8 +573,194 @@ pub fn calculate_pivot_sum(data: &str, row_field: &str, col_field: &str, value_f
         column_fields: vec![col_field.to_string()],
         value_fields: vec![value_field.to_string()],
         aggregation: "sum".to_string(),
+        decimals: None,
+        filters: Vec::new(),
+        nan_placeholder: None,
+        thousands_separator: None,
+        decimal_separator: None,
     };
     
     pivot.set_config(&serde_json::to_string(&config).unwrap())?;
     pivot.generate_pivot()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pivot_with_sample(config_json: &str) -> PivotTable {
+        let mut pivot = PivotTable::new();
+        pivot.add_data(&pivot.get_sample_data()).unwrap();
+        pivot.set_config(config_json).unwrap();
+        pivot
+    }
+
+    #[test]
+    fn test_decimals_zero_formats_without_decimal_point() {
+        let pivot = pivot_with_sample(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"sum","decimals":0}"#);
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        assert_eq!(result.rows[0], vec!["Laptop", "1000", "1200", "2200"]);
+        assert!(result.rows.iter().flatten().all(|cell| !cell.contains('.')));
+    }
+
+    #[test]
+    fn test_count_formats_as_integer() {
+        let pivot = pivot_with_sample(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"count","decimals":3}"#);
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        assert_eq!(result.rows[0], vec!["Laptop", "1", "1", "2"]);
+        assert_eq!(result.rows.last().unwrap(), &vec!["Total", "2", "2", "4"]);
+    }
+
+    #[test]
+    fn test_add_data_keyed_replaces_matching_rows() {
+        let mut pivot = PivotTable::new();
+        let keys = r#"["Product","Region"]"#;
+        pivot.add_data_keyed(&pivot.get_sample_data(), keys).unwrap();
+        pivot.add_data_keyed(r#"[
+            {"Product":"Laptop","Region":"North","Sales":"1500"},
+            {"Product":"Tablet","Region":"North","Sales":"300"}
+        ]"#, keys).unwrap();
+        pivot.set_config(&pivot.get_sample_config()).unwrap();
+
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+        assert_eq!(result.rows[0], vec!["Laptop", "1500.00", "1200.00", "2700.00"]);
+        assert_eq!(result.rows[2][0], "Tablet");
+        assert_eq!(result.rows[2][1], "300.00");
+        assert_eq!(pivot.raw_data.data.len(), 5);
+    }
+
+    #[test]
+    fn test_generate_pivot_html() {
+        let pivot = pivot_with_sample(&PivotTable::new().get_sample_config());
+        let html = pivot.generate_pivot_html().unwrap();
+
+        assert!(html.starts_with("<table"));
+        assert!(html.contains("<thead>"));
+        assert_eq!(html.matches("<tr").count(), 4); // header + 2 products + totals
+        assert!(html.contains("<tr class=\"pivot-total-row\">\n      <td style=\"border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; font-weight: bold; background-color: #f3f4f6;\">Total</td>"));
+    }
+
+    fn pivot_with_non_numeric(aggregation: &str) -> PivotResult {
+        let mut pivot = PivotTable::new();
+        pivot.add_data(r#"[
+            {"Product":"Laptop","Region":"North","Sales":"1000"},
+            {"Product":"Laptop","Region":"North","Sales":"n/a"},
+            {"Product":"Laptop","Region":"North"},
+            {"Product":"Laptop","Region":"South","Sales":"500"}
+        ]"#).unwrap();
+        pivot.set_config(&format!(
+            r#"{{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"{}"}}"#,
+            aggregation
+        )).unwrap();
+        serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_count_includes_non_numeric_rows() {
+        let result = pivot_with_non_numeric("count");
+        assert_eq!(result.rows[0], vec!["Laptop", "3", "1", "4"]);
+    }
+
+    #[test]
+    fn test_filter_excludes_region_before_aggregation() {
+        let pivot = pivot_with_sample(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"sum","filters":[{"field":"Region","op":"neq","value":"North"}]}"#);
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        assert_eq!(result.headers, vec!["", "South", "Total"]);
+        assert_eq!(result.rows[0], vec!["Laptop", "1200.00", "1200.00"]);
+    }
+
+    #[test]
+    fn test_numeric_filter_uses_tolerant_parser() {
+        let pivot = pivot_with_sample(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"sum","filters":[{"field":"Sales","op":"gt","value":" 1,000 "}]}"#);
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0], vec!["Laptop", "1200.00", "1200.00"]);
+    }
+
+    #[test]
+    fn test_two_column_fields_give_composite_headers() {
+        let mut pivot = PivotTable::new();
+        pivot.add_data(r#"[
+            {"Region":"North","Year":"2023","Quarter":"Q1","Sales":"100"},
+            {"Region":"North","Year":"2024","Quarter":"Q1","Sales":"400"},
+            {"Region":"North","Year":"2023","Quarter":"Q2","Sales":"200"},
+            {"Region":"South","Year":"2023","Quarter":"Q1","Sales":"50"},
+            {"Region":"South","Year":"2023","Quarter":"Q1","Sales":"25"},
+            {"Region":"South","Year":"2023","Quarter":"Q2","Sales":"10"},
+            {"Region":"South","Year":"2024","Quarter":"Q1","Sales":"40"}
+        ]"#).unwrap();
+        pivot.set_config(r#"{"row_fields":["Region"],"column_fields":["Year","Quarter"],"value_fields":["Sales"],"aggregation":"sum","decimals":0}"#).unwrap();
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        // Grouped by year even though 2024 was seen before 2023 > Q2
+        assert_eq!(result.headers, vec!["", "2023 > Q1", "2023 > Q2", "2024 > Q1", "Total"]);
+        assert_eq!(result.rows[0], vec!["North", "100", "200", "400", "700"]);
+        assert_eq!(result.rows[1], vec!["South", "75", "10", "40", "125"]);
+        assert_eq!(result.rows[2], vec!["Total", "175", "210", "440", "825"]);
+
+        let html = pivot.generate_pivot_html().unwrap();
+        assert_eq!(html.matches("<tr").count(), 5); // 2 header levels + 2 regions + totals
+        assert!(html.contains("colspan=\"2\" style=\"border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; font-weight: bold; background-color: #f3f4f6;\">2023</th>"));
+        assert!(html.contains(">Q2</th>"));
+        assert!(html.contains("rowspan=\"2\""));
+    }
+
+    #[test]
+    fn test_empty_average_renders_blank_cell() {
+        let mut pivot = PivotTable::new();
+        pivot.add_data(r#"[
+            {"Product":"Laptop","Region":"North","Sales":"1000"},
+            {"Product":"Laptop","Region":"East","Sales":"n/a"},
+            {"Product":"Phone","Region":"East","Sales":"300"}
+        ]"#).unwrap();
+        pivot.set_config(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"average"}"#).unwrap();
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+
+        assert_eq!(result.rows[0], vec!["Laptop", "1000.00", "", "1000.00"]);
+        assert!(result.rows.iter().flatten().all(|cell| !cell.contains("NaN") && !cell.contains("inf")));
+        assert_eq!(result.rows[2], vec!["Total", "1000.00", "300.00", "1300.00"]);
+
+        pivot.set_config(r#"{"row_fields":["Product"],"column_fields":["Region"],"value_fields":["Sales"],"aggregation":"min","nan_placeholder":"-"}"#).unwrap();
+        let result: PivotResult = serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap();
+        assert_eq!(result.rows[1], vec!["Phone", "-", "300.00", "300.00"]);
+    }
+
+    #[test]
+    fn test_locale_separators_us_and_eu() {
+        let data = r#"[{"Region":"North","Year":"2024","Sales":"1234.5"},{"Region":"North","Year":"2024","Sales":"1000000"}]"#;
+        let format_with = |separators: &str| -> PivotResult {
+            let mut pivot = PivotTable::new();
+            pivot.add_data(data).unwrap();
+            pivot.set_config(&format!(r#"{{"row_fields":["Region"],"column_fields":["Year"],"value_fields":["Sales"],"aggregation":"sum"{}}}"#, separators)).unwrap();
+            serde_json::from_str(&pivot.generate_pivot().unwrap()).unwrap()
+        };
+
+        let us = format_with(r#","thousands_separator":",","decimal_separator":".""#);
+        assert_eq!(us.rows[0][1], "1,001,234.50");
+        let eu = format_with(r#","thousands_separator":".","decimal_separator":",""#);
+        assert_eq!(eu.rows[0][1], "1.001.234,50");
+        assert_eq!(eu.totals, vec![1001234.5, 1001234.5]); // Raw values are kept for calculations
+
+        assert_eq!(format_with("").rows[0][1], "1001234.50");
+        assert_eq!(localize_number("-1234", " ", ","), "-1 234");
+    }
+
+    #[test]
+    fn test_format_number_drops_negative_zero_sign() {
+        assert_eq!(format_number(-0.0, 2, ""), "0.00");
+        assert_eq!(format_number(-0.001, 2, ""), "0.00");
+        assert_eq!(format_number(-0.5, 0, ""), "0");
+        assert_eq!(format_number(-1.5, 1, ""), "-1.5");
+        assert_eq!(format_number(f64::INFINITY, 2, "n/a"), "n/a");
+    }
+
+    #[test]
+    fn test_count_numeric_skips_non_numeric_rows() {
+        let result = pivot_with_non_numeric("count_numeric");
+        assert_eq!(result.rows[0], vec!["Laptop", "1", "1", "2"]);
+    }
+}
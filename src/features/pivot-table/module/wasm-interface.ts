@@ -4,11 +4,22 @@ export interface PivotConfig {
   row_fields: string[];
   column_fields: string[];
   value_fields: string[];
-  aggregation: 'sum' | 'count' | 'average' | 'max' | 'min';
+  aggregation: 'sum' | 'count' | 'count_numeric' | 'average' | 'max' | 'min';
+  decimals?: number;
+  filters?: PivotFilter[];
+  nan_placeholder?: string;
+  thousands_separator?: string; // e.g. "," (US) or "." (European)
+  decimal_separator?: string; // Default "."
+}
+
+export interface PivotFilter {
+  field: string;
+  op: 'eq' | 'neq' | 'gt' | 'lt' | 'contains';
+  value: string | number;
 }
 
 export interface PivotResult {
-  headers: string[];
+  headers: string[]; // Several column fields give composite headers, e.g. "2023 > Q1"
   rows: string[][];
   totals: number[];
 }
@@ -25,8 +36,10 @@ export interface PivotData {
 export interface PivotTableWasm {
   // Methods
   add_data(data_json: string): void;
+  add_data_keyed(data_json: string, key_fields_json: string): void;
   set_config(config_json: string): void;
   generate_pivot(): string;
+  generate_pivot_html(): string;
   get_sample_data(): string;
   get_sample_config(): string;
 }
@@ -88,7 +88,11 @@ export interface UseWasmEngineReturn {
     panX: number,
     panY: number
   ) => { zoom: number; pan_x: number; pan_y: number } | null;
-  zoomToPoint: (screenX: number, screenY: number, zoomDelta: number) => number;
+  zoomToPoint: (
+    screenX: number,
+    screenY: number,
+    zoomDelta: number
+  ) => { zoom: number; pan_x: number; pan_y: number } | null;
 
   // Spatial Queries
   getElementsInRegion: (
@@ -493,12 +497,9 @@ export const useWasmEngine = (
   );
 
   const zoomToPoint = useCallback(
-    (screenX: number, screenY: number, zoomDelta: number): number => {
-      return (
-        safeCall(
-          () => engineRef.current!.zoomToPoint(screenX, screenY, zoomDelta),
-          1.0
-        ) || 1.0
+    (screenX: number, screenY: number, zoomDelta: number) => {
+      return safeCall(() =>
+        engineRef.current!.zoomToPoint(screenX, screenY, zoomDelta)
       );
     },
     [safeCall]
@@ -494,12 +494,12 @@ const WasmHtmlBuilder: React.FC<WasmHtmlBuilderProps> = ({
                 }}
                 onZoomToPoint={(screenX, screenY, zoomDelta) => {
                   if (wasmEngine.state.isLoaded) {
-                    const newZoom = wasmEngine.zoomToPoint(
+                    const transform = wasmEngine.zoomToPoint(
                       screenX,
                       screenY,
                       zoomDelta,
                     );
-                    setCurrentZoom(newZoom);
+                    setCurrentZoom(transform?.zoom ?? 1.0);
                   }
                 }}
                 currentZoom={currentZoom}
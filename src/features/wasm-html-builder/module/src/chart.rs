@@ -0,0 +1,167 @@
+use crate::types::Element;
+use crate::utils;
+
+/// Chart kinds a chart element can draw
+pub const CHART_KINDS: [&str; 3] = ["bar", "line", "pie"];
+
+/// Share of each bar slot taken by the bar itself
+const BAR_WIDTH_RATIO: f64 = 0.8;
+
+/// Chart element content: `{"sourceTableId": "...", "column": 1, "kind": "bar"}`
+struct ChartSpec {
+    source_table_id: String,
+    column: usize,
+    kind: String,
+}
+
+fn parse_content(content: &str) -> Option<ChartSpec> {
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    let kind = value.get("kind").and_then(|v| v.as_str()).unwrap_or("bar").to_lowercase();
+    if !CHART_KINDS.contains(&kind.as_str()) {
+        return None;
+    }
+    Some(ChartSpec {
+        source_table_id: value.get("sourceTableId")?.as_str()?.to_string(),
+        column: value.get("column").and_then(|v| v.as_u64()).unwrap_or(0) as usize,
+        kind,
+    })
+}
+
+/// Table a chart element reads from, or `None` while the chart is not connected
+pub fn source_table_id(content: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    let source_table_id = value.get("sourceTableId")?.as_str()?;
+    Some(source_table_id.to_string()).filter(|id| !id.is_empty())
+}
+
+/// Chart content pointed at another source table, other settings kept
+pub fn with_source_table_id(content: &str, source_table_id: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(mut value) if value.is_object() => {
+            value["sourceTableId"] = serde_json::Value::String(source_table_id.to_string());
+            value.to_string()
+        }
+        _ => content.to_string(),
+    }
+}
+
+/// Numeric cells of a table column, skipping header rows, merged placeholders and computed totals
+pub fn column_values(table: &Element, column: usize) -> Vec<f64> {
+    let Some(table_data) = &table.table_data else {
+        return Vec::new();
+    };
+    table_data
+        .rows
+        .iter()
+        .skip(table_data.header_rows)
+        .filter_map(|row| row.cells.get(column))
+        .filter(|cell| !cell.computed && (cell.row_span != 0 || cell.col_span != 0))
+        .filter_map(|cell| cell.content.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .collect()
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Inline SVG for a chart element, read from its source table's current cells,
+/// or `None` when the table, column or numeric data is missing
+pub fn chart_svg(content: &str, elements: &[Element]) -> Option<String> {
+    let spec = parse_content(content)?;
+    let table = elements
+        .iter()
+        .find(|element| element.id == spec.source_table_id && element.element_type == "table")?;
+    let values = column_values(table, spec.column);
+    if values.is_empty() {
+        return None;
+    }
+
+    let svg = match spec.kind.as_str() {
+        "line" => line_svg(&values),
+        "pie" => pie_svg(&values)?,
+        _ => bar_svg(&values),
+    };
+    Some(svg)
+}
+
+/// Bars share a 100x100 view box; heights are proportional to the largest value
+fn bar_svg(values: &[f64]) -> String {
+    let max = values.iter().cloned().fold(0.0_f64, f64::max);
+    let slot = 100.0 / values.len() as f64;
+    let width = slot * BAR_WIDTH_RATIO;
+    let color = &utils::generate_palette(1)[0];
+
+    let mut svg = String::from(
+        "<svg class=\"chart chart-bar\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 100 100\" width=\"100%\" height=\"100%\" preserveAspectRatio=\"none\">",
+    );
+    for (index, value) in values.iter().enumerate() {
+        // Negative values have no bar
+        let height = if max > 0.0 { value.max(0.0) / max * 100.0 } else { 0.0 };
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            round2(index as f64 * slot + (slot - width) / 2.0),
+            round2(100.0 - height),
+            round2(width),
+            round2(height),
+            color
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Polyline through the values, scaled between the smallest and largest value
+fn line_svg(values: &[f64]) -> String {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    let step = if values.len() > 1 { 100.0 / (values.len() - 1) as f64 } else { 0.0 };
+
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let y = if range > 0.0 { 100.0 - (value - min) / range * 100.0 } else { 50.0 };
+            format!("{},{}", round2(index as f64 * step), round2(y))
+        })
+        .collect();
+    format!(
+        "<svg class=\"chart chart-line\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 100 100\" width=\"100%\" height=\"100%\" preserveAspectRatio=\"none\"><polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\"/></svg>",
+        points.join(" "),
+        utils::generate_palette(1)[0]
+    )
+}
+
+/// One slice per positive value; `None` when nothing is positive
+fn pie_svg(values: &[f64]) -> Option<String> {
+    let total: f64 = values.iter().filter(|value| **value > 0.0).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let colors = utils::generate_palette(values.len());
+
+    let mut svg = String::from(
+        "<svg class=\"chart chart-pie\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 100 100\" width=\"100%\" height=\"100%\">",
+    );
+    let point = |angle: f64| (round2(50.0 + 50.0 * angle.sin()), round2(50.0 - 50.0 * angle.cos()));
+    let mut start = 0.0_f64;
+    for (value, color) in values.iter().zip(&colors).filter(|(value, _)| **value > 0.0) {
+        let sweep = value / total * std::f64::consts::TAU;
+        if sweep >= std::f64::consts::TAU - f64::EPSILON {
+            // A single slice is the whole circle, which an arc cannot draw
+            svg.push_str(&format!("<circle cx=\"50\" cy=\"50\" r=\"50\" fill=\"{}\"/>", color));
+        } else {
+            let (x1, y1) = point(start);
+            let (x2, y2) = point(start + sweep);
+            let large_arc = u8::from(sweep > std::f64::consts::PI);
+            svg.push_str(&format!(
+                "<path d=\"M50,50 L{},{} A50,50 0 {} 1 {},{} Z\" fill=\"{}\"/>",
+                x1, y1, large_arc, x2, y2, color
+            ));
+        }
+        start += sweep;
+    }
+    svg.push_str("</svg>");
+    Some(svg)
+}
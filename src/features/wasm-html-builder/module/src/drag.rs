@@ -1,5 +1,6 @@
 use std::sync::{Mutex, Arc};
 use crate::types::*;
+use crate::utils;
 
 /// Simple drag state
 #[derive(Debug, Clone)]
@@ -7,32 +8,68 @@ pub struct DragState {
     pub element_id: String,
     pub offset_x: f64,
     pub offset_y: f64,
+    pub start_x: f64, // Element position when the drag started
+    pub start_y: f64,
+}
+
+/// Axis a drag is constrained to (e.g. while Shift is held)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DragAxisLock {
+    #[default]
+    None,
+    X, // Horizontal movement only
+    Y, // Vertical movement only
 }
 
 /// Drag management module
 pub struct DragManager {
     pub drag_state: Mutex<Option<DragState>>,
+    axis_lock: Mutex<DragAxisLock>,
 }
 
 impl DragManager {
     pub fn new() -> Self {
         Self {
             drag_state: std::sync::Mutex::new(None),
+            axis_lock: Mutex::new(DragAxisLock::None),
+        }
+    }
+
+    /// ล็อกการ drag ให้เคลื่อนที่แกนเดียว: "none", "x" หรือ "y" (เปลี่ยนได้ระหว่าง drag)
+    pub fn set_drag_axis_lock(&self, axis: &str) -> bool {
+        let axis_lock = match axis {
+            "none" => DragAxisLock::None,
+            "x" => DragAxisLock::X,
+            "y" => DragAxisLock::Y,
+            _ => return false,
+        };
+        *utils::lock(&self.axis_lock) = axis_lock;
+        true
+    }
+
+    /// แกนที่ล็อกการ drag อยู่: "none", "x" หรือ "y"
+    pub fn drag_axis_lock(&self) -> &'static str {
+        match *utils::lock(&self.axis_lock) {
+            DragAxisLock::None => "none",
+            DragAxisLock::X => "x",
+            DragAxisLock::Y => "y",
         }
     }
 
     /// เริ่ม drag operation (working implementation)
     pub fn start_drag(&self, element_id: &str, mouse_x: f64, mouse_y: f64, elements: &Arc<Mutex<Vec<Element>>>) -> bool {
-        let elements_guard = elements.lock().unwrap();
+        let elements_guard = utils::lock(elements);
         if let Some(element) = elements_guard.iter().find(|e| e.id == element_id) {
             let offset_x = mouse_x - element.x;
             let offset_y = mouse_y - element.y;
             
-            let mut drag_state = self.drag_state.lock().unwrap();
+            let mut drag_state = utils::lock(&self.drag_state);
             *drag_state = Some(DragState {
                 element_id: element_id.to_string(),
                 offset_x,
                 offset_y,
+                start_x: element.x,
+                start_y: element.y,
             });
             
             true
@@ -43,11 +80,14 @@ impl DragManager {
 
     /// อัพเดท drag operation (working implementation)
     pub fn update_drag(&self, mouse_x: f64, mouse_y: f64, _zoom: f64, _pan_x: f64, _pan_y: f64, element_manager: &crate::element::ElementManager) -> String {
-        let drag_state = self.drag_state.lock().unwrap();
+        let drag_state = utils::lock(&self.drag_state);
         
         if let Some(ref drag) = *drag_state {
-            let new_x = mouse_x - drag.offset_x;
-            let new_y = mouse_y - drag.offset_y;
+            let (new_x, new_y) = match *utils::lock(&self.axis_lock) {
+                DragAxisLock::None => (mouse_x - drag.offset_x, mouse_y - drag.offset_y),
+                DragAxisLock::X => (mouse_x - drag.offset_x, drag.start_y),
+                DragAxisLock::Y => (drag.start_x, mouse_y - drag.offset_y),
+            };
             
             // อัพเดทตำแหน่ง element
             let success = element_manager.update_element_position(&drag.element_id, new_x, new_y);
@@ -66,7 +106,7 @@ impl DragManager {
 
     /// จบ drag operation (working implementation)
     pub fn end_drag(&self) -> bool {
-        let mut drag_state = self.drag_state.lock().unwrap();
+        let mut drag_state = utils::lock(&self.drag_state);
         let was_dragging = drag_state.is_some();
         *drag_state = None;
         
@@ -85,19 +125,45 @@ impl DragManager {
 
     /// ได้ drag state ปัจจุบัน
     pub fn get_drag_state(&self) -> Option<DragState> {
-        let drag_state = self.drag_state.lock().unwrap();
+        let drag_state = utils::lock(&self.drag_state);
         drag_state.clone()
     }
 
     /// ตรวจสอบว่ากำลัง drag อยู่หรือไม่
     pub fn is_dragging(&self) -> bool {
-        let drag_state = self.drag_state.lock().unwrap();
+        let drag_state = utils::lock(&self.drag_state);
         drag_state.is_some()
     }
 
     /// Clear drag state
     pub fn clear(&self) {
-        let mut drag_state = self.drag_state.lock().unwrap();
+        let mut drag_state = utils::lock(&self.drag_state);
         *drag_state = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::ElementManager;
+
+    #[test]
+    fn test_axis_lock_constrains_drag() {
+        let element_manager = ElementManager::new();
+        let element = element_manager.create("button", 100.0, 100.0);
+        let drag_manager = DragManager::new();
+
+        assert!(drag_manager.start_drag(&element.id, 110.0, 110.0, &Arc::new(Mutex::new(element_manager.all()))));
+        assert!(drag_manager.set_drag_axis_lock("x"));
+        drag_manager.update_drag(160.0, 190.0, 1.0, 0.0, 0.0, &element_manager);
+        let moved = element_manager.find(&element.id).unwrap();
+        assert_eq!((moved.x, moved.y), (150.0, 100.0));
+
+        assert!(drag_manager.set_drag_axis_lock("none"));
+        drag_manager.update_drag(160.0, 190.0, 1.0, 0.0, 0.0, &element_manager);
+        let moved = element_manager.find(&element.id).unwrap();
+        assert_eq!((moved.x, moved.y), (150.0, 180.0));
+
+        assert!(!drag_manager.set_drag_axis_lock("z"));
+    }
+}
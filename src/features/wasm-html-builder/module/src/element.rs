@@ -1,40 +1,185 @@
+use serde::{Deserialize, Serialize};
 use serde_json;
 use std::sync::{Mutex, MutexGuard, Arc};
+use std::collections::HashMap;
+use crate::chart;
 use crate::types::*;
+use crate::utils;
+
+/// Upper bound on deleted elements kept for reuse
+const MAX_POOLED_ELEMENTS: usize = 256;
+
+/// Position/size difference (px) under which two elements count as duplicates
+const DUPLICATE_TOLERANCE: f64 = 1.0;
+
+/// Separation passes `resolve_overlaps` makes before giving up on a crowded set
+const MAX_PUSH_PASSES: usize = 200;
+
+/// Gap (px) between cells when `resolve_overlaps` lays elements out as a grid
+const RESOLVE_GRID_GAP: f64 = 16.0;
+
+/// Smallest side (px) of a refitted line box, the default line thickness
+const MIN_LINE_BOX_SIZE: f64 = 2.0;
+
+/// Identifies a `copy_elements` payload
+const CLIPBOARD_FORMAT: &str = "york-elements";
+
+/// Version written by `copy_elements`
+const CLIPBOARD_VERSION: u32 = 1;
+
+/// Portable copy of elements, readable by any engine instance
+#[derive(Serialize, Deserialize)]
+struct ClipboardPayload {
+    format: String,
+    version: u32,
+    elements: Vec<Element>,
+}
+
+/// Free list of deleted elements, reused by `create` when pooling is enabled
+#[derive(Default)]
+struct ElementPool {
+    enabled: bool,
+    free: Vec<Element>,
+}
 
 /// Element management module
 pub struct ElementManager {
     elements: Arc<Mutex<Vec<Element>>>,
+    id_counter: Mutex<u64>,
+    pool: Mutex<ElementPool>,
+    size_snap: Mutex<f64>, // Resize increment in px, 0 = off
+    size_match_tolerance: Mutex<f64>, // Snap to another element's width/height within this many px, 0 = off
 }
 
 impl ElementManager {
     pub fn new() -> Self {
+        Self::new_with_data(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    pub fn new_with_data(elements: Arc<Mutex<Vec<Element>>>) -> Self {
         Self {
-            elements: Arc::new(Mutex::new(Vec::new())),
+            elements,
+            id_counter: Mutex::new(0),
+            pool: Mutex::new(ElementPool::default()),
+            size_snap: Mutex::new(0.0),
+            size_match_tolerance: Mutex::new(0.0),
         }
     }
 
-    pub fn new_with_data(elements: Arc<Mutex<Vec<Element>>>) -> Self {
-        Self { elements }
+    /// Round resized widths/heights to a multiple of `increment` (0 disables)
+    pub fn set_size_snap(&self, increment: f64) -> bool {
+        if !increment.is_finite() || increment < 0.0 {
+            return false;
+        }
+        *utils::lock(&self.size_snap) = increment;
+        true
+    }
+
+    /// Snap resized widths/heights to another element's exact size when within `tolerance` px (0 disables)
+    pub fn set_size_match_tolerance(&self, tolerance: f64) -> bool {
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return false;
+        }
+        *utils::lock(&self.size_match_tolerance) = tolerance;
+        true
+    }
+
+    /// Current resize increment (0 = off)
+    pub fn size_snap(&self) -> f64 {
+        *utils::lock(&self.size_snap)
+    }
+
+    /// Current size-match tolerance (0 = off)
+    pub fn size_match_tolerance(&self) -> f64 {
+        *utils::lock(&self.size_match_tolerance)
+    }
+
+    /// Reuse deleted element allocations for new elements (disabling drops the free list)
+    pub fn set_element_pooling(&self, enabled: bool) {
+        let mut pool = utils::lock(&self.pool);
+        pool.enabled = enabled;
+        if !enabled {
+            pool.free = Vec::new();
+        }
+    }
+
+    fn recycle(&self, element: Element) {
+        let mut pool = utils::lock(&self.pool);
+        if pool.enabled && pool.free.len() < MAX_POOLED_ELEMENTS {
+            pool.free.push(element);
+        }
+    }
+
+    /// Generate an element ID that is unique within this engine instance
+    fn generate_element_id(&self, elements: &[Element]) -> String {
+        let timestamp = utils::now_ms() as u64;
+        let mut counter = utils::lock(&self.id_counter);
+        loop {
+            *counter += 1;
+            let element_id = format!("element-{}-{}", timestamp, *counter);
+            if !elements.iter().any(|e| e.id == element_id) {
+                return element_id;
+            }
+        }
     }
 
     /// สร้าง element ใหม่ (working implementation with unique IDs)
-    pub fn create_element(&self, component_type: &str, x: f64, y: f64) -> String {
-        let mut elements = self.elements.lock().unwrap();
+    pub fn create(&self, component_type: &str, x: f64, y: f64) -> Element {
+        let mut elements = utils::lock(&self.elements);
         
-        // Generate unique ID using timestamp and random number
-        let timestamp = js_sys::Date::now() as u64;
-        let random = (js_sys::Math::random() * 1000000.0) as u64;
-        let element_id = format!("element-{}-{}", timestamp, random);
-        
-        let mut element = Element::new(
-            element_id,
-            component_type.to_string(),
-            component_type.to_string(),
-        );
+        // Generate unique ID using timestamp and a monotonic counter
+        let element_id = self.generate_element_id(&elements);
+        self.insert_new(&mut elements, element_id, component_type, x, y)
+    }
+
+    /// สร้าง element ใหม่ด้วย id ที่กำหนด (None ถ้า id ว่างหรือซ้ำ)
+    pub fn create_with_id(&self, element_id: &str, component_type: &str, x: f64, y: f64) -> Option<Element> {
+        let mut elements = utils::lock(&self.elements);
+        if element_id.is_empty() || elements.iter().any(|e| e.id == element_id) {
+            return None;
+        }
+        Some(self.insert_new(&mut elements, element_id.to_string(), component_type, x, y))
+    }
+
+    /// สร้างตารางขนาด rows x cols (รวมแถว header) ขนาด element ตามแถวและคอลัมน์ (None ถ้า rows หรือ cols เป็น 0)
+    pub fn create_table(&self, rows: u32, cols: u32, x: f64, y: f64) -> Option<Element> {
+        if rows == 0 || cols == 0 {
+            return None;
+        }
+        let mut elements = utils::lock(&self.elements);
+        let element_id = self.generate_element_id(&elements);
+        let mut element = self.insert_new(&mut elements, element_id, "table", x, y);
+
+        element.create_default_table(rows, cols);
+        if let Some(table_data) = &element.table_data {
+            let total_width: f64 = table_data.column_widths.iter().sum();
+            let total_height: f64 = table_data.rows.iter().map(|row| row.height.max(20.0)).sum();
+            element.width = (total_width + 32.0).max(200.0);
+            element.height = (total_height + 32.0).max(100.0);
+        }
+        if let Some(stored) = elements.iter_mut().find(|stored| stored.id == element.id) {
+            *stored = element.clone();
+        }
+        Some(element)
+    }
+
+    fn insert_new(&self, elements: &mut Vec<Element>, element_id: String, component_type: &str, x: f64, y: f64) -> Element {
+        let pooled = utils::lock(&self.pool).free.pop();
+        let mut element = match pooled {
+            Some(mut element) => {
+                element.reset(&element_id, component_type);
+                element
+            }
+            None => Element::new(
+                element_id,
+                component_type.to_string(),
+                component_type.to_string(),
+            ),
+        };
         
         element.x = x;
         element.y = y;
+        element.created_at = utils::now_ms();
         
         // Initialize table data for table elements
         if component_type == "table" {
@@ -43,16 +188,21 @@ impl ElementManager {
         
         elements.push(element.clone());
         
-        serde_json::to_string(&element).unwrap_or_else(|_| "{}".to_string())
+        element
     }
 
     /// อัพเดทตำแหน่ง element (working implementation)
     pub fn update_element_position(&self, element_id: &str, x: f64, y: f64) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        if !utils::all_finite(&[x, y]) {
+            return false;
+        }
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id {
                 element.x = x;
                 element.y = y;
+                // A manual move detaches the element from its percentage layout
+                element.relative_layout = None;
                 return true;
             }
         }
@@ -61,11 +211,34 @@ impl ElementManager {
 
     /// อัพเดท element size (working implementation)
     pub fn update_element_size(&self, element_id: &str, width: f64, height: f64) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        if !utils::all_finite(&[width, height]) {
+            return false;
+        }
+        let increment = *utils::lock(&self.size_snap);
+        let tolerance = *utils::lock(&self.size_match_tolerance);
+        let mut elements = utils::lock(&self.elements);
+
+        // Matching another element's size takes precedence over the grid increment
+        let snap = |value: f64, matched: Option<f64>| match matched {
+            Some(matched) => matched,
+            None if increment > 0.0 => utils::snap_to_grid(value, increment).max(increment),
+            None => value,
+        };
+        let (matched_width, matched_height) = if tolerance > 0.0 {
+            let others: Vec<&Element> = elements.iter().filter(|e| e.id != element_id).collect();
+            (
+                closest_within(width, others.iter().map(|e| e.width), tolerance),
+                closest_within(height, others.iter().map(|e| e.height), tolerance),
+            )
+        } else {
+            (None, None)
+        };
+        let (width, height) = (snap(width, matched_width), snap(height, matched_height));
+
         for element in elements.iter_mut() {
             if element.id == element_id {
-                element.set_width(width);
-                element.set_height(height);
+                element.set_size(width, height);
+                element.relative_layout = None;
                 return true;
             }
         }
@@ -74,7 +247,7 @@ impl ElementManager {
 
     /// อัพเดท element content
     pub fn update_element_content(&self, element_id: &str, content: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id {
                 element.content = content.to_string();
@@ -84,150 +257,887 @@ impl ElementManager {
         false
     }
 
+    /// เชื่อม target ให้รับ content ของ source ทุกครั้งที่ source ถูกอัพเดท (false ถ้าไม่พบ element หรือทำให้เกิดวงวน)
+    pub fn link_elements(&self, source_id: &str, target_id: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if source_id == target_id || !elements.iter().any(|element| element.id == source_id) {
+            return false;
+        }
+        // Following the source's own links back must not reach the target
+        let mut upstream = Some(source_id.to_string());
+        let mut visited = Vec::new();
+        while let Some(id) = upstream {
+            if id == target_id || visited.contains(&id) {
+                return false;
+            }
+            upstream = elements.iter().find(|element| element.id == id).and_then(|element| element.linked_from.clone());
+            visited.push(id);
+        }
+        match elements.iter_mut().find(|element| element.id == target_id) {
+            Some(target) => {
+                target.linked_from = Some(source_id.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ยกเลิกการเชื่อม content ของ target
+    pub fn unlink_element(&self, target_id: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == target_id && element.linked_from.is_some()) {
+            Some(target) => {
+                target.linked_from = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// คัดลอก content ของ source ไปยัง elements ที่เชื่อมไว้ (ต่อเป็นทอด ๆ) คืน ids ที่ถูกอัพเดท
+    pub fn propagate_linked_content(&self, source_id: &str) -> Vec<String> {
+        let mut elements = utils::lock(&self.elements);
+        let mut updated: Vec<String> = Vec::new();
+        let mut pending = vec![source_id.to_string()];
+        while let Some(current_id) = pending.pop() {
+            let Some(value) = elements.iter().find(|element| element.id == current_id).map(Self::mirrored_value) else {
+                continue;
+            };
+            for target in elements.iter_mut() {
+                if target.linked_from.as_deref() == Some(current_id.as_str()) && target.id != source_id && !updated.contains(&target.id) {
+                    Self::apply_slot_content(target, &value);
+                    updated.push(target.id.clone());
+                    pending.push(target.id.clone());
+                }
+            }
+        }
+        updated
+    }
+
+    /// Value a linked element receives: a form field's value, otherwise the content itself
+    fn mirrored_value(element: &Element) -> String {
+        if element.element_type == "form_field" {
+            serde_json::from_str::<serde_json::Value>(&element.content)
+                .ok()
+                .and_then(|data| data.get("value").and_then(|value| value.as_str()).map(str::to_string))
+                .unwrap_or_default()
+        } else {
+            element.content.clone()
+        }
+    }
+
     /// อัพเดท element style
     pub fn update_element_style(&self, element_id: &str, style_json: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id {
                 // Parse partial style update
                 if let Ok(style_update) = serde_json::from_str::<serde_json::Value>(style_json) {
-                    let mut updated = false;
-                    if let Some(font_size) = style_update.get("fontSize").and_then(|v| v.as_f64()) {
-                        element.style.font_size = font_size;
-                        updated = true;
-                    }
-                    if let Some(font_family) = style_update.get("fontFamily").and_then(|v| v.as_str()) {
-                        element.style.font_family = font_family.to_string();
-                        updated = true;
-                    }
-                    if let Some(font_weight) = style_update.get("fontWeight").and_then(|v| v.as_str()) {
-                        element.style.font_weight = font_weight.to_string();
-                        updated = true;
-                    }
-                    if let Some(font_style) = style_update.get("fontStyle").and_then(|v| v.as_str()) {
-                        element.style.font_style = font_style.to_string();
-                        updated = true;
-                    }
-                    if let Some(color) = style_update.get("color").and_then(|v| v.as_str()) {
-                        element.style.color = color.to_string();
-                        updated = true;
-                    }
-                    if let Some(bg_color) = style_update.get("backgroundColor").and_then(|v| v.as_str()) {
-                        element.style.background_color = bg_color.to_string();
-                        updated = true;
-                    }
-                    if let Some(text_align) = style_update.get("textAlign").and_then(|v| v.as_str()) {
-                        element.style.text_align = text_align.to_string();
-                        updated = true;
-                    }
-                    if let Some(padding) = style_update.get("padding").and_then(|v| v.as_f64()) {
-                        element.style.padding = padding;
-                        updated = true;
-                    }
-                    if let Some(border_radius) = style_update.get("borderRadius").and_then(|v| v.as_f64()) {
-                        element.style.border_radius = border_radius;
-                        updated = true;
-                    }
-                    if let Some(border_width) = style_update.get("borderWidth").and_then(|v| v.as_f64()) {
-                        element.style.border_width = border_width;
-                        updated = true;
-                    }
-                    if let Some(border_color) = style_update.get("borderColor").and_then(|v| v.as_str()) {
-                        element.style.border_color = border_color.to_string();
-                        updated = true;
+                    return Self::apply_style_update(element, &style_update);
+                } else {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    /// อัพเดท style เดียวกันให้หลาย elements ภายใต้ lock เดียว คืนค่า IDs ที่อัพเดทสำเร็จ
+    pub fn batch_update_style(&self, ids_json: &str, style_json: &str) -> Vec<String> {
+        let (element_ids, style_update) = match (
+            serde_json::from_str::<Vec<String>>(ids_json),
+            serde_json::from_str::<serde_json::Value>(style_json),
+        ) {
+            (Ok(element_ids), Ok(style_update)) => (element_ids, style_update),
+            _ => return Vec::new(),
+        };
+
+        let mut elements = utils::lock(&self.elements);
+        let mut updated_ids = Vec::new();
+        for element in elements.iter_mut() {
+            if element_ids.contains(&element.id) && Self::apply_style_update(element, &style_update) {
+                updated_ids.push(element.id.clone());
+            }
+        }
+        updated_ids
+    }
+
+    /// แยก elements ที่ทับกัน: "push" ดันออกจากกันด้วยระยะน้อยที่สุด, "grid" จัดเป็นตาราง คืนตำแหน่งใหม่ (id, x, y)
+    pub fn resolve_overlaps(&self, ids_json: &str, strategy: &str) -> Vec<(String, f64, f64)> {
+        let Ok(element_ids) = serde_json::from_str::<Vec<String>>(ids_json) else {
+            return Vec::new();
+        };
+        match strategy {
+            "grid" => {
+                let (start_x, start_y) = {
+                    let elements = utils::lock(&self.elements);
+                    let listed: Vec<&Element> = elements.iter().filter(|e| element_ids.contains(&e.id)).collect();
+                    if listed.is_empty() {
+                        return Vec::new();
                     }
-                    
-                    // Fill style updates
-                    if let Some(fill_update) = style_update.get("fill") {
-                        if let Some(fill_color) = fill_update.get("color").and_then(|v| v.as_str()) {
-                            element.style.fill.color = fill_color.to_string();
-                            updated = true;
-                        }
-                        if let Some(fill_opacity) = fill_update.get("opacity").and_then(|v| v.as_f64()) {
-                            element.style.fill.opacity = fill_opacity;
-                            updated = true;
-                        }
-                        if let Some(fill_enabled) = fill_update.get("enabled").and_then(|v| v.as_bool()) {
-                            element.style.fill.enabled = fill_enabled;
-                            updated = true;
+                    (
+                        listed.iter().map(|e| e.x).fold(f64::INFINITY, f64::min),
+                        listed.iter().map(|e| e.y).fold(f64::INFINITY, f64::min),
+                    )
+                };
+                // Roughly square grid anchored at the group's top-left corner
+                let cols = (element_ids.len() as f64).sqrt().ceil() as usize;
+                let moved = self.arrange_grid(ids_json, cols, RESOLVE_GRID_GAP, start_x, start_y);
+                let elements = utils::lock(&self.elements);
+                moved
+                    .into_iter()
+                    .filter_map(|id| elements.iter().find(|e| e.id == id).map(|e| (id.clone(), e.x, e.y)))
+                    .collect()
+            }
+            "push" => {
+                let mut elements = utils::lock(&self.elements);
+                let mut indexes: Vec<usize> = Vec::new();
+                for element_id in &element_ids {
+                    if let Some(index) = elements.iter().position(|e| &e.id == element_id) {
+                        if !indexes.contains(&index) {
+                            indexes.push(index);
                         }
                     }
-                    
-                    // Stroke style updates
-                    if let Some(stroke_update) = style_update.get("stroke") {
-                        if let Some(stroke_color) = stroke_update.get("color").and_then(|v| v.as_str()) {
-                            element.style.stroke.color = stroke_color.to_string();
-                            updated = true;
-                        }
-                        if let Some(stroke_opacity) = stroke_update.get("opacity").and_then(|v| v.as_f64()) {
-                            element.style.stroke.opacity = stroke_opacity;
-                            updated = true;
-                        }
-                        if let Some(stroke_width) = stroke_update.get("width").and_then(|v| v.as_f64()) {
-                            element.style.stroke.width = stroke_width;
-                            updated = true;
-                        }
-                        if let Some(stroke_position) = stroke_update.get("position").and_then(|v| v.as_str()) {
-                            element.style.stroke.position = stroke_position.to_string();
-                            updated = true;
-                        }
-                        if let Some(stroke_style) = stroke_update.get("style").and_then(|v| v.as_str()) {
-                            element.style.stroke.style = stroke_style.to_string();
-                            updated = true;
-                        }
-                        if let Some(stroke_enabled) = stroke_update.get("enabled").and_then(|v| v.as_bool()) {
-                            element.style.stroke.enabled = stroke_enabled;
-                            updated = true;
+                }
+
+                for _ in 0..MAX_PUSH_PASSES {
+                    let mut moved = false;
+                    for a in 0..indexes.len() {
+                        for b in a + 1..indexes.len() {
+                            let (first, second) = (&elements[indexes[a]], &elements[indexes[b]]);
+                            let Some((dx, dy)) = separation(first, second) else {
+                                continue;
+                            };
+                            // Each element takes half of the minimal translation
+                            elements[indexes[a]].x -= dx / 2.0;
+                            elements[indexes[a]].y -= dy / 2.0;
+                            elements[indexes[a]].relative_layout = None;
+                            elements[indexes[b]].x += dx / 2.0;
+                            elements[indexes[b]].y += dy / 2.0;
+                            elements[indexes[b]].relative_layout = None;
+                            moved = true;
                         }
                     }
-                    
-                    return updated;
-                } else {
-                    return false;
+                    if !moved {
+                        break;
+                    }
                 }
+                indexes.iter().map(|&index| (elements[index].id.clone(), elements[index].x, elements[index].y)).collect()
             }
+            _ => Vec::new(),
+        }
+    }
+
+    /// จัด elements เป็นตาราง `cols` คอลัมน์ตามลำดับใน ids (ความสูงแถว = element ที่สูงที่สุดในแถว)
+    pub fn arrange_grid(&self, ids_json: &str, cols: usize, gap: f64, start_x: f64, start_y: f64) -> Vec<String> {
+        let element_ids = match serde_json::from_str::<Vec<String>>(ids_json) {
+            Ok(element_ids) if cols > 0 && gap.is_finite() && start_x.is_finite() && start_y.is_finite() => element_ids,
+            _ => return Vec::new(),
+        };
+
+        let mut elements = utils::lock(&self.elements);
+        // Listed order decides grid order; unknown ids are skipped
+        let mut indexes: Vec<usize> = Vec::new();
+        for element_id in &element_ids {
+            if let Some(index) = elements.iter().position(|e| &e.id == element_id) {
+                if !indexes.contains(&index) {
+                    indexes.push(index);
+                }
+            }
+        }
+
+        let mut column_widths = vec![0.0f64; cols];
+        let mut row_heights = vec![0.0f64; indexes.len().div_ceil(cols)];
+        for (slot, &index) in indexes.iter().enumerate() {
+            column_widths[slot % cols] = column_widths[slot % cols].max(elements[index].width);
+            row_heights[slot / cols] = row_heights[slot / cols].max(elements[index].height);
+        }
+
+        let mut updated_ids = Vec::with_capacity(indexes.len());
+        for (slot, &index) in indexes.iter().enumerate() {
+            let (row, col) = (slot / cols, slot % cols);
+            let element = &mut elements[index];
+            element.x = start_x + column_widths[..col].iter().map(|width| width + gap).sum::<f64>();
+            element.y = start_y + row_heights[..row].iter().map(|height| height + gap).sum::<f64>();
+            element.relative_layout = None;
+            updated_ids.push(element.id.clone());
+        }
+        updated_ids
+    }
+
+    /// ล็อกสัดส่วน element เมื่อปรับขนาด
+    pub fn set_lock_aspect(&self, element_id: &str, locked: bool) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if let Some(element) = elements.iter_mut().find(|e| e.id == element_id) {
+            element.lock_aspect = locked;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set or clear the exported tabindex of an element
+    pub fn set_tab_index(&self, element_id: &str, tab_index: Option<i32>) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if let Some(element) = elements.iter_mut().find(|e| e.id == element_id) {
+            element.tab_index = tab_index;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set the reflow anchors used when the element's paper is resized
+    pub fn set_anchors(&self, element_id: &str, anchor_right: bool, anchor_bottom: bool, anchor_center: bool) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if let Some(element) = elements.iter_mut().find(|e| e.id == element_id) {
+            element.anchor_right = anchor_right;
+            element.anchor_bottom = anchor_bottom;
+            element.anchor_center = anchor_center;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// ตั้งค่าการขึ้นหน้าใหม่ก่อน/หลัง element และการไม่ตัดหน้ากลาง element เมื่อพิมพ์
+    pub fn set_page_breaks(&self, element_id: &str, before: bool, after: bool, inside_avoid: bool) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if let Some(element) = elements.iter_mut().find(|e| e.id == element_id) {
+            element.page_break_before = before;
+            element.page_break_after = after;
+            element.page_break_inside_avoid = inside_avoid;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Merge a partial style update (camelCase keys) into the element's style
+    fn apply_style_update(element: &mut Element, style_update: &serde_json::Value) -> bool {
+        let mut updated = false;
+        if let Some(font_size) = style_update.get("fontSize").and_then(|v| v.as_f64()) {
+            element.style.font_size = font_size;
+            updated = true;
+        }
+        if let Some(font_family) = style_update.get("fontFamily").and_then(|v| v.as_str()) {
+            element.style.font_family = font_family.to_string();
+            updated = true;
+        }
+        if let Some(font_weight) = style_update.get("fontWeight").and_then(|v| v.as_str()) {
+            element.style.font_weight = font_weight.to_string();
+            updated = true;
+        }
+        if let Some(font_style) = style_update.get("fontStyle").and_then(|v| v.as_str()) {
+            element.style.font_style = font_style.to_string();
+            updated = true;
+        }
+        if let Some(color) = style_update.get("color").and_then(|v| v.as_str()) {
+            element.style.color = color.to_string();
+            updated = true;
+        }
+        if let Some(bg_color) = style_update.get("backgroundColor").and_then(|v| v.as_str()) {
+            element.style.background_color = bg_color.to_string();
+            updated = true;
+        }
+        if let Some(text_align) = style_update.get("textAlign").and_then(|v| v.as_str()) {
+            element.style.text_align = text_align.to_string();
+            updated = true;
+        }
+        if let Some(padding) = style_update.get("padding").and_then(|v| v.as_f64()) {
+            element.style.padding = padding;
+            updated = true;
+        }
+        if let Some(border_radius) = style_update.get("borderRadius").and_then(|v| v.as_f64()) {
+            element.style.border_radius = border_radius;
+            updated = true;
+        }
+        if let Some(border_width) = style_update.get("borderWidth").and_then(|v| v.as_f64()) {
+            element.style.border_width = border_width;
+            updated = true;
+        }
+        if let Some(border_color) = style_update.get("borderColor").and_then(|v| v.as_str()) {
+            element.style.border_color = border_color.to_string();
+            updated = true;
+        }
+        match style_update.get("blendMode") {
+            Some(serde_json::Value::Null) => {
+                element.style.blend_mode = None;
+                updated = true;
+            }
+            Some(serde_json::Value::String(blend_mode)) if is_valid_blend_mode(blend_mode) => {
+                element.style.blend_mode = Some(blend_mode.clone());
+                updated = true;
+            }
+            _ => {}
+        }
+        match style_update.get("direction") {
+            Some(serde_json::Value::Null) => {
+                element.style.direction = None;
+                updated = true;
+            }
+            Some(serde_json::Value::String(direction)) if TEXT_DIRECTIONS.contains(&direction.as_str()) => {
+                element.style.direction = Some(direction.clone());
+                updated = true;
+            }
+            _ => {}
+        }
+        match style_update.get("overflow") {
+            Some(serde_json::Value::Null) => {
+                element.style.overflow = None;
+                updated = true;
+            }
+            Some(serde_json::Value::String(overflow)) if OVERFLOW_MODES.contains(&overflow.as_str()) => {
+                element.style.overflow = Some(overflow.clone());
+                updated = true;
+            }
+            _ => {}
+        }
+        match style_update.get("lineHeight") {
+            Some(serde_json::Value::Null) => {
+                element.style.line_height = None;
+                updated = true;
+            }
+            Some(value) => {
+                if let Some(line_height) = value.as_f64().filter(|line_height| line_height.is_finite() && *line_height > 0.0) {
+                    element.style.line_height = Some(line_height);
+                    updated = true;
+                }
+            }
+            None => {}
+        }
+        match style_update.get("letterSpacing") {
+            Some(serde_json::Value::Null) => {
+                element.style.letter_spacing = None;
+                updated = true;
+            }
+            Some(value) => {
+                if let Some(letter_spacing) = value.as_f64().filter(|letter_spacing| letter_spacing.is_finite()) {
+                    element.style.letter_spacing = Some(letter_spacing);
+                    updated = true;
+                }
+            }
+            None => {}
+        }
+        
+        // Fill style updates
+        if let Some(fill_update) = style_update.get("fill") {
+            if let Some(fill_color) = fill_update.get("color").and_then(|v| v.as_str()) {
+                element.style.fill.color = fill_color.to_string();
+                updated = true;
+            }
+            if let Some(fill_opacity) = fill_update.get("opacity").and_then(|v| v.as_f64()) {
+                element.style.fill.opacity = fill_opacity;
+                updated = true;
+            }
+            if let Some(fill_enabled) = fill_update.get("enabled").and_then(|v| v.as_bool()) {
+                element.style.fill.enabled = fill_enabled;
+                updated = true;
+            }
+        }
+        
+        // Stroke style updates
+        if let Some(stroke_update) = style_update.get("stroke") {
+            if let Some(stroke_color) = stroke_update.get("color").and_then(|v| v.as_str()) {
+                element.style.stroke.color = stroke_color.to_string();
+                updated = true;
+            }
+            if let Some(stroke_opacity) = stroke_update.get("opacity").and_then(|v| v.as_f64()) {
+                element.style.stroke.opacity = stroke_opacity;
+                updated = true;
+            }
+            if let Some(stroke_width) = stroke_update.get("width").and_then(|v| v.as_f64()) {
+                element.style.stroke.width = stroke_width;
+                updated = true;
+            }
+            if let Some(stroke_position) = stroke_update.get("position").and_then(|v| v.as_str()) {
+                element.style.stroke.position = stroke_position.to_string();
+                updated = true;
+            }
+            if let Some(stroke_style) = stroke_update.get("style").and_then(|v| v.as_str()) {
+                element.style.stroke.style = stroke_style.to_string();
+                updated = true;
+            }
+            if let Some(stroke_enabled) = stroke_update.get("enabled").and_then(|v| v.as_bool()) {
+                element.style.stroke.enabled = stroke_enabled;
+                updated = true;
+            }
+            match stroke_update.get("lineCap") {
+                Some(serde_json::Value::Null) => {
+                    element.style.stroke.line_cap = None;
+                    updated = true;
+                }
+                Some(serde_json::Value::String(line_cap)) if LINE_CAPS.contains(&line_cap.as_str()) => {
+                    element.style.stroke.line_cap = Some(line_cap.clone());
+                    updated = true;
+                }
+                _ => {}
+            }
+            match stroke_update.get("lineJoin") {
+                Some(serde_json::Value::Null) => {
+                    element.style.stroke.line_join = None;
+                    updated = true;
+                }
+                Some(serde_json::Value::String(line_join)) if LINE_JOINS.contains(&line_join.as_str()) => {
+                    element.style.stroke.line_join = Some(line_join.clone());
+                    updated = true;
+                }
+                _ => {}
+            }
+        }
+        
+        updated
+    }
+
+    /// กำหนด slot key ให้ element (ค่าว่าง = ยกเลิก slot)
+    pub fn set_slot_key(&self, element_id: &str, slot_key: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if let Some(element) = elements.iter_mut().find(|e| e.id == element_id) {
+            element.slot_key = if slot_key.is_empty() { None } else { Some(slot_key.to_string()) };
+            true
+        } else {
+            false
+        }
+    }
+
+    /// ได้รายการ slots ทั้งหมดพร้อม element id
+    pub fn get_slots(&self) -> String {
+        let elements = utils::lock(&self.elements);
+        let slots: Vec<serde_json::Value> = elements.iter()
+            .filter_map(|element| element.slot_key.as_ref().map(|slot_key| serde_json::json!({
+                "slotKey": slot_key,
+                "elementId": element.id
+            })))
+            .collect();
+        serde_json::Value::Array(slots).to_string()
+    }
+
+    /// เติมค่าให้ทุก element ที่มี slot key ตรงกัน
+    pub fn fill_slot(&self, slot_key: &str, content: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        let mut filled = false;
+        for element in elements.iter_mut() {
+            if element.slot_key.as_deref() == Some(slot_key) {
+                Self::apply_slot_content(element, content);
+                filled = true;
+            }
+        }
+        filled
+    }
+
+    /// Clone template elements with fresh IDs, shifted by (dx, dy), filling slots from the record
+    pub fn merge_record(&self, template: &[Element], dx: f64, dy: f64, record: &serde_json::Value) -> Vec<String> {
+        let mut elements = utils::lock(&self.elements);
+        let mut new_ids = Vec::with_capacity(template.len());
+
+        for source in template {
+            let mut element = source.clone();
+            element.id = self.generate_element_id(&elements);
+            element.x += dx;
+            element.y += dy;
+
+            if let Some(value) = element.slot_key.as_ref().and_then(|slot_key| record.get(slot_key)) {
+                let content = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                Self::apply_slot_content(&mut element, &content);
+            }
+
+            new_ids.push(element.id.clone());
+            elements.push(element);
+        }
+
+        new_ids
+    }
+
+    /// Form fields keep their label/layout JSON and only take the slot value
+    fn apply_slot_content(element: &mut Element, content: &str) {
+        if element.element_type == "form_field" {
+            let mut form_data = serde_json::from_str::<serde_json::Value>(&element.content)
+                .ok()
+                .filter(|value| value.is_object())
+                .unwrap_or_else(|| serde_json::json!({}));
+            form_data["value"] = serde_json::Value::String(content.to_string());
+            element.content = form_data.to_string();
+        } else {
+            element.content = content.to_string();
         }
-        false
     }
 
     /// ลบ element (working implementation)
     pub fn delete_element(&self, element_id: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
-        let initial_len = elements.len();
-        elements.retain(|element| element.id != element_id);
-        
-        elements.len() < initial_len
+        let mut elements = utils::lock(&self.elements);
+        let mut deleted = false;
+        while let Some(index) = elements.iter().position(|element| element.id == element_id) {
+            self.recycle(elements.remove(index));
+            deleted = true;
+        }
+        deleted
     }
 
     /// ได้ element ตาม ID (optimized with spatial indexing)
     pub fn get_element(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        match self.find(element_id) {
+            Some(element) => serde_json::to_string(&element).unwrap_or_else(|_| "null".to_string()),
+            None => "null".to_string(),
+        }
+    }
+
+    /// ค้นหา element ตาม ID
+    pub fn find(&self, element_id: &str) -> Option<Element> {
+        let elements = utils::lock(&self.elements);
         // Use binary search for O(log n) lookup if elements are sorted by ID
         // For now, keep linear search but this could be optimized further
+        elements.iter().find(|element| element.id == element_id).cloned()
+    }
+
+    /// หา element ที่อยู่บนสุด ณ จุดที่กำหนด (z_index สูงสุด, ถ้าเท่ากันตัวที่เพิ่มทีหลังอยู่บน)
+    pub fn find_topmost_at_point(&self, x: f64, y: f64) -> String {
+        match self.topmost_at_point(x, y) {
+            Some(element) => serde_json::to_string(&element).unwrap_or_else(|_| "null".to_string()),
+            None => "null".to_string(),
+        }
+    }
+
+    /// หา element ที่อยู่บนสุด ณ จุดที่กำหนด
+    pub fn topmost_at_point(&self, x: f64, y: f64) -> Option<Element> {
+        let elements = utils::lock(&self.elements);
+        // max_by_key keeps the last maximum, i.e. the most recently inserted on ties
+        elements
+            .iter()
+            .filter(|element| element.visible && element.contains_point(x, y))
+            .max_by_key(|element| element.z_index)
+            .cloned()
+    }
+
+    /// วัดระยะห่างระหว่างสอง elements (ค่าติดลบ = ทับซ้อนกัน)
+    pub fn measure_between(&self, id_a: &str, id_b: &str) -> String {
+        let elements = utils::lock(&self.elements);
+        let (a, b) = match (
+            elements.iter().find(|e| e.id == id_a),
+            elements.iter().find(|e| e.id == id_b),
+        ) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return "null".to_string(),
+        };
+
+        // Distance between facing edges on each axis
+        let horizontal_gap = (b.x - (a.x + a.width)).max(a.x - (b.x + b.width));
+        let vertical_gap = (b.y - (a.y + a.height)).max(a.y - (b.y + b.height));
+        let center_a = Point::new(a.x + a.width / 2.0, a.y + a.height / 2.0);
+        let center_b = Point::new(b.x + b.width / 2.0, b.y + b.height / 2.0);
+
+        serde_json::json!({
+            "horizontalGap": horizontal_gap,
+            "verticalGap": vertical_gap,
+            "centerDistance": center_a.distance_to(&center_b),
+        })
+        .to_string()
+    }
+
+    /// จุดยึด 8 จุด (มุมและกึ่งกลางขอบ) ของ element สำหรับต่อเส้น
+    pub fn get_element_anchors(&self, element_id: &str) -> String {
+        match self.find(element_id) {
+            Some(element) => serde_json::Value::Array(
+                element
+                    .anchor_points()
+                    .iter()
+                    .map(|(name, point)| serde_json::json!({ "name": name, "x": point.x, "y": point.y }))
+                    .collect(),
+            )
+            .to_string(),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// ย้ายปลายเส้น (`"start"`/`"end"`) ไปที่ (x, y) โดย snap เข้าจุดยึดที่ใกล้ที่สุดของ element อื่นภายใน threshold
+    pub fn snap_line_endpoint(&self, line_id: &str, which: &str, x: f64, y: f64, threshold: f64) -> String {
+        if !matches!(which, "start" | "end") || !utils::all_finite(&[x, y, threshold]) || threshold < 0.0 {
+            return "null".to_string();
+        }
+        let mut elements = utils::lock(&self.elements);
+        if !elements.iter().any(|element| element.id == line_id && element.element_type == "line") {
+            return "null".to_string();
+        }
+
+        let target = Point::new(x, y);
+        let nearest = elements
+            .iter()
+            .filter(|element| element.visible && element.id != line_id && element.element_type != "line")
+            .flat_map(|element| element.anchor_points().map(|(name, point)| (element.id.clone(), name, point)))
+            .map(|(element_id, name, point)| (point.distance_to(&target), element_id, name, point))
+            .filter(|(distance, ..)| *distance <= threshold)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let point = nearest.as_ref().map_or(target, |(_, _, _, point)| *point);
+
+        let Some(line) = elements.iter_mut().find(|element| element.id == line_id) else {
+            return "null".to_string();
+        };
+        // Endpoints are stored relative to the line element
+        let mut line_data = serde_json::from_str::<serde_json::Value>(&line.content)
+            .ok()
+            .filter(|value| value.is_object())
+            .unwrap_or_else(|| serde_json::json!({ "lineType": "straight", "startX": 0, "startY": 0, "endX": line.width, "endY": 0 }));
+        let endpoint = |axis: &str, default: f64| line_data.get(axis).and_then(|v| v.as_f64()).unwrap_or(default);
+        let (mut start, mut end) = (
+            Point::new(line.x + endpoint("startX", 0.0), line.y + endpoint("startY", 0.0)),
+            Point::new(line.x + endpoint("endX", line.width), line.y + endpoint("endY", 0.0)),
+        );
+        if which == "start" {
+            start = point;
+        } else {
+            end = point;
+        }
+
+        // Refit the box around both endpoints, since the line is drawn clipped to it
+        line.x = start.x.min(end.x);
+        line.y = start.y.min(end.y);
+        line.width = (start.x - end.x).abs().max(MIN_LINE_BOX_SIZE);
+        line.height = (start.y - end.y).abs().max(MIN_LINE_BOX_SIZE);
+        line.relative_layout = None;
+        line_data["startX"] = serde_json::json!(start.x - line.x);
+        line_data["startY"] = serde_json::json!(start.y - line.y);
+        line_data["endX"] = serde_json::json!(end.x - line.x);
+        line_data["endY"] = serde_json::json!(end.y - line.y);
+        line.content = line_data.to_string();
+
+        serde_json::json!({
+            "x": point.x,
+            "y": point.y,
+            "snapped": nearest.is_some(),
+            "elementId": nearest.as_ref().map(|(_, element_id, ..)| element_id.clone()),
+            "anchor": nearest.as_ref().map(|(_, _, name, _)| *name),
+        })
+        .to_string()
+    }
+
+    /// คัดลอก elements ตาม ids (JSON array) เป็น payload JSON ที่ engine อื่นนำไป paste ได้ ("null" ถ้า ids ไม่ถูกต้อง)
+    pub fn copy_elements(&self, ids_json: &str) -> String {
+        let Ok(ids) = serde_json::from_str::<Vec<String>>(ids_json) else {
+            return "null".to_string();
+        };
+        let elements = utils::lock(&self.elements);
+        let payload = ClipboardPayload {
+            format: CLIPBOARD_FORMAT.to_string(),
+            version: CLIPBOARD_VERSION,
+            elements: ids
+                .iter()
+                .filter_map(|id| elements.iter().find(|element| &element.id == id))
+                .cloned()
+                .collect(),
+        };
+        serde_json::to_string(&payload).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// วาง elements จาก payload ของ `copy_elements` ด้วย id ใหม่ เลื่อนไป (offset_x, offset_y)
+    /// การอ้างอิงระหว่าง elements ที่คัดลอกมาด้วยกัน (element ใน cell, ตารางของ chart) ชี้ไปยังสำเนาใหม่
+    pub fn paste_elements_from(&self, payload: &str, offset_x: f64, offset_y: f64) -> Option<Vec<Element>> {
+        let payload = serde_json::from_str::<ClipboardPayload>(payload).ok()?;
+        if payload.format != CLIPBOARD_FORMAT || !utils::all_finite(&[offset_x, offset_y]) {
+            return None;
+        }
+
+        let mut elements = utils::lock(&self.elements);
+        // The ID counter never repeats, so copies pasted together get distinct IDs
+        let new_ids: HashMap<String, String> = payload
+            .elements
+            .iter()
+            .map(|source| (source.id.clone(), self.generate_element_id(&elements)))
+            .collect();
+
+        let created_at = utils::now_ms();
+        let mut pasted = Vec::with_capacity(payload.elements.len());
+        for source in payload.elements {
+            let mut element = source;
+            element.id = new_ids[&element.id].clone();
+            element.x += offset_x;
+            element.y += offset_y;
+            element.created_at = created_at;
+            element.relative_layout = None; // Papers differ between documents
+            if element.element_type == "chart" {
+                if let Some(new_source) = chart::source_table_id(&element.content).and_then(|id| new_ids.get(&id)) {
+                    element.content = chart::with_source_table_id(&element.content, new_source);
+                }
+            }
+            if let Some(new_source) = element.linked_from.as_ref().and_then(|id| new_ids.get(id)) {
+                element.linked_from = Some(new_source.clone());
+            }
+            if let Some(table_data) = element.table_data.as_mut() {
+                for cell in table_data.rows.iter_mut().flat_map(|row| row.cells.iter_mut()) {
+                    if let Some(new_nested) = cell.element_id.as_ref().and_then(|id| new_ids.get(id)) {
+                        cell.element_id = Some(new_nested.clone());
+                    }
+                }
+            }
+            pasted.push(element);
+        }
+        elements.extend(pasted.iter().cloned());
+        Some(pasted)
+    }
+
+    /// ตรวจการอ้างอิงข้าม element ที่ขาด (chart ที่ชี้ไปยังตารางที่ไม่มีแล้ว, element ใน cell ที่ถูกลบ)
+    /// คืน JSON array ของ `{elementId, kind, referenceId}` (cell มี `row`, `col` เพิ่ม)
+    pub fn check_integrity(&self) -> String {
+        let elements = utils::lock(&self.elements);
+        let by_id: HashMap<&str, &Element> = elements.iter().map(|element| (element.id.as_str(), element)).collect();
+
+        let mut issues = Vec::new();
         for element in elements.iter() {
-            if element.id == element_id {
-                return serde_json::to_string(element).unwrap_or_else(|_| "null".to_string());
+            if element.element_type == "chart" {
+                if let Some(source_table_id) = chart::source_table_id(&element.content) {
+                    if !by_id.get(source_table_id.as_str()).is_some_and(|source| source.is_table()) {
+                        issues.push(serde_json::json!({
+                            "elementId": element.id,
+                            "kind": "missing_chart_source",
+                            "referenceId": source_table_id,
+                        }));
+                    }
+                }
+            }
+            if let Some(source_id) = element.linked_from.as_deref().filter(|id| !by_id.contains_key(id)) {
+                issues.push(serde_json::json!({
+                    "elementId": element.id,
+                    "kind": "missing_link_source",
+                    "referenceId": source_id,
+                }));
+            }
+            let Some(table_data) = &element.table_data else {
+                continue;
+            };
+            for (row, table_row) in table_data.rows.iter().enumerate() {
+                for (col, cell) in table_row.cells.iter().enumerate() {
+                    if let Some(nested_id) = cell.element_id.as_deref().filter(|id| !by_id.contains_key(id)) {
+                        issues.push(serde_json::json!({
+                            "elementId": element.id,
+                            "kind": "missing_cell_element",
+                            "referenceId": nested_id,
+                            "row": row,
+                            "col": col,
+                        }));
+                    }
+                }
             }
         }
-        
-        "null".to_string()
+        serde_json::Value::Array(issues).to_string()
     }
 
-    /// ได้ elements ทั้งหมด
+    /// หา elements ที่ซ้ำกันวางทับกัน (ชนิด ขนาด เนื้อหา และตำแหน่งเดียวกัน) คืนเป็นกลุ่มของ ids
+    pub fn find_duplicate_elements(&self) -> String {
+        let mut elements = self.all();
+        elements.sort_by(|a, b| a.stable_cmp(b));
+
+        let is_duplicate = |a: &Element, b: &Element| {
+            a.element_type == b.element_type
+                && a.content == b.content
+                && (a.x - b.x).abs() <= DUPLICATE_TOLERANCE
+                && (a.y - b.y).abs() <= DUPLICATE_TOLERANCE
+                && (a.width - b.width).abs() <= DUPLICATE_TOLERANCE
+                && (a.height - b.height).abs() <= DUPLICATE_TOLERANCE
+        };
+
+        // Each element joins the first group whose first member it duplicates
+        let mut groups: Vec<Vec<&Element>> = Vec::new();
+        for element in &elements {
+            match groups.iter_mut().find(|group| is_duplicate(group[0], element)) {
+                Some(group) => group.push(element),
+                None => groups.push(vec![element]),
+            }
+        }
+
+        let duplicate_groups: Vec<Vec<&str>> = groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.into_iter().map(|element| element.id.as_str()).collect())
+            .collect();
+        serde_json::to_string(&duplicate_groups).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// ได้ elements ทั้งหมด (เรียงตาม z_index แล้วตาม id)
     pub fn get_all_elements(&self) -> String {
-        let elements = self.elements.lock().unwrap();
-        serde_json::to_string(&*elements).unwrap_or_else(|_| "[]".to_string())
+        serde_json::to_string(&self.all_sorted()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// ได้ elements ทั้งหมดเรียงตาม `by`: "created_desc" (ใหม่สุดก่อน), "type" หรือ "z_index" (ค่าเริ่มต้น)
+    pub fn get_all_elements_sorted(&self, by: &str) -> String {
+        let mut elements = self.all();
+        match by {
+            "created_desc" => {
+                // Same-millisecond creations keep newest (last inserted) first
+                elements.reverse();
+                elements.sort_by(|a, b| b.created_at.total_cmp(&a.created_at));
+            }
+            "type" => elements.sort_by(|a, b| a.element_type.cmp(&b.element_type).then_with(|| a.stable_cmp(b))),
+            _ => elements.sort_by(|a, b| a.stable_cmp(b)),
+        }
+        serde_json::to_string(&elements).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// ได้สำเนาของ elements ทั้งหมดตามลำดับที่เก็บ
+    pub fn all(&self) -> Vec<Element> {
+        utils::lock(&self.elements).clone()
+    }
+
+    /// ได้สำเนาของ elements ทั้งหมดเรียงแบบคงที่ (z_index แล้ว id)
+    pub fn all_sorted(&self) -> Vec<Element> {
+        let mut elements = self.all();
+        elements.sort_by(|a, b| a.stable_cmp(b));
+        elements
+    }
+
+    /// เรียง elements ที่เก็บไว้ตามลำดับคงที่ (z_index แล้ว id)
+    pub fn sort_elements(&self) {
+        utils::lock(&self.elements).sort_by(|a, b| a.stable_cmp(b));
+    }
+
+    /// จัด z_index ใหม่ให้ต่อเนื่อง 0..n-1 ตามลำดับการแสดงผลเดิม คืน (id, z_index ใหม่) จากหลังไปหน้า
+    pub fn normalize_z_indices(&self) -> Vec<(String, i32)> {
+        let mut elements = utils::lock(&self.elements);
+        let mut order: Vec<usize> = (0..elements.len()).collect();
+        order.sort_by(|&a, &b| elements[a].stable_cmp(&elements[b]));
+
+        order
+            .into_iter()
+            .enumerate()
+            .map(|(z_index, index)| {
+                let element = &mut elements[index];
+                element.z_index = z_index as i32;
+                (element.id.clone(), element.z_index)
+            })
+            .collect()
     }
 
     /// ได้จำนวน elements
     pub fn get_element_count(&self) -> usize {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         elements.len()
     }
 
-    /// ตรวจสอบการชน (minimal implementation)
-    pub fn check_collisions(&self, _element_id: &str) -> String {
-        r#"{"element_id":"","colliding_elements":[],"is_out_of_bounds":false}"#.to_string()
+    /// ตรวจสอบการชน
+    pub fn check_collisions(&self, element_id: &str) -> String {
+        match self.collisions(element_id) {
+            Some(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+            None => r#"{"element_id":"","colliding_elements":[],"is_out_of_bounds":false}"#.to_string(),
+        }
+    }
+
+    /// หา elements ที่ทับซ้อนกับ element ที่กำหนด
+    pub fn collisions(&self, element_id: &str) -> Option<CollisionResult> {
+        let elements = utils::lock(&self.elements);
+        let element = elements.iter().find(|e| e.id == element_id)?;
+        let bounds = element.get_bounds();
+
+        let colliding_elements = elements
+            .iter()
+            .filter(|other| other.id != element.id && bounds.intersects(&other.get_bounds()))
+            .map(|other| other.id.clone())
+            .collect();
+
+        Some(CollisionResult {
+            element_id: element.id.clone(),
+            colliding_elements,
+            is_out_of_bounds: false,
+        })
     }
 
     /// หา elements ในพื้นที่ที่กำหนด (minimal implementation)
@@ -235,26 +1145,40 @@ impl ElementManager {
         r#"{"elements":[],"total_count":0}"#.to_string()
     }
 
-    /// อัพเดทตำแหน่งหลาย elements พร้อมกัน (minimal implementation)
-    pub fn batch_update_positions(&self, _updates_json: &str) -> String {
-        "[]".to_string()
+    /// อัพเดทตำแหน่งหลาย elements พร้อมกัน คืน (id, สำเร็จหรือไม่) ตามลำดับ (ว่างถ้า JSON ไม่ถูกต้อง)
+    pub fn batch_update_positions(&self, updates_json: &str) -> Vec<(String, bool)> {
+        let Ok(updates) = serde_json::from_str::<Vec<PositionUpdate>>(updates_json) else {
+            return Vec::new();
+        };
+        updates
+            .into_iter()
+            .map(|update| {
+                let moved = self.update_element_position(&update.element_id, update.x, update.y);
+                (update.element_id, moved)
+            })
+            .collect()
+    }
+
+    /// แทนที่ elements ทั้งหมด (ใช้ตอน rollback)
+    pub fn replace_all(&self, new_elements: Vec<Element>) {
+        *utils::lock(&self.elements) = new_elements;
     }
 
     /// ได้ elements reference สำหรับ export
     pub fn get_elements_ref(&self) -> MutexGuard<Vec<Element>> {
-        self.elements.lock().unwrap()
+        utils::lock(&self.elements)
     }
 
     /// Clear all elements
     pub fn clear(&self) {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         elements.clear();
     }
 
     /// Performance optimization methods for StylePanel
     /// Parse form field data efficiently in WASM
     pub fn parse_form_field_data(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.element_type == "form_field" {
                 // Parse and return optimized form field data
@@ -283,7 +1207,7 @@ impl ElementManager {
 
     /// Calculate safe style properties efficiently in WASM
     pub fn calculate_safe_style(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id {
                 // Calculate and return optimized safe style
@@ -309,7 +1233,7 @@ impl ElementManager {
 
     /// Get element type flags efficiently
     pub fn get_element_type_flags(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id {
                 let flags = serde_json::json!({
@@ -330,7 +1254,7 @@ impl ElementManager {
 
     /// Batch update form field content efficiently
     pub fn batch_update_form_field(&self, element_id: &str, updates_json: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.element_type == "form_field" {
                 if let Ok(updates) = serde_json::from_str::<serde_json::Value>(updates_json) {
@@ -369,7 +1293,7 @@ impl ElementManager {
 
     /// Get optimized element data for StylePanel
     pub fn get_element_for_style_panel(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id {
                 // Return only necessary data for StylePanel
@@ -438,16 +1362,16 @@ impl ElementManager {
                 }
             }
             
-            // Validate color (hex format)
+            // Validate color (hex, rgb()/hsl() or named)
             if let Some(color) = style_update.get("color").and_then(|v| v.as_str()) {
-                if color.starts_with('#') && color.len() == 7 {
+                if utils::is_valid_css_color(color) {
                     validated.insert("color".to_string(), serde_json::Value::String(color.to_string()));
                 }
             }
             
             // Validate background color
             if let Some(bg_color) = style_update.get("backgroundColor").and_then(|v| v.as_str()) {
-                if bg_color.starts_with('#') && bg_color.len() == 7 {
+                if utils::is_valid_css_color(bg_color) {
                     validated.insert("backgroundColor".to_string(), serde_json::Value::String(bg_color.to_string()));
                 }
             }
@@ -482,11 +1406,46 @@ impl ElementManager {
             
             // Validate border color
             if let Some(border_color) = style_update.get("borderColor").and_then(|v| v.as_str()) {
-                if border_color.starts_with('#') && border_color.len() == 7 {
+                if utils::is_valid_css_color(border_color) {
                     validated.insert("borderColor".to_string(), serde_json::Value::String(border_color.to_string()));
                 }
             }
             
+            // Validate blend mode
+            if let Some(blend_mode) = style_update.get("blendMode").and_then(|v| v.as_str()) {
+                if is_valid_blend_mode(blend_mode) {
+                    validated.insert("blendMode".to_string(), serde_json::Value::String(blend_mode.to_string()));
+                }
+            }
+
+            // Validate line height (0.5-5 times the font size)
+            if let Some(line_height) = style_update.get("lineHeight").and_then(|v| v.as_f64()) {
+                if (0.5..=5.0).contains(&line_height) {
+                    validated.insert("lineHeight".to_string(), serde_json::json!(line_height));
+                }
+            }
+
+            // Validate letter spacing (-5 to 50px)
+            if let Some(letter_spacing) = style_update.get("letterSpacing").and_then(|v| v.as_f64()) {
+                if (-5.0..=50.0).contains(&letter_spacing) {
+                    validated.insert("letterSpacing".to_string(), serde_json::json!(letter_spacing));
+                }
+            }
+
+            // Validate overflow mode
+            if let Some(overflow) = style_update.get("overflow").and_then(|v| v.as_str()) {
+                if OVERFLOW_MODES.contains(&overflow) {
+                    validated.insert("overflow".to_string(), serde_json::Value::String(overflow.to_string()));
+                }
+            }
+
+            // Validate text direction
+            if let Some(direction) = style_update.get("direction").and_then(|v| v.as_str()) {
+                if TEXT_DIRECTIONS.contains(&direction) {
+                    validated.insert("direction".to_string(), serde_json::Value::String(direction.to_string()));
+                }
+            }
+            
             return serde_json::Value::Object(validated).to_string();
         }
         
@@ -495,7 +1454,7 @@ impl ElementManager {
 
     /// Performance optimized element lookup with caching
     pub fn get_elements_summary(&self) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         
         let summary = serde_json::json!({
             "total": elements.len(),
@@ -512,3 +1471,452 @@ impl ElementManager {
         summary.to_string()
     }
 }
+
+/// Minimal translation moving `second` off `first` along one axis, or `None` when they only touch or are apart
+fn separation(first: &Element, second: &Element) -> Option<(f64, f64)> {
+    let overlap_x = (first.x + first.width).min(second.x + second.width) - first.x.max(second.x);
+    let overlap_y = (first.y + first.height).min(second.y + second.height) - first.y.max(second.y);
+    if overlap_x <= f64::EPSILON || overlap_y <= f64::EPSILON {
+        return None;
+    }
+    let center_dx = (second.x + second.width / 2.0) - (first.x + first.width / 2.0);
+    let center_dy = (second.y + second.height / 2.0) - (first.y + first.height / 2.0);
+    // Push along the axis with less overlap, away from the first element's center
+    if overlap_x <= overlap_y {
+        Some((if center_dx < 0.0 { -overlap_x } else { overlap_x }, 0.0))
+    } else {
+        Some((0.0, if center_dy < 0.0 { -overlap_y } else { overlap_y }))
+    }
+}
+
+/// Candidate closest to `value`, if any is within `tolerance`
+fn closest_within(value: f64, candidates: impl Iterator<Item = f64>, tolerance: f64) -> Option<f64> {
+    candidates
+        .filter(|candidate| (candidate - value).abs() <= tolerance)
+        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_create_element_ids_are_unique() {
+        let element_manager = ElementManager::new();
+        let mut ids = HashSet::new();
+
+        for _ in 0..10_000 {
+            ids.insert(element_manager.create("text", 0.0, 0.0).id);
+        }
+
+        assert_eq!(ids.len(), 10_000);
+        assert_eq!(element_manager.get_element_count(), 10_000);
+    }
+
+    #[test]
+    fn test_recovers_from_poisoned_lock() {
+        let elements = Arc::new(Mutex::new(Vec::new()));
+        let element_manager = ElementManager::new_with_data(Arc::clone(&elements));
+        element_manager.create("text", 0.0, 0.0);
+
+        let poisoner = Arc::clone(&elements);
+        let result = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("operation failed while holding the lock");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(elements.is_poisoned());
+
+        assert_eq!(element_manager.get_element_count(), 1);
+        element_manager.create("text", 10.0, 10.0);
+        assert_eq!(element_manager.get_element_count(), 2);
+        assert!(!elements.is_poisoned());
+    }
+
+    #[test]
+    fn test_find_topmost_at_point() {
+        let mut elements = Vec::new();
+        for (id, z_index) in [("middle", 5), ("top", 10), ("bottom", 1)] {
+            let mut element = Element::new(id.to_string(), "button".to_string(), "button".to_string());
+            element.x = 100.0;
+            element.y = 100.0;
+            element.z_index = z_index;
+            elements.push(element);
+        }
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(elements)));
+
+        let topmost: Element = serde_json::from_str(&element_manager.find_topmost_at_point(110.0, 110.0)).unwrap();
+        assert_eq!(topmost.id, "top");
+        assert_eq!(element_manager.find_topmost_at_point(10.0, 10.0), "null");
+
+        // Equal z-index: the later element is drawn on top
+        let mut tied = element_manager.create("button", 100.0, 100.0);
+        tied.z_index = 10;
+        utils::lock(&element_manager.elements)[3] = tied.clone();
+        assert_eq!(element_manager.topmost_at_point(110.0, 110.0).unwrap().id, tied.id);
+    }
+
+    #[test]
+    fn test_measure_between() {
+        let mut left = Element::new("left".to_string(), "button".to_string(), "button".to_string());
+        left.x = 100.0;
+        left.y = 100.0;
+        left.width = 50.0;
+        left.height = 40.0;
+        let mut right = left.clone();
+        right.id = "right".to_string();
+        right.x = 180.0;
+        right.y = 120.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![left, right])));
+
+        let measurement: serde_json::Value = serde_json::from_str(&element_manager.measure_between("left", "right")).unwrap();
+        assert_eq!(measurement["horizontalGap"], 30.0); // 180 - (100 + 50)
+        assert_eq!(measurement["verticalGap"], -20.0); // overlapping rows
+        assert_eq!(measurement["centerDistance"], (80.0f64 * 80.0 + 20.0 * 20.0).sqrt());
+
+        // Symmetric
+        let reversed: serde_json::Value = serde_json::from_str(&element_manager.measure_between("right", "left")).unwrap();
+        assert_eq!(reversed["horizontalGap"], 30.0);
+        assert_eq!(element_manager.measure_between("left", "missing"), "null");
+    }
+
+    #[test]
+    fn test_snap_line_endpoint_to_anchor() {
+        let element_manager = ElementManager::new();
+        let rectangle = element_manager.create("rectangle", 100.0, 100.0);
+        let line = element_manager.create("line", 300.0, 200.0);
+
+        let anchors: serde_json::Value = serde_json::from_str(&element_manager.get_element_anchors(&rectangle.id)).unwrap();
+        assert_eq!(anchors.as_array().unwrap().len(), 8);
+        assert_eq!(anchors[4], serde_json::json!({ "name": "right", "x": 250.0, "y": 150.0 }));
+
+        // Near the rectangle's right-center anchor
+        let result: serde_json::Value =
+            serde_json::from_str(&element_manager.snap_line_endpoint(&line.id, "end", 254.0, 147.0, 10.0)).unwrap();
+        assert_eq!(result["snapped"], true);
+        assert_eq!(result["anchor"], "right");
+        assert_eq!(result["elementId"], rectangle.id.as_str());
+        // The box is refitted around both endpoints so the line is not clipped
+        let snapped_line = element_manager.find(&line.id).unwrap();
+        assert_eq!((snapped_line.x, snapped_line.y, snapped_line.width, snapped_line.height), (250.0, 150.0, 50.0, 50.0));
+        let line_data: serde_json::Value = serde_json::from_str(&snapped_line.content).unwrap();
+        assert_eq!((line_data["startX"].as_f64(), line_data["startY"].as_f64()), (Some(50.0), Some(50.0)));
+        assert_eq!((line_data["endX"].as_f64(), line_data["endY"].as_f64()), (Some(0.0), Some(0.0)));
+
+        // Nothing within the threshold keeps the dropped position
+        let result: serde_json::Value =
+            serde_json::from_str(&element_manager.snap_line_endpoint(&line.id, "start", 400.0, 400.0, 10.0)).unwrap();
+        assert_eq!(result["snapped"], false);
+        let moved_line = element_manager.find(&line.id).unwrap();
+        assert_eq!((moved_line.x, moved_line.y, moved_line.width, moved_line.height), (250.0, 150.0, 150.0, 250.0));
+        let line_data: serde_json::Value = serde_json::from_str(&moved_line.content).unwrap();
+        assert_eq!((line_data["startX"].as_f64(), line_data["startY"].as_f64()), (Some(150.0), Some(250.0)));
+        assert_eq!(element_manager.snap_line_endpoint(&rectangle.id, "end", 0.0, 0.0, 10.0), "null");
+        assert_eq!(element_manager.snap_line_endpoint(&line.id, "middle", 0.0, 0.0, 10.0), "null");
+    }
+
+    #[test]
+    fn test_normalize_z_indices() {
+        let element_manager = ElementManager::new();
+        for (id, z_index) in [("a", 5), ("b", 100), ("c", 5)] {
+            let mut element = Element::new(id.to_string(), id.to_string(), "text".to_string());
+            element.z_index = z_index;
+            utils::lock(&element_manager.elements).push(element);
+        }
+
+        let expected = vec![("a".to_string(), 0), ("c".to_string(), 1), ("b".to_string(), 2)];
+        assert_eq!(element_manager.normalize_z_indices(), expected);
+        let z_indices: Vec<(String, i32)> = element_manager.all_sorted().into_iter().map(|element| (element.id, element.z_index)).collect();
+        assert_eq!(z_indices, expected);
+    }
+
+    #[test]
+    fn test_check_integrity_flags_chart_without_table() {
+        let element_manager = ElementManager::new();
+        let table = element_manager.create("table", 0.0, 0.0);
+        let chart = element_manager.create("chart", 0.0, 300.0);
+        let unconnected = element_manager.create("chart", 300.0, 300.0);
+        element_manager.update_element_content(&chart.id, &format!(r#"{{"sourceTableId":"{}","column":1,"kind":"bar"}}"#, table.id));
+        assert_eq!(element_manager.check_integrity(), "[]");
+
+        assert!(element_manager.delete_element(&table.id));
+        let issues: serde_json::Value = serde_json::from_str(&element_manager.check_integrity()).unwrap();
+        assert_eq!(
+            issues,
+            serde_json::json!([{ "elementId": chart.id, "kind": "missing_chart_source", "referenceId": table.id }])
+        );
+        assert!(!issues.to_string().contains(&unconnected.id));
+    }
+
+    #[test]
+    fn test_find_duplicate_elements() {
+        let mut original = Element::new("original".to_string(), "text".to_string(), "text".to_string());
+        original.x = 100.0;
+        original.y = 100.0;
+        original.content = "Total".to_string();
+        let mut pasted = original.clone();
+        pasted.id = "pasted".to_string();
+        pasted.x = 100.5;
+        let mut distinct = original.clone();
+        distinct.id = "distinct".to_string();
+        distinct.content = "Subtotal".to_string();
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![original, distinct, pasted])));
+
+        let groups: Vec<Vec<String>> = serde_json::from_str(&element_manager.find_duplicate_elements()).unwrap();
+        assert_eq!(groups, vec![vec!["original".to_string(), "pasted".to_string()]]);
+
+        assert!(element_manager.delete_element("pasted"));
+        assert_eq!(element_manager.find_duplicate_elements(), "[]");
+    }
+
+    #[test]
+    fn test_lock_aspect_on_resize() {
+        let mut banner = Element::new("banner".to_string(), "image".to_string(), "image".to_string());
+        banner.width = 200.0;
+        banner.height = 100.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![banner])));
+
+        assert!(element_manager.set_lock_aspect("banner", true));
+        assert!(element_manager.update_element_size("banner", 300.0, 100.0));
+        let resized = element_manager.find("banner").unwrap();
+        assert_eq!((resized.width, resized.height), (300.0, 150.0));
+
+        assert!(element_manager.set_lock_aspect("banner", false));
+        assert!(element_manager.update_element_size("banner", 300.0, 100.0));
+        assert_eq!(element_manager.find("banner").unwrap().height, 100.0);
+
+        assert!(element_manager.create("circle", 0.0, 0.0).lock_aspect);
+    }
+
+    #[test]
+    fn test_get_all_elements_is_sorted_deterministically() {
+        let mut elements = Vec::new();
+        for (id, z_index) in [("c", 1), ("b", 0), ("a", 1), ("d", -1)] {
+            let mut element = Element::new(id.to_string(), "text".to_string(), "text".to_string());
+            element.z_index = z_index;
+            elements.push(element);
+        }
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(elements)));
+
+        let listed: Vec<Element> = serde_json::from_str(&element_manager.get_all_elements()).unwrap();
+        let ids: Vec<&str> = listed.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["d", "b", "a", "c"]);
+
+        // Storage keeps insertion order until sorted explicitly
+        assert_eq!(element_manager.all()[0].id, "c");
+        element_manager.sort_elements();
+        let stored: Vec<String> = element_manager.all().into_iter().map(|e| e.id).collect();
+        assert_eq!(stored, vec!["d", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_fill_slots_by_key() {
+        let heading = Element::new("heading".to_string(), "heading".to_string(), "heading".to_string());
+        let field = Element::new("field".to_string(), "form_field".to_string(), "form_field".to_string());
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![heading, field])));
+
+        assert!(element_manager.set_slot_key("heading", "title"));
+        assert!(element_manager.set_slot_key("field", "customer"));
+
+        let slots: Vec<serde_json::Value> = serde_json::from_str(&element_manager.get_slots()).unwrap();
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots[0]["slotKey"], "title");
+        assert_eq!(slots[0]["elementId"], "heading");
+
+        assert!(element_manager.fill_slot("title", "Invoice #42"));
+        assert!(element_manager.fill_slot("customer", "Alice"));
+        assert!(!element_manager.fill_slot("missing", "x"));
+
+        let heading: Element = serde_json::from_str(&element_manager.get_element("heading")).unwrap();
+        assert_eq!(heading.content, "Invoice #42");
+
+        let field: Element = serde_json::from_str(&element_manager.get_element("field")).unwrap();
+        let form_data: serde_json::Value = serde_json::from_str(&field.content).unwrap();
+        assert_eq!(form_data["value"], "Alice");
+        assert_eq!(form_data["label"], "Label:");
+    }
+
+    #[test]
+    fn test_element_pooling_leaves_no_ghost_elements() {
+        let element_manager = ElementManager::new();
+        element_manager.set_element_pooling(true);
+
+        let mut used = element_manager.create("table", 10.0, 10.0);
+        assert!(element_manager.update_element_content(&used.id, "edited"));
+        assert!(element_manager.delete_element(&used.id));
+
+        for _ in 0..1000 {
+            let element = element_manager.create("paragraph", 5.0, 5.0);
+            assert!(element_manager.delete_element(&element.id));
+            assert!(element_manager.find(&element.id).is_none());
+        }
+        assert!(element_manager.all().is_empty());
+
+        // A recycled element is indistinguishable from a fresh one
+        used = element_manager.create("circle", 1.0, 2.0);
+        let mut fresh = Element::new(used.id.clone(), "circle".to_string(), "circle".to_string());
+        fresh.x = 1.0;
+        fresh.y = 2.0;
+        fresh.created_at = used.created_at;
+        assert_eq!(serde_json::to_value(&used).unwrap(), serde_json::to_value(&fresh).unwrap());
+        assert_eq!(element_manager.all().len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_overlaps_push() {
+        let element_manager = ElementManager::new();
+        let ids: Vec<String> = [(0.0, 0.0), (20.0, 10.0), (40.0, 20.0)]
+            .iter()
+            .map(|&(x, y)| {
+                let element = element_manager.create("text", x, y);
+                assert!(element_manager.update_element_size(&element.id, 100.0, 50.0));
+                element.id
+            })
+            .collect();
+        let before: Vec<(f64, f64)> = ids.iter().map(|id| element_manager.find(id).map(|e| (e.x, e.y)).unwrap()).collect();
+
+        let positions = element_manager.resolve_overlaps(&serde_json::to_string(&ids).unwrap(), "push");
+        assert_eq!(positions.len(), 3);
+
+        let elements: Vec<Element> = ids.iter().map(|id| element_manager.find(id).unwrap()).collect();
+        for a in 0..elements.len() {
+            for b in a + 1..elements.len() {
+                assert!(separation(&elements[a], &elements[b]).is_none(), "{} overlaps {}", elements[a].id, elements[b].id);
+            }
+        }
+        // Pushed apart vertically (the axis of least overlap), not scattered
+        let displacement: f64 = elements
+            .iter()
+            .zip(&before)
+            .map(|(element, (x, y))| (element.x - x).abs() + (element.y - y).abs())
+            .sum();
+        assert!(displacement <= 140.0, "moved {}px in total", displacement);
+
+        assert!(element_manager.resolve_overlaps(&serde_json::to_string(&ids).unwrap(), "scatter").is_empty());
+    }
+
+    #[test]
+    fn test_arrange_grid() {
+        let element_manager = ElementManager::new();
+        let sizes = [(100.0, 50.0), (80.0, 70.0), (60.0, 40.0), (120.0, 30.0), (90.0, 60.0), (50.0, 20.0)];
+        let ids: Vec<String> = sizes
+            .iter()
+            .map(|&(width, height)| {
+                let element = element_manager.create("button", 500.0, 500.0);
+                element_manager.update_element_size(&element.id, width, height);
+                element.id
+            })
+            .collect();
+
+        let ids_json = serde_json::to_string(&ids).unwrap();
+        let updated = element_manager.arrange_grid(&ids_json, 3, 10.0, 20.0, 40.0);
+        assert_eq!(updated, ids);
+
+        let positions: Vec<(f64, f64)> = ids
+            .iter()
+            .map(|id| element_manager.find(id).map(|e| (e.x, e.y)).unwrap())
+            .collect();
+        // Columns are 120, 90 and 60 wide; rows are 70 and 60 tall
+        assert_eq!(positions, vec![
+            (20.0, 40.0), (150.0, 40.0), (250.0, 40.0),
+            (20.0, 120.0), (150.0, 120.0), (250.0, 120.0),
+        ]);
+
+        assert!(element_manager.arrange_grid(&ids_json, 0, 10.0, 0.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_non_finite_position_and_size() {
+        let element_manager = ElementManager::new();
+        let element = element_manager.create("button", 40.0, 60.0);
+
+        assert!(!element_manager.update_element_position(&element.id, f64::NAN, 10.0));
+        assert!(!element_manager.update_element_position(&element.id, 10.0, f64::INFINITY));
+        assert!(!element_manager.update_element_size(&element.id, f64::NAN, f64::NEG_INFINITY));
+
+        let unchanged = element_manager.find(&element.id).unwrap();
+        assert_eq!((unchanged.x, unchanged.y), (40.0, 60.0));
+        assert_eq!((unchanged.width, unchanged.height), (element.width, element.height));
+    }
+
+    #[test]
+    fn test_validate_style_update_accepts_css_colors() {
+        let element_manager = ElementManager::new();
+        let validated: serde_json::Value = serde_json::from_str(&element_manager.validate_style_update(
+            r##"{"color":"#abc","backgroundColor":"rgba(0,0,0,0.5)","borderColor":"red"}"##,
+        ))
+        .unwrap();
+        assert_eq!(validated["color"], "#abc");
+        assert_eq!(validated["backgroundColor"], "rgba(0,0,0,0.5)");
+        assert_eq!(validated["borderColor"], "red");
+
+        let rejected = element_manager.validate_style_update(r#"{"color":"reddish","backgroundColor":"rgb(1,2)"}"#);
+        assert_eq!(rejected, "{}");
+    }
+
+    #[test]
+    fn test_create_with_explicit_id() {
+        let element_manager = ElementManager::new();
+        let element = element_manager.create_with_id("invoice-total", "table", 20.0, 30.0).unwrap();
+        assert_eq!(element.id, "invoice-total");
+        assert_eq!((element.x, element.y), (20.0, 30.0));
+        assert_eq!(element_manager.find("invoice-total").unwrap().table_data.unwrap().rows.len(), 3);
+
+        assert!(element_manager.create_with_id("invoice-total", "text", 0.0, 0.0).is_none());
+        assert!(element_manager.create_with_id("", "text", 0.0, 0.0).is_none());
+        assert_eq!(element_manager.get_element_count(), 1);
+    }
+
+    #[test]
+    fn test_get_all_elements_sorted_by_recency() {
+        let element_manager = ElementManager::new();
+        let first = element_manager.create("text", 0.0, 0.0);
+        let second = element_manager.create("button", 0.0, 0.0);
+        let third = element_manager.create("heading", 0.0, 0.0);
+        assert!(first.created_at > 0.0);
+
+        let ids = |json: String| -> Vec<String> {
+            let elements: Vec<Element> = serde_json::from_str(&json).unwrap();
+            elements.into_iter().map(|element| element.id).collect()
+        };
+        assert_eq!(ids(element_manager.get_all_elements_sorted("created_desc")), vec![third.id.clone(), second.id.clone(), first.id.clone()]);
+        assert_eq!(ids(element_manager.get_all_elements_sorted("type")), vec![second.id, third.id, first.id]);
+    }
+
+    #[test]
+    fn test_size_matches_nearby_element() {
+        let element_manager = ElementManager::new();
+        let reference = element_manager.create("button", 0.0, 0.0);
+        assert!(element_manager.update_element_size(&reference.id, 200.0, 60.0));
+        let resized = element_manager.create("button", 0.0, 100.0);
+        assert!(element_manager.set_size_match_tolerance(5.0));
+
+        assert!(element_manager.update_element_size(&resized.id, 198.0, 90.0));
+        let snapped = element_manager.find(&resized.id).unwrap();
+        assert_eq!((snapped.width, snapped.height), (200.0, 90.0));
+
+        assert!(element_manager.update_element_size(&resized.id, 190.0, 90.0));
+        assert_eq!(element_manager.find(&resized.id).unwrap().width, 190.0);
+        assert!(!element_manager.set_size_match_tolerance(f64::NAN));
+    }
+
+    #[test]
+    fn test_size_snap() {
+        let element_manager = ElementManager::new();
+        let element = element_manager.create("button", 0.0, 0.0);
+        assert!(element_manager.set_size_snap(8.0));
+
+        assert!(element_manager.update_element_size(&element.id, 123.0, 45.0));
+        let resized = element_manager.find(&element.id).unwrap();
+        assert_eq!((resized.width, resized.height), (120.0, 48.0));
+
+        assert!(element_manager.set_size_snap(0.0));
+        assert!(element_manager.update_element_size(&element.id, 123.0, 45.0));
+        let resized = element_manager.find(&element.id).unwrap();
+        assert_eq!((resized.width, resized.height), (123.0, 45.0));
+
+        assert!(!element_manager.set_size_snap(-8.0));
+    }
+}
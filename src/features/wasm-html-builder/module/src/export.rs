@@ -1,68 +1,177 @@
+use qrcode::{Color, QrCode};
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Mutex, Arc};
+use crate::barcode;
+use crate::chart;
 use crate::types::*;
+use crate::utils;
 
-/// HTML export module
-pub struct ExportManager {
-    elements: Arc<Mutex<Vec<Element>>>,
-    papers: Arc<Mutex<Vec<A4Paper>>>,
+/// Options accepted by the export functions (unknown fields are ignored)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    /// Render interactive controls as static markup for print/PDF output
+    pub print_mode: bool,
+    /// Web fonts embedded by `export_standalone_html`
+    pub fonts: Vec<EmbeddedFont>,
+    /// Values substituted for `{{key}}` placeholders, set by `export_html_with_context`
+    #[serde(skip)]
+    pub context: HashMap<String, String>,
+    /// Which page an element overlapping several papers is rendered on
+    pub assign_mode: PaperAssignMode,
+    /// Number interactive elements in reading order instead of using their `tab_index`
+    pub auto_tab_order: bool,
+    /// Render each paper's title as a header band at the top of its page
+    pub show_page_titles: bool,
+    /// Draw consecutive same-type shapes on the same layer into one shared `<svg>`
+    pub batch_shapes: bool,
+    /// Wrap case-insensitive matches in text and table content with `<mark>` (empty = off)
+    pub highlight_query: String,
+    /// Print bleed in millimetres added around each A4 page. Pages are laid out at A4
+    /// portrait, so the bleed is skipped when any paper has another size or orientation
+    pub bleed_mm: f64,
+    /// Draw crop marks at the page corners, inside the bleed
+    pub crop_marks: bool,
+    /// Outline the printable area with a dashed guide (not in print mode)
+    pub printable_guide: bool,
+    /// Override for the printer margin the guide is drawn at, in millimetres
+    /// (default: the engine's `set_printer_margin` value)
+    pub printer_margin_mm: Option<f64>,
+    /// Render enabled `fill`/`stroke` as background/border on non-shape elements too
+    pub apply_fill_stroke: bool,
+    /// Stack each page's elements in reading order (normal flow) instead of at their positions.
+    /// Element page-break flags only apply here: print engines ignore breaks on absolute boxes
+    pub flow_layout: bool,
 }
 
-impl ExportManager {
-    pub fn new(elements: Arc<Mutex<Vec<Element>>>, papers: Arc<Mutex<Vec<A4Paper>>>) -> Self {
-        Self { elements, papers }
+/// Page assignment for elements that overlap more than one paper
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaperAssignMode {
+    /// First overlapping paper
+    FirstPaper,
+    /// Paper containing the element's center, else the first overlapping paper
+    #[default]
+    CenterPaper,
+    /// Every overlapping paper (the element is duplicated)
+    AllPapers,
+}
+
+/// Base64-encoded web font inlined as an `@font-face` rule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedFont {
+    pub family: String,
+    pub data: String,
+    #[serde(default = "default_font_format")]
+    pub format: String,
+    #[serde(default)]
+    pub weight: Option<String>,
+    #[serde(default)]
+    pub style: Option<String>,
+}
+
+fn default_font_format() -> String {
+    "woff2".to_string()
+}
+
+fn is_base64(data: &str) -> bool {
+    !data.is_empty() && data.bytes().all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'='))
+}
+
+fn is_font_weight(weight: &str) -> bool {
+    matches!(weight, "normal" | "bold")
+        || (weight.len() == 3 && weight.ends_with("00") && matches!(weight.as_bytes()[0], b'1'..=b'9'))
+}
+
+impl EmbeddedFont {
+    /// `@font-face` rule, or `None` when any field could break out of the stylesheet
+    fn to_font_face(&self) -> Option<String> {
+        let family = self.family.replace(['"', '\\'], "");
+        let data = self.data.trim();
+        let weight = self.weight.as_deref().unwrap_or("normal");
+        let style = self.style.as_deref().unwrap_or("normal");
+        if family.trim().is_empty()
+            || family.contains(['<', '>'])
+            || !is_base64(data)
+            || !is_font_weight(weight)
+            || !matches!(style, "normal" | "italic" | "oblique")
+        {
+            return None;
+        }
+
+        let (mime, format) = match self.format.to_lowercase().as_str() {
+            "woff" => ("font/woff", "woff"),
+            "ttf" | "truetype" => ("font/ttf", "truetype"),
+            "otf" | "opentype" => ("font/otf", "opentype"),
+            _ => ("font/woff2", "woff2"),
+        };
+        Some(format!(
+            "@font-face {{\n    font-family: \"{}\";\n    src: url(\"data:{};base64,{}\") format(\"{}\");\n    font-weight: {};\n    font-style: {};\n}}\n",
+            family, mime, data, format, weight, style
+        ))
     }
+}
 
-    /// Clean and modernize HTML content (convert deprecated tags to modern HTML/CSS)
-    fn clean_html_content(&self, content: &str) -> String {
-        let mut cleaned = content.to_string();
-        
-        // Convert <font color="..."> to <span style="color: ...">
-        // Simple regex-like replacement for basic cases
-        while let Some(start_idx) = cleaned.find("<font color=\"") {
-            if let Some(color_start) = cleaned[start_idx..].find('"') {
-                let color_start_abs = start_idx + color_start + 1;
-                if let Some(color_end) = cleaned[color_start_abs..].find('"') {
-                    let color = &cleaned[color_start_abs..color_start_abs + color_end];
-                    if let Some(tag_end) = cleaned[start_idx..].find('>') {
-                        let tag_end_abs = start_idx + tag_end + 1;
-                        
-                        // Find matching </font>
-                        if let Some(close_tag_idx) = cleaned[tag_end_abs..].find("</font>") {
-                            let close_tag_abs = tag_end_abs + close_tag_idx;
-                            let inner_content = &cleaned[tag_end_abs..close_tag_abs].to_string();
-                            
-                            // Replace with modern span
-                            let new_tag = format!("<span style=\"color: {}\">{}</span>", color, inner_content);
-                            cleaned.replace_range(start_idx..close_tag_abs + 7, &new_tag);
-                        } else {
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
+impl ExportOptions {
+    pub fn from_json(options_json: &str) -> Self {
+        serde_json::from_str(options_json).unwrap_or_default()
+    }
+
+    /// Printer margin in millimetres, `default` when unset or invalid
+    fn printer_margin(&self, default: f64) -> f64 {
+        self.printer_margin_mm
+            .filter(|margin| margin.is_finite() && *margin >= 0.0)
+            .unwrap_or(default)
+    }
+
+    /// Bleed in millimetres, or `None` when unset, invalid or some paper is not A4 portrait
+    fn bleed(&self, papers: &[A4Paper]) -> Option<f64> {
+        let all_a4 = papers.iter().all(|paper| paper.size == PaperSize::A4 && paper.orientation == PaperOrientation::Portrait);
+        Some(self.bleed_mm).filter(|bleed| all_a4 && bleed.is_finite() && *bleed > 0.0)
+    }
+}
+
+/// Rendered document returned by the export functions
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    pub html: String,
+    pub css: String,
+    pub metadata: ExportMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportMetadata {
+    pub total_elements: usize,
+    pub total_pages: usize,
+    pub css_classes_count: usize,
+    pub export_timestamp: f64,
+    pub framework_used: String,
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// Problem found in the document while exporting (the export still succeeds)
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportWarning {
+    pub element_id: String,
+    pub kind: String,
+    pub message: String,
+}
+
+impl ExportWarning {
+    fn new(element: &Element, kind: &str, message: String) -> Self {
+        Self {
+            element_id: element.id.clone(),
+            kind: kind.to_string(),
+            message,
         }
-        
-        cleaned
     }
+}
 
-    /// Export HTML (complete implementation)
-    pub fn export_html(&self, _options_json: &str) -> String {
-        let elements = self.elements.lock().unwrap();
-        let papers = self.papers.lock().unwrap();
-        
-        // สร้าง HTML structure
-        let mut html = String::new();
-        let mut css = String::new();
-        
-        // CSS สำหรับ A4 papers
-        css.push_str("
+/// Stylesheet shared by every export (papers, element types and print rules)
+const BASE_CSS: &str = "
 /* Force print background colors and images */
 * {
     -webkit-print-color-adjust: exact !important;
@@ -89,6 +198,19 @@ impl ExportManager {
     page-break-after: always;
 }
 
+.page-title {
+    position: absolute;
+    top: 0;
+    left: 0;
+    right: 0;
+    padding: 8px 16px;
+    font-size: 14px;
+    font-weight: 600;
+    color: #374151;
+    background-color: #f3f4f6;
+    border-bottom: 1px solid #d1d5db;
+}
+
 .element {
     position: absolute;
     box-sizing: border-box;
@@ -106,7 +228,6 @@ impl ExportManager {
     display: flex;
     align-items: center;
     justify-content: center;
-    cursor: pointer;
     border: 1px solid #007bff;
     background: #007bff;
     color: white;
@@ -135,6 +256,11 @@ impl ExportManager {
     overflow-wrap: break-word;
 }
 
+.element-table td.table-total-cell {
+    border-top: 2px solid #6b7280;
+    font-variant-numeric: tabular-nums;
+}
+
 .form-field {
     display: flex;
     align-items: center;
@@ -168,6 +294,13 @@ impl ExportManager {
     pointer-events: none;
 }
 
+.printable-guide {
+    position: absolute;
+    box-sizing: border-box;
+    border: 1px dashed #93c5fd;
+    pointer-events: none;
+}
+
 .checkbox {
     display: inline-block;
     border: 1px solid #222;
@@ -183,6 +316,7 @@ impl ExportManager {
     }
     body { margin: 0; }
     .paper-container { padding: 0; background: white; }
+    .printable-guide { display: none; }
     .a4-paper { 
         width: 210mm; 
         min-height: 297mm; 
@@ -205,70 +339,707 @@ impl ExportManager {
         min-height: auto !important;
     }
 }
-        ");
+        ";
+
+/// Added to `BASE_CSS` unless `print_mode` is set: rules only clickable output needs
+const INTERACTIVE_CSS: &str = "
+.element-button {
+    cursor: pointer;
+}
+";
+
+/// Added to `BASE_CSS` for `flow_layout` exports: pages grow with their content and
+/// elements follow each other, so the page-break classes take effect when printed
+const FLOW_CSS: &str = "
+.flow-layout .a4-paper {
+    min-height: 0;
+    overflow: visible;
+    page-break-after: auto;
+}
+
+.flow-layout .element {
+    position: relative !important;
+    left: auto !important;
+    top: auto !important;
+}
+
+@media print {
+    .flow-layout .a4-paper { min-height: 0; page-break-after: auto; }
+    .page-break-before { break-before: page; page-break-before: always; }
+    .page-break-after { break-after: page; page-break-after: always; }
+    .page-break-inside-avoid { break-inside: avoid; page-break-inside: avoid; }
+}
+";
+
+/// Stylesheet of `export_table_html`: just the table rules of `BASE_CSS`
+const TABLE_CSS: &str = "
+body {
+    margin: 16px;
+    font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif;
+}
+
+.element-table {
+    border-collapse: collapse;
+    table-layout: auto;
+}
+
+.element-table th,
+.element-table td {
+    border: 1px solid #ccc;
+    padding: 4px 8px;
+    text-align: left;
+    vertical-align: top;
+    word-wrap: break-word;
+    overflow-wrap: break-word;
+}
+
+.element-table td.table-total-cell {
+    border-top: 2px solid #6b7280;
+    font-variant-numeric: tabular-nums;
+}
+
+@media print {
+    * {
+        -webkit-print-color-adjust: exact !important;
+        print-color-adjust: exact !important;
+    }
+    body { margin: 0; }
+}
+";
+
+/// Markup bytes of a blank element of one type, measured from the export templates
+struct MarkupCost {
+    fixed: usize,
+    /// Bytes each content byte adds (text is copied through, symbols grow with their data)
+    per_content_byte: f64,
+    /// Bytes each blank table cell adds
+    per_cell: usize,
+}
+
+impl MarkupCost {
+    fn estimate(&self, element: &Element) -> usize {
+        match &element.table_data {
+            Some(table_data) => {
+                let cells = table_data.rows.iter().flat_map(|row| &row.cells);
+                let content: usize = cells.clone().map(|cell| cell.content.len()).sum();
+                self.fixed + self.per_cell * cells.count() + content
+            }
+            None => self.fixed + (self.per_content_byte * element.content.len() as f64).round() as usize,
+        }
+    }
+}
+
+/// HTML export module
+pub struct ExportManager {
+    elements: Arc<Mutex<Vec<Element>>>,
+    papers: Arc<Mutex<Vec<A4Paper>>>,
+    printer_margin_mm: Arc<Mutex<f64>>,
+}
+
+impl ExportManager {
+    pub fn new(elements: Arc<Mutex<Vec<Element>>>, papers: Arc<Mutex<Vec<A4Paper>>>) -> Self {
+        Self { elements, papers, printer_margin_mm: Arc::new(Mutex::new(DEFAULT_PRINTER_MARGIN_MM)) }
+    }
+
+    /// Draw the printable-area guide at a shared printer margin (see `PaperManager::printer_margin_handle`)
+    pub fn with_printer_margin(mut self, printer_margin_mm: Arc<Mutex<f64>>) -> Self {
+        self.printer_margin_mm = printer_margin_mm;
+        self
+    }
+
+    /// Clean and modernize HTML content (convert deprecated tags to modern HTML/CSS)
+    fn clean_html_content(&self, content: &str) -> String {
+        let mut cleaned = content.to_string();
         
-        // HTML structure
-        html.push_str("<div class=\"paper-container\">\n");
+        // Convert <font color="..."> to <span style="color: ...">
+        // Simple regex-like replacement for basic cases
+        while let Some(start_idx) = cleaned.find("<font color=\"") {
+            if let Some(color_start) = cleaned[start_idx..].find('"') {
+                let color_start_abs = start_idx + color_start + 1;
+                if let Some(color_end) = cleaned[color_start_abs..].find('"') {
+                    let color = &cleaned[color_start_abs..color_start_abs + color_end];
+                    if let Some(tag_end) = cleaned[start_idx..].find('>') {
+                        let tag_end_abs = start_idx + tag_end + 1;
+                        
+                        // Find matching </font>
+                        if let Some(close_tag_idx) = cleaned[tag_end_abs..].find("</font>") {
+                            let close_tag_abs = tag_end_abs + close_tag_idx;
+                            let inner_content = &cleaned[tag_end_abs..close_tag_abs].to_string();
+                            
+                            // Replace with modern span
+                            let new_tag = format!("<span style=\"color: {}\">{}</span>", color, inner_content);
+                            cleaned.replace_range(start_idx..close_tag_abs + 7, &new_tag);
+                        } else {
+                            break;
+                        }
+                    } else {
+                        break;
+                    }
+                } else {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
         
-        // สร้าง A4 papers
-        for (page_index, paper) in papers.iter().enumerate() {
+        cleaned
+    }
+
+    /// Export HTML (complete implementation)
+    pub fn export_html(&self, options_json: &str) -> String {
+        self.export_with_options(&ExportOptions::from_json(options_json))
+    }
+
+    /// Export HTML for print/PDF: buttons and inputs become static elements
+    pub fn export_print_html(&self, options_json: &str) -> String {
+        let mut options = ExportOptions::from_json(options_json);
+        options.print_mode = true;
+        self.export_with_options(&options)
+    }
+
+    /// Export HTML with `{{key}}` placeholders resolved from a context map (stored content is unchanged)
+    pub fn export_html_with_context(&self, options_json: &str, context_json: &str) -> String {
+        let mut options = ExportOptions::from_json(options_json);
+        let context: HashMap<String, serde_json::Value> =
+            serde_json::from_str(context_json).unwrap_or_default();
+        options.context = context
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(text) => (key, text),
+                other => (key, other.to_string()),
+            })
+            .collect();
+        self.export_with_options(&options)
+    }
+
+    /// Export a complete HTML document with CSS and fonts inlined, for archival
+    pub fn export_standalone_html(&self, options_json: &str) -> String {
+        let options = ExportOptions::from_json(options_json);
+        let result = self.render(&options);
+
+        let font_faces: String = options.fonts.iter().filter_map(EmbeddedFont::to_font_face).collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n<title>Exported Document</title>\n<style>\n{}{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            font_faces, result.css, result.html
+        )
+    }
+
+    /// Export one table as a standalone HTML document, keeping cell styles and merges; empty when not a table
+    pub fn export_table_html(&self, element_id: &str) -> String {
+        let elements = utils::lock(&self.elements);
+        let Some(table) = elements
+            .iter()
+            .find(|element| element.id == element_id && element.element_type == "table" && element.table_data.is_some())
+        else {
+            return String::new();
+        };
+
+        // No page position: the table flows at the top of the document
+        let mut base_style = String::from("position: static;");
+        if let Some(direction) = table.style.direction.as_deref().filter(|direction| TEXT_DIRECTIONS.contains(direction)) {
+            base_style.push_str(&format!(" direction: {};", direction));
+        }
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            self.escape_html(&table.id),
+            TABLE_CSS,
+            self.generate_table_html(table, &base_style, &ExportOptions::default(), &elements, &[])
+        )
+    }
+
+    /// Export for email clients: each page becomes a positioning-free layout table with inline styles only
+    pub fn export_email_html(&self) -> String {
+        let elements = utils::lock(&self.elements);
+        let papers = utils::lock(&self.papers);
+        let options = ExportOptions { print_mode: true, ..ExportOptions::default() };
+
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n</head>\n<body style=\"margin: 0; padding: 0; background-color: #f5f5f5;\">\n",
+        );
+        // Elements placed in table cells are rendered inside their cell, not as bands of their own
+        let nested_ids = nested_element_ids(&elements);
+        for page_index in 0..papers.len() {
+            let paper = &papers[page_index];
+            let mut page_elements: Vec<&Element> = elements
+                .iter()
+                .filter(|element| element.visible && !nested_ids.contains(element.id.as_str()))
+                .filter(|element| is_element_on_page(element, page_index, &papers, options.assign_mode))
+                .collect();
+            page_elements.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+            // Elements whose vertical extents overlap share a band (one table row)
+            let mut bands: Vec<(f64, f64, Vec<&Element>)> = Vec::new();
+            for element in page_elements {
+                match bands.last_mut() {
+                    Some((_, bottom, members)) if element.y < *bottom => {
+                        *bottom = bottom.max(element.y + element.height);
+                        members.push(element);
+                    }
+                    _ => bands.push((element.y, element.y + element.height, vec![element])),
+                }
+            }
+
             html.push_str(&format!(
-                "  <div class=\"a4-paper\" id=\"page-{}\">\n",
-                page_index + 1
+                "<table role=\"presentation\" width=\"{0}\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\" align=\"center\" style=\"width: {0}px; background-color: #ffffff; border-collapse: collapse;\">\n",
+                paper.width
             ));
-            
-            // หา elements ที่อยู่ในหน้านี้
-            let page_elements: Vec<&Element> = elements.iter()
-                .filter(|element| self.is_element_in_paper(element, paper))
-                .collect();
-            
-            // เรียง elements ตาม z-index
-            let mut sorted_elements = page_elements;
-            sorted_elements.sort_by(|a, b| a.z_index.cmp(&b.z_index));
-            
-            // สร้าง HTML สำหรับแต่ละ element
-            for element in sorted_elements {
-                html.push_str(&self.generate_element_html(element, paper));
+            let mut cursor_y = paper.y;
+            for (top, bottom, mut members) in bands {
+                if top > cursor_y {
+                    html.push_str(&format!(
+                        "  <tr><td height=\"{0}\" style=\"height: {0}px; font-size: 0; line-height: 0;\">&nbsp;</td></tr>\n",
+                        top - cursor_y
+                    ));
+                }
+                members.sort_by(|a, b| a.x.total_cmp(&b.x));
+                html.push_str("  <tr><td valign=\"top\">\n    <table role=\"presentation\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\" style=\"border-collapse: collapse;\"><tr>\n");
+                let mut cursor_x = paper.x;
+                for element in members {
+                    if element.x > cursor_x {
+                        html.push_str(&format!(
+                            "      <td width=\"{0}\" style=\"width: {0}px; font-size: 0; line-height: 0;\">&nbsp;</td>\n",
+                            element.x - cursor_x
+                        ));
+                    }
+                    html.push_str(&self.generate_email_cell_html(element, &elements));
+                    cursor_x = cursor_x.max(element.x + element.width);
+                }
+                html.push_str("    </tr></table>\n  </td></tr>\n");
+                cursor_y = cursor_y.max(bottom);
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Markup of one element at (0, 0) inside a wrapper sized to it, for previews; empty when the element is missing
+    pub fn export_element_html(&self, element_id: &str) -> String {
+        let elements = utils::lock(&self.elements);
+        let Some(element) = elements.iter().find(|element| element.id == element_id) else {
+            return String::new();
+        };
+
+        // A paper at the element's origin puts it at (0, 0)
+        let origin = Paper::new(String::new(), PaperSize::A4, PaperOrientation::Portrait, element.x, element.y);
+        format!(
+            "<div class=\"element-fragment\" style=\"position: relative; width: {}px; height: {}px;\">\n{}</div>\n",
+            element.width,
+            element.height,
+            self.generate_element_html(element, &origin, &ExportOptions::default(), &elements, &[])
+        )
+    }
+
+    /// Number of pages `export_html_page` can export
+    pub fn get_export_page_count(&self) -> usize {
+        utils::lock(&self.papers).len()
+    }
+
+    /// Markup of one page, for streaming large documents page by page; concatenated in order
+    /// inside the paper container they give `export_html`'s HTML, styled by `get_export_css`.
+    /// Empty when out of range
+    pub fn export_html_page(&self, page_index: usize, options_json: &str) -> String {
+        let options = ExportOptions::from_json(options_json);
+        let elements = utils::lock(&self.elements);
+        let papers = utils::lock(&self.papers);
+        if page_index >= papers.len() {
+            return String::new();
+        }
+        let tab_order = self.tab_order(&options, &elements, &papers);
+        self.page_html(page_index, &options, &elements, &papers, &tab_order, &nested_element_ids(&elements))
+    }
+
+    /// Stylesheet every export starts from (papers, element types and print rules), for styling a live editor
+    pub fn get_base_css(&self) -> String {
+        format!("{}{}", BASE_CSS, INTERACTIVE_CSS)
+    }
+
+    /// Stylesheet `export_html` returns for these options (print mode, bleed and layout rules included)
+    pub fn get_export_css(&self, options_json: &str) -> String {
+        export_css(&ExportOptions::from_json(options_json), &utils::lock(&self.papers))
+    }
+
+    /// Approximate `{html, css, total}` byte counts of an export, without rendering it.
+    /// Page wrappers and the stylesheet are exact; each element is costed from a blank sample
+    /// of its type rendered once, so the estimate follows the markup templates
+    pub fn estimate_export_size(&self, options_json: &str) -> String {
+        let options = ExportOptions::from_json(options_json);
+        let elements = utils::lock(&self.elements);
+        let papers = utils::lock(&self.papers);
+        let nested_ids = nested_element_ids(&elements);
+
+        let mut costs: HashMap<&str, MarkupCost> = HashMap::new();
+        let mut html = container_open(&options).len() + "</div>\n".len();
+        for (page_index, paper) in papers.iter().enumerate() {
+            html += self.page_html(page_index, &options, &[], &papers, &HashMap::new(), &HashSet::new()).len();
+            for element in elements
+                .iter()
+                .filter(|element| !nested_ids.contains(element.id.as_str()))
+                .filter(|element| is_element_on_page(element, page_index, &papers, options.assign_mode))
+            {
+                html += costs
+                    .entry(element.element_type.as_str())
+                    .or_insert_with(|| self.markup_cost(&element.element_type, paper, &options))
+                    .estimate(element);
             }
-            
-            html.push_str("  </div>\n");
         }
+        let css = export_css(&options, &papers).len();
+
+        serde_json::json!({ "html": html, "css": css, "total": html + css }).to_string()
+    }
+
+    /// Cost of one element type, from blank samples: with and without content, or with 1 and 4 table cells
+    fn markup_cost(&self, element_type: &str, paper: &A4Paper, options: &ExportOptions) -> MarkupCost {
+        let sample = |content: &str, table_size: Option<u32>| {
+            let mut element = Element::new("sample".to_string(), element_type.to_string(), element_type.to_string());
+            element.x = paper.x;
+            element.y = paper.y;
+            element.content = content.to_string();
+            if let Some(size) = table_size {
+                element.create_default_table(size, size);
+                let cells = element.table_data.iter_mut().flat_map(|table_data| &mut table_data.rows).flat_map(|row| &mut row.cells);
+                cells.for_each(|cell| cell.content.clear());
+            }
+            self.generate_element_html(&element, paper, options, &[], &[]).len()
+        };
+
+        if element_type == "table" {
+            let (one_cell, four_cells) = (sample("", Some(1)), sample("", Some(2)));
+            let per_cell = four_cells.saturating_sub(one_cell) / 3;
+            return MarkupCost { fixed: one_cell.saturating_sub(per_cell), per_content_byte: 1.0, per_cell };
+        }
+        let default_content = Element::new(String::new(), element_type.to_string(), element_type.to_string()).content;
+        let blank = sample("", None);
+        let per_content_byte = if default_content.is_empty() {
+            1.0
+        } else {
+            sample(&default_content, None).saturating_sub(blank) as f64 / default_content.len() as f64
+        };
+        MarkupCost { fixed: blank, per_content_byte, per_cell: 0 }
+    }
+
+    /// Cover-sheet list of page titles with their page numbers (untitled pages are skipped)
+    pub fn generate_toc(&self) -> String {
+        let papers = utils::lock(&self.papers);
+
+        let mut html = String::from("<ol class=\"toc\">\n");
+        for (page_index, paper) in papers.iter().enumerate() {
+            if let Some(title) = paper.title.as_deref().filter(|title| !title.trim().is_empty()) {
+                html.push_str(&format!(
+                    "  <li class=\"toc-entry\"><a href=\"#page-{0}\"><span class=\"toc-title\">{1}</span><span class=\"toc-page\">{0}</span></a></li>\n",
+                    page_index + 1,
+                    self.escape_html(title)
+                ));
+            }
+        }
+        html.push_str("</ol>\n");
+        html
+    }
+
+    /// Bounding box of each page's visible elements (clipped to the paper), or the paper bounds when empty
+    pub fn get_page_content_bounds(&self) -> String {
+        let elements = utils::lock(&self.elements);
+        let papers = utils::lock(&self.papers);
+        let options = ExportOptions::default();
+
+        let pages: Vec<serde_json::Value> = papers
+            .iter()
+            .enumerate()
+            .map(|(page_index, paper)| {
+                let (paper_right, paper_bottom) = (paper.x + paper.width, paper.y + paper.height);
+                let corners: Vec<(f64, f64)> = elements
+                    .iter()
+                    .filter(|element| element.visible && is_element_on_page(element, page_index, &papers, options.assign_mode))
+                    .flat_map(|element| {
+                        [
+                            (element.x.max(paper.x), element.y.max(paper.y)),
+                            ((element.x + element.width).min(paper_right), (element.y + element.height).min(paper_bottom)),
+                        ]
+                    })
+                    .collect();
+                let content = utils::calculate_bounds(&corners);
+                serde_json::json!({
+                    "paperId": paper.id,
+                    "pageNumber": page_index + 1,
+                    "isEmpty": content.is_none(),
+                    "bounds": content.unwrap_or_else(|| paper.get_bounds()),
+                })
+            })
+            .collect();
+        serde_json::to_string(&pages).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn export_with_options(&self, options: &ExportOptions) -> String {
+        serde_json::to_string(&self.render(options)).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Render papers and their elements to HTML and CSS
+    pub fn render(&self, options: &ExportOptions) -> ExportResult {
+        let elements = utils::lock(&self.elements);
+        let papers = utils::lock(&self.papers);
         
-        html.push_str("</div>\n");
+        // สร้าง HTML structure
+        let mut html = String::new();
+        let mut css = String::new();
         
-        let timestamp = js_sys::Date::now();
+        // CSS สำหรับ A4 papers
+        css.push_str(&export_css(options, &papers));
         
-        let result = format!(
-            r#"{{"html":"{}","css":"{}","metadata":{{"total_elements":{},"total_pages":{},"css_classes_count":10,"export_timestamp":{},"framework_used":"None"}}}}"#,
-            html.replace('"', "\\\"").replace('\n', "\\n"),
-            css.replace('"', "\\\"").replace('\n', "\\n"),
-            elements.len(),
-            papers.len(),
-            timestamp
-        );
+        let tab_order = self.tab_order(options, &elements, &papers);
+        let nested_ids = nested_element_ids(&elements);
+
+        // HTML structure
+        html.push_str(container_open(options));
         
-        result
+        // สร้าง A4 papers
+        for page_index in 0..papers.len() {
+            html.push_str(&self.page_html(page_index, options, &elements, &papers, &tab_order, &nested_ids));
+        }
+        
+        html.push_str("</div>\n");
+        
+        ExportResult {
+            html,
+            css,
+            metadata: ExportMetadata {
+                total_elements: elements.len(),
+                total_pages: papers.len(),
+                css_classes_count: 10,
+                export_timestamp: utils::now_ms(),
+                framework_used: "None".to_string(),
+                warnings: self.collect_warnings(&elements, &papers),
+            },
+        }
     }
 
-    /// ตรวจสอบว่า element อยู่ในขอบเขตของ paper หรือไม่
-    fn is_element_in_paper(&self, element: &Element, paper: &A4Paper) -> bool {
-        let element_right = element.x + element.width;
-        let element_bottom = element.y + element.height;
-        let paper_right = paper.x + paper.width;
-        let paper_bottom = paper.y + paper.height;
+    /// Markup of one page (`<div class="a4-paper">` and its elements); `tab_order` and
+    /// `nested_ids` are computed over the whole document so pages match the full export
+    fn page_html(
+        &self,
+        page_index: usize,
+        options: &ExportOptions,
+        elements: &[Element],
+        papers: &[A4Paper],
+        tab_order: &HashMap<String, i32>,
+        nested_ids: &HashSet<&str>,
+    ) -> String {
+        let paper = &papers[page_index];
+        let mut html = String::new();
+        html.push_str(&format!(
+            "  <div class=\"a4-paper\" id=\"page-{}\">\n",
+            page_index + 1
+        ));
+
+        if options.show_page_titles {
+            if let Some(title) = paper.title.as_deref().filter(|title| !title.trim().is_empty()) {
+                html.push_str(&format!("    <div class=\"page-title\">{}</div>\n", self.escape_html(title)));
+            }
+        }
+        if options.crop_marks {
+            if let Some(bleed) = options.bleed(papers) {
+                html.push_str(&crop_marks_html(bleed));
+            }
+        }
+        if options.printable_guide && !options.print_mode {
+            let printable = paper.printable_area(options.printer_margin(*utils::lock(&self.printer_margin_mm)));
+            html.push_str(&format!(
+                "    <div class=\"printable-guide\" style=\"left: {}px; top: {}px; width: {}px; height: {}px;\"></div>\n",
+                printable.x - paper.x, printable.y - paper.y, printable.width, printable.height
+            ));
+        }
+        
+        // หา elements ที่อยู่ในหน้านี้
+        let page_elements: Vec<&Element> = elements.iter()
+            .filter(|element| !nested_ids.contains(element.id.as_str()))
+            .filter(|element| is_element_on_page(element, page_index, papers, options.assign_mode))
+            .collect();
+        
+        // เรียง elements ตาม z-index แล้วตาม id (flow layout เรียงตามลำดับการอ่าน)
+        let mut sorted_elements = page_elements;
+        if options.flow_layout {
+            sorted_elements.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+        } else {
+            sorted_elements.sort_by(|a, b| a.stable_cmp(b));
+        }
         
-        // Element overlaps with paper if it's within the paper boundaries
-        element.x < paper_right && element_right > paper.x &&
-        element.y < paper_bottom && element_bottom > paper.y
+        // สร้าง HTML สำหรับแต่ละ element
+        let mut index = 0;
+        while index < sorted_elements.len() {
+            if options.batch_shapes && !options.flow_layout {
+                let shapes = batched_shape_run(&sorted_elements[index..], paper);
+                if shapes.len() > 1 {
+                    html.push_str(&format!(
+                        "    <svg class=\"element element-shapes\" style=\"left: 0px; top: 0px; width: {}px; height: {}px; z-index: {}; overflow: visible;\">\n",
+                        paper.width, paper.height, sorted_elements[index].z_index
+                    ));
+                    for shape in &shapes {
+                        html.push_str(&format!("        {}\n", shape));
+                    }
+                    html.push_str("    </svg>\n");
+                    index += shapes.len();
+                    continue;
+                }
+            }
+            let element = sorted_elements[index];
+            index += 1;
+            let element_html = if options.context.is_empty() {
+                self.generate_element_html(element, paper, options, elements, &[])
+            } else {
+                let resolved = self.resolve_placeholders(element, &options.context);
+                self.generate_element_html(&resolved, paper, options, elements, &[])
+            };
+            let tab_index = if options.auto_tab_order {
+                tab_order.get(&element.id).copied()
+            } else {
+                element.tab_index.filter(|_| element.is_interactive() && !options.print_mode)
+            };
+            match tab_index {
+                Some(tab_index) => html.push_str(&with_tab_index(&element_html, tab_index)),
+                None => html.push_str(&element_html),
+            }
+        }
+        
+        html.push_str("  </div>\n");
+        html
     }
 
-    fn generate_element_html(&self, element: &Element, paper: &A4Paper) -> String {
+    /// Tab indexes assigned in reading order, when the options ask for it
+    fn tab_order(&self, options: &ExportOptions, elements: &[Element], papers: &[A4Paper]) -> HashMap<String, i32> {
+        if options.auto_tab_order && !options.print_mode {
+            self.reading_order_tab_indexes(elements, papers, options.assign_mode)
+        } else {
+            HashMap::new()
+        }
+    }
+
+    /// Content, colors and placement that will not export as the user expects
+    fn collect_warnings(&self, elements: &[Element], papers: &[A4Paper]) -> Vec<ExportWarning> {
+        let mut warnings = Vec::new();
+
+        for element in elements {
+            if matches!(element.element_type.as_str(), "form_field" | "checkbox" | "line")
+                && serde_json::from_str::<serde_json::Value>(&element.content).is_err()
+            {
+                warnings.push(ExportWarning::new(
+                    element,
+                    "invalid_content",
+                    format!("{} content is not valid JSON; defaults are used", element.element_type),
+                ));
+            }
+
+            if element.element_type == "qrcode" && QrCode::new(element.content.as_bytes()).is_err() {
+                warnings.push(ExportWarning::new(
+                    element,
+                    "invalid_content",
+                    "QR code payload is too long to encode".to_string(),
+                ));
+            }
+
+            if element.element_type == "barcode" && barcode::barcode_svg(&element.content).is_none() {
+                warnings.push(ExportWarning::new(
+                    element,
+                    "invalid_content",
+                    "barcode data cannot be encoded as Code128".to_string(),
+                ));
+            }
+
+            if element.element_type == "chart" && chart::chart_svg(&element.content, elements).is_none() {
+                warnings.push(ExportWarning::new(
+                    element,
+                    "invalid_content",
+                    "chart source table or column has no numeric data".to_string(),
+                ));
+            }
+
+            let colors = [
+                ("color", &element.style.color),
+                ("backgroundColor", &element.style.background_color),
+                ("borderColor", &element.style.border_color),
+            ];
+            for (property, color) in colors {
+                let allowed = color.is_empty() || utils::is_valid_css_color(color);
+                if !allowed {
+                    warnings.push(ExportWarning::new(
+                        element,
+                        "invalid_color",
+                        format!("{} \"{}\" is not a valid CSS color", property, color),
+                    ));
+                }
+            }
+
+            if let Some(table_data) = &element.table_data {
+                for (row_index, row) in table_data.rows.iter().enumerate() {
+                    if row.cells.len() != table_data.columns {
+                        warnings.push(ExportWarning::new(
+                            element,
+                            "inconsistent_table",
+                            format!("row {} has {} cells, expected {}", row_index, row.cells.len(), table_data.columns),
+                        ));
+                    }
+                }
+            }
+
+            if !papers.is_empty() && !papers.iter().any(|paper| overlaps_paper(element, paper)) {
+                warnings.push(ExportWarning::new(
+                    element,
+                    "outside_paper",
+                    "element is outside every paper and is not exported".to_string(),
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// Copy of the element with placeholders in its text content and table cells substituted
+    fn resolve_placeholders(&self, element: &Element, context: &HashMap<String, String>) -> Element {
+        let mut resolved = element.clone();
+        // form_field, checkbox and line keep structured JSON in their content
+        if !matches!(element.element_type.as_str(), "form_field" | "checkbox" | "line") {
+            // Rich text is emitted unescaped, so the substituted values must be escaped
+            let is_rich = resolved.content.contains('<') && resolved.content.contains('>');
+            resolved.content = substitute_placeholders(&resolved.content, context, |value| {
+                if is_rich { self.escape_html(value) } else { value.to_string() }
+            });
+        }
+        if let Some(table_data) = resolved.table_data.as_mut() {
+            for cell in table_data.rows.iter_mut().flat_map(|row| row.cells.iter_mut()) {
+                cell.content = substitute_placeholders(&cell.content, context, str::to_string);
+            }
+        }
+        resolved
+    }
+
+    /// Tab indexes (from 1) for interactive elements: page by page, top-to-bottom, then left-to-right
+    fn reading_order_tab_indexes(&self, elements: &[Element], papers: &[A4Paper], mode: PaperAssignMode) -> HashMap<String, i32> {
+        let mut tab_order = HashMap::new();
+        for page_index in 0..papers.len() {
+            let mut page_elements: Vec<&Element> = elements
+                .iter()
+                .filter(|element| element.is_interactive() && is_element_on_page(element, page_index, papers, mode))
+                .collect();
+            page_elements.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+            for element in page_elements {
+                let next = tab_order.len() as i32 + 1;
+                tab_order.entry(element.id.clone()).or_insert(next);
+            }
+        }
+        tab_order
+    }
+
+    /// `ancestors` are the tables this element is nested in through their cells
+    fn generate_element_html(&self, element: &Element, paper: &A4Paper, options: &ExportOptions, elements: &[Element], ancestors: &[&str]) -> String {
         let mut html = String::new();
         
         // คำนวณตำแหน่งสัมพันธ์กับ paper
         let relative_x = element.x - paper.x;
         let relative_y = element.y - paper.y;
         
+        let direction = element.style.direction.as_deref().filter(|direction| TEXT_DIRECTIONS.contains(direction));
+        let text_align = directional_text_align(&element.style.text_align, direction);
+
         // สร้าง style string โดยไม่มี border (เว้นแต่จะเป็น button หรือ input)
         let has_border = matches!(element.element_type.as_str(), "button" | "input");
         
@@ -281,7 +1052,7 @@ impl ExportManager {
                 relative_x, relative_y, element.width, element.height, element.z_index,
                 element.style.font_size, element.style.font_family, element.style.font_weight,
                 element.style.font_style, element.style.color, element.style.background_color,
-                element.style.text_align, element.style.padding, element.style.border_radius,
+                text_align, element.style.padding, element.style.border_radius,
                 element.style.border_width, element.style.border_color
             )
         } else {
@@ -293,19 +1064,49 @@ impl ExportManager {
                 relative_x, relative_y, element.width, element.height, element.z_index,
                 element.style.font_size, element.style.font_family, element.style.font_weight,
                 element.style.font_style, element.style.color, element.style.background_color,
-                element.style.text_align, element.style.padding, element.style.border_radius
+                text_align, element.style.padding, element.style.border_radius
             )
         };
+        let style = match element.style.blend_mode.as_deref() {
+            Some(blend_mode) if is_valid_blend_mode(blend_mode) => {
+                format!("{} mix-blend-mode: {};", style, blend_mode)
+            }
+            _ => style,
+        };
+        let style = match direction {
+            Some(direction) => format!("{} direction: {};", style, direction),
+            None => style,
+        };
+        let style = if matches!(
+            element.element_type.as_str(),
+            "text" | "heading" | "paragraph" | "button" | "input" | "form_field" | "checkbox" | "table"
+        ) {
+            format!("{}{}", style, typography_css(&element.style))
+        } else {
+            style
+        };
+        let style = match element.style.overflow.as_deref() {
+            Some("ellipsis") => format!("{} overflow: hidden; text-overflow: ellipsis; white-space: nowrap;", style),
+            Some(overflow) if OVERFLOW_MODES.contains(&overflow) => format!("{} overflow: {};", style, overflow),
+            _ => style,
+        };
+        // Declared last so they override background-color and border above
+        let style = if options.apply_fill_stroke && !element.is_shape() {
+            format!("{}{}", style, fill_stroke_css(&element.style))
+        } else {
+            style
+        };
 
         // Helper to handle rich text content
         let get_content = |content: &str| -> String {
-            if content.contains('<') && content.contains('>') {
+            let content = if content.contains('<') && content.contains('>') {
                 // Already contains HTML, clean and modernize it
                 self.clean_html_content(content)
             } else {
                 // Plain text, escape it
                 self.escape_html(content)
-            }
+            };
+            highlight_matches(&content, &self.escape_html(&options.highlight_query))
         };
 
         match element.element_type.as_str() {
@@ -327,6 +1128,18 @@ impl ExportManager {
                     style, get_content(&element.content)
                 ));
             }
+            "button" if options.print_mode => {
+                html.push_str(&format!(
+                    "    <div class=\"element element-button\" style=\"{}\">{}</div>\n",
+                    style, get_content(&element.content)
+                ));
+            }
+            "input" if options.print_mode => {
+                html.push_str(&format!(
+                    "    <span class=\"element element-input\" style=\"{}\">{}</span>\n",
+                    style, self.escape_html(&element.content)
+                ));
+            }
             "button" => {
                 html.push_str(&format!(
                     "    <button class=\"element element-button\" style=\"{}\">{}</button>\n",
@@ -339,8 +1152,20 @@ impl ExportManager {
                     self.escape_html(&element.content), style
                 ));
             }
+            "qrcode" => {
+                html.push_str(&self.generate_qrcode_html(element, &style));
+            }
+            "barcode" => {
+                html.push_str(&self.generate_barcode_html(element, &style));
+            }
+            "chart" => {
+                html.push_str(&self.generate_chart_html(element, &style, elements));
+            }
+            "image" => {
+                html.push_str(&self.generate_image_html(element, &style));
+            }
             "table" => {
-                html.push_str(&self.generate_table_html(element, &style));
+                html.push_str(&self.generate_table_html(element, &style, options, elements, ancestors));
             }
             "form_field" => {
                 html.push_str(&self.generate_form_field_html(element, &style));
@@ -365,17 +1190,176 @@ impl ExportManager {
                 ));
             }
         }
-        
-        html
+        
+        let page_break_classes = element.page_break_classes();
+        if !options.flow_layout || page_break_classes.is_empty() {
+            html
+        } else {
+            with_extra_classes(&html, &page_break_classes.join(" "))
+        }
+    }
+
+    fn generate_image_html(&self, element: &Element, base_style: &str) -> String {
+        let src = element.content.trim();
+        if is_image_src(src) {
+            format!(
+                "    <img class=\"element element-image\" src=\"{}\" alt=\"\" style=\"{} object-fit: contain;\" />\n",
+                self.escape_html(src), base_style
+            )
+        } else {
+            // No usable source yet: keep the placeholder box
+            format!(
+                "    <div class=\"element element-image\" style=\"{}\">{}</div>\n",
+                base_style, self.escape_html(&element.content)
+            )
+        }
+    }
+
+    fn generate_qrcode_html(&self, element: &Element, base_style: &str) -> String {
+        match qr_code_svg(&element.content) {
+            Some(svg) => format!(
+                "    <div class=\"element element-qrcode\" style=\"{}\">{}</div>\n",
+                base_style, svg
+            ),
+            // Payload too long for any QR version
+            None => format!(
+                "    <div class=\"element element-qrcode\" style=\"{}\">{}</div>\n",
+                base_style, self.escape_html(&element.content)
+            ),
+        }
+    }
+
+    fn generate_barcode_html(&self, element: &Element, base_style: &str) -> String {
+        match barcode::barcode_svg(&element.content) {
+            Some(svg) => format!(
+                "    <div class=\"element element-barcode\" style=\"{}\">{}</div>\n",
+                base_style, svg
+            ),
+            // Unsupported symbology or characters: show the data instead of bars
+            None => format!(
+                "    <div class=\"element element-barcode\" style=\"{}\">{}</div>\n",
+                base_style, self.escape_html(&element.content)
+            ),
+        }
+    }
+
+    /// Chart SVG is computed from the source table on every export, so it follows table edits
+    fn generate_chart_html(&self, element: &Element, base_style: &str, elements: &[Element]) -> String {
+        match chart::chart_svg(&element.content, elements) {
+            Some(svg) => format!(
+                "    <div class=\"element element-chart\" style=\"{}\">{}</div>\n",
+                base_style, svg
+            ),
+            // Missing source table or no numeric data in the column
+            None => format!(
+                "    <div class=\"element element-chart\" style=\"{}\">{}</div>\n",
+                base_style, self.escape_html(&element.content)
+            ),
+        }
+    }
+
+    /// Table cell for the email layout: inline styles only, no positioning, flexbox or SVG
+    fn generate_email_cell_html(&self, element: &Element, elements: &[Element]) -> String {
+        let style = &element.style;
+        let background = if style.background_color.is_empty() { "transparent" } else { style.background_color.as_str() };
+        let cell_style = format!(
+            "width: {}px; height: {}px; font-size: {}px; font-family: {}; font-weight: {}; font-style: {}; color: {}; background-color: {}; text-align: {}; padding: {}px; vertical-align: top;",
+            element.width, element.height, style.font_size, style.font_family, style.font_weight,
+            style.font_style, style.color, background, style.text_align, style.padding
+        );
+
+        format!(
+            "      <td width=\"{}\" valign=\"top\" style=\"{}\">{}</td>\n",
+            element.width, cell_style, self.email_content(element, elements, &[])
+        )
+    }
+
+    /// Inner markup of an element in the email export; elements placed in table cells are rendered
+    /// inside their cell. `ancestors` are the enclosing tables, so a nesting cycle ends as cell text
+    fn email_content(&self, element: &Element, elements: &[Element], ancestors: &[&str]) -> String {
+        match element.element_type.as_str() {
+            "image" if is_image_src(element.content.trim()) => format!(
+                "<img src=\"{}\" alt=\"\" width=\"{}\" style=\"display: block; max-width: 100%; border: 0;\" />",
+                self.escape_html(element.content.trim()), element.width
+            ),
+            "table" => match &element.table_data {
+                Some(table_data) => {
+                    let mut table = String::from("<table role=\"presentation\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\" style=\"border-collapse: collapse; width: 100%;\">");
+                    for (row_index, row) in table_data.rows.iter().enumerate() {
+                        table.push_str("<tr>");
+                        for (col_index, cell) in row.cells.iter().enumerate().filter(|(_, cell)| cell.row_span != 0 || cell.col_span != 0) {
+                            let content = cell
+                                .element_id
+                                .as_deref()
+                                .filter(|nested_id| *nested_id != element.id && !ancestors.contains(nested_id))
+                                .and_then(|nested_id| elements.iter().find(|nested| nested.id == nested_id))
+                                .map(|nested| {
+                                    let mut chain = ancestors.to_vec();
+                                    chain.push(&element.id);
+                                    self.email_content(nested, elements, &chain)
+                                })
+                                .unwrap_or_else(|| self.escape_html(&table_data.display_content(row_index, col_index, cell)));
+                            table.push_str(&format!(
+                                "<td rowspan=\"{}\" colspan=\"{}\" style=\"{} padding: {}px; font-size: {}px; font-weight: {}; color: {}; background-color: {}; text-align: {};\">{}</td>",
+                                cell.row_span, cell.col_span, cell_border_css(&cell.style), cell.style.padding.max(0.0), cell.style.font_size,
+                                cell.style.font_weight, cell.style.color, cell.style.background_color,
+                                cell.style.text_align, content
+                            ));
+                        }
+                        table.push_str("</tr>");
+                    }
+                    table.push_str("</table>");
+                    table
+                }
+                None => self.escape_html(&element.content),
+            },
+            "form_field" | "checkbox" => {
+                let data: serde_json::Value = serde_json::from_str(&element.content).unwrap_or_default();
+                let label = data.get("label").and_then(|v| v.as_str()).unwrap_or("");
+                if element.element_type == "checkbox" {
+                    let mark = if data.get("checked").and_then(|v| v.as_bool()).unwrap_or(false) { "&#9745;" } else { "&#9744;" };
+                    format!("{} {}", mark, self.escape_html(label))
+                } else {
+                    let value = data.get("value").and_then(|v| v.as_str()).unwrap_or("");
+                    format!(
+                        "{} <span style=\"border-bottom: 1px solid #000000;\">{}</span>",
+                        self.escape_html(label), self.escape_html(value)
+                    )
+                }
+            }
+            "rectangle" | "circle" | "line" | "qrcode" | "barcode" | "chart" => "&nbsp;".to_string(),
+            _ if element.content.contains('<') && element.content.contains('>') => self.clean_html_content(&element.content),
+            _ => self.escape_html(&element.content),
+        }
     }
 
-    fn generate_table_html(&self, element: &Element, base_style: &str) -> String {
+    /// Markup of an element nested in a table cell, flowing inline at the element's size;
+    /// `None` when the element is missing or already an enclosing table (a nesting cycle)
+    fn nested_element_html(&self, nested_id: &str, table: &Element, options: &ExportOptions, elements: &[Element], ancestors: &[&str]) -> Option<String> {
+        if nested_id == table.id || ancestors.contains(&nested_id) {
+            return None;
+        }
+        let nested = elements.iter().find(|element| element.id == nested_id)?;
+        let origin = Paper::new(String::new(), PaperSize::A4, PaperOrientation::Portrait, nested.x, nested.y);
+        let mut chain = ancestors.to_vec();
+        chain.push(&table.id);
+        Some(format!(
+            "<div class=\"cell-element\" style=\"position: relative; width: {}px; height: {}px;\">{}</div>",
+            nested.width,
+            nested.height,
+            self.generate_element_html(nested, &origin, options, elements, &chain).trim()
+        ))
+    }
+
+    fn generate_table_html(&self, element: &Element, base_style: &str, options: &ExportOptions, elements: &[Element], ancestors: &[&str]) -> String {
+        let highlight_query = self.escape_html(&options.highlight_query);
         let mut html = String::new();
         
         if let Some(ref table_data) = element.table_data {
             // Calculate total table width from column widths
             let total_width: f64 = table_data.column_widths.iter().sum();
             let table_width_style = format!("{} table-layout: auto; width: {}px;", base_style, total_width);
+            let table_direction = element.style.direction.as_deref().filter(|direction| TEXT_DIRECTIONS.contains(direction));
             
             
             html.push_str(&format!(
@@ -383,7 +1367,7 @@ impl ExportManager {
                 table_width_style
             ));
             
-            for (_row_index, row) in table_data.rows.iter().enumerate() {
+            for (row_index, row) in table_data.rows.iter().enumerate() {
                 html.push_str("      <tr>\n");
                 for (col_index, cell) in row.cells.iter().enumerate() {
                     // Skip merged cells that are marked as merged (row_span=0 and col_span=0)
@@ -392,18 +1376,14 @@ impl ExportManager {
                     }
                     
                     // Use actual column width from table data
-                    let cell_width = if col_index < table_data.column_widths.len() {
-                        let width = table_data.column_widths[col_index];
-                        format!("width: {}px; min-width: {}px;", width, width)
-                    } else {
-                        String::new()
-                    };
+                    let width = table_data.column_width(col_index);
+                    let cell_width = format!("width: {}px; min-width: {}px;", width, width);
                     
                     // Use actual row height from table data
-                    let cell_height = format!("height: {}px; min-height: {}px;", row.height, row.height);
+                    let row_height = if row.height > 0.0 { row.height } else { table_data.default_row_height };
+                    let cell_height = format!("height: {}px; min-height: {}px;", row_height, row_height);
                     
-                    // Add border styling for better table appearance
-                    let border_style = "border: 1px solid #ccc;";
+                    let border_style = cell_border_css(&cell.style);
                     
                     // Use cell-specific styles instead of hardcoded values
                     let cell_font_size = cell.style.font_size;
@@ -412,25 +1392,43 @@ impl ExportManager {
                     let cell_font_style = &cell.style.font_style;
                     let cell_color = &cell.style.color;
                     let cell_background_color = &cell.style.background_color;
-                    let cell_text_align = &cell.style.text_align;
-                    let cell_padding = cell.style.padding;
+                    // Cells without their own direction inherit the table's through CSS
+                    let own_direction = cell.style.direction.as_deref().filter(|direction| TEXT_DIRECTIONS.contains(direction));
+                    let cell_text_align = directional_text_align(&cell.style.text_align, own_direction.or(table_direction));
+                    let cell_vertical_align = cell.style.vertical_align.as_deref()
+                        .filter(|align| VERTICAL_ALIGNS.contains(align))
+                        .unwrap_or("top");
+                    let cell_padding = if cell.style.padding >= 0.0 { cell.style.padding } else { table_data.default_cell_padding };
                     
                     
-                    let cell_style = format!("{} {} {} font-size: {}px; font-family: {}; font-weight: {}; font-style: {}; color: {}; background-color: {}; text-align: {}; padding: {}px;", 
+                    let mut cell_style = format!("{} {} {} font-size: {}px; font-family: {}; font-weight: {}; font-style: {}; color: {}; background-color: {}; text-align: {}; vertical-align: {}; padding: {}px;", 
                                            cell_width, cell_height, border_style,
                                            cell_font_size, cell_font_family, cell_font_weight, cell_font_style,
-                                           cell_color, cell_background_color, cell_text_align, cell_padding);
+                                           cell_color, cell_background_color, cell_text_align, cell_vertical_align, cell_padding);
+                    if let Some(direction) = own_direction {
+                        cell_style.push_str(&format!(" direction: {};", direction));
+                    }
+                    cell_style.push_str(&typography_css(&cell.style));
+                    
+                    let cell_class = if cell.computed { " class=\"table-total-cell\"" } else { "" };
+                    let cell_content = cell
+                        .element_id
+                        .as_deref()
+                        .and_then(|nested_id| self.nested_element_html(nested_id, element, options, elements, ancestors))
+                        .unwrap_or_else(|| {
+                            let content = table_data.display_content(row_index, col_index, cell);
+                            highlight_matches(&self.escape_html(&content), &highlight_query)
+                        });
                     
                     if cell.row_span > 1 || cell.col_span > 1 {
                         html.push_str(&format!(
-                            "        <td rowspan=\"{}\" colspan=\"{}\" style=\"{}\">{}</td>\n",
-                            cell.row_span, cell.col_span, cell_style,
-                            self.escape_html(&cell.content)
+                            "        <td{} rowspan=\"{}\" colspan=\"{}\" style=\"{}\">{}</td>\n",
+                            cell_class, cell.row_span, cell.col_span, cell_style, cell_content
                         ));
                     } else {
                         html.push_str(&format!(
-                            "        <td style=\"{}\">{}</td>\n",
-                            cell_style, self.escape_html(&cell.content)
+                            "        <td{} style=\"{}\">{}</td>\n",
+                            cell_class, cell_style, cell_content
                         ));
                     }
                 }
@@ -572,14 +1570,7 @@ impl ExportManager {
     }
 
     fn generate_rectangle_html(&self, element: &Element, base_style: &str) -> String {
-        let fill_color = if element.style.fill.enabled {
-            element.style.fill.color.clone()
-        } else if !element.style.background_color.is_empty() && element.style.background_color != "transparent" {
-            // Use background_color as fallback when fill is not enabled
-            element.style.background_color.clone()
-        } else {
-            "transparent".to_string()
-        };
+        let fill_color = shape_fill_color(element);
         
         let stroke_width = element.style.stroke.width;
         let stroke_color = element.style.stroke.color.clone();
@@ -623,14 +1614,7 @@ impl ExportManager {
     }
 
     fn generate_circle_html(&self, element: &Element, base_style: &str) -> String {
-        let fill_color = if element.style.fill.enabled {
-            element.style.fill.color.clone()
-        } else if !element.style.background_color.is_empty() && element.style.background_color != "transparent" {
-            // Use background_color as fallback when fill is not enabled
-            element.style.background_color.clone()
-        } else {
-            "transparent".to_string()
-        };
+        let fill_color = shape_fill_color(element);
         
         let stroke_width = element.style.stroke.width;
         let stroke_color = element.style.stroke.color.clone();
@@ -715,6 +1699,19 @@ impl ExportManager {
         } else {
             "solid".to_string()
         };
+        let stroke_opacity = if element.style.stroke.enabled {
+            element.style.stroke.opacity.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        // Caps and joins are only written when set, so the SVG defaults apply otherwise
+        let mut stroke_shape = String::new();
+        if let Some(line_cap) = element.style.stroke.line_cap.as_deref().filter(|cap| LINE_CAPS.contains(cap)) {
+            stroke_shape.push_str(&format!(" stroke-linecap=\"{}\"", line_cap));
+        }
+        if let Some(line_join) = element.style.stroke.line_join.as_deref().filter(|join| LINE_JOINS.contains(join)) {
+            stroke_shape.push_str(&format!(" stroke-linejoin=\"{}\"", line_join));
+        }
 
         // Generate SVG path based on line type
         let path_d = match line_type {
@@ -777,7 +1774,7 @@ impl ExportManager {
             r#"    <div class="element element-line" style="{}">
         <svg width="100%" height="100%" style="position: absolute; top: 0; left: 0;">
             {}
-            <path d="{}" stroke="{}" stroke-width="{}" stroke-dasharray="{}" fill="none" 
+            <path d="{}" stroke="{}" stroke-width="{}" stroke-opacity="{}"{} stroke-dasharray="{}" fill="none" 
                   marker-start="{}" marker-end="{}"/>
         </svg>
     </div>
@@ -787,6 +1784,8 @@ impl ExportManager {
             path_d,
             stroke_color,
             stroke_width,
+            stroke_opacity,
+            stroke_shape,
             stroke_dasharray,
             marker_start,
             marker_end
@@ -801,3 +1800,1112 @@ impl ExportManager {
             .replace('\'', "&#x27;")
     }
 }
+
+fn cell_element_ids(element: &Element) -> impl Iterator<Item = &str> {
+    element
+        .table_data
+        .iter()
+        .flat_map(|table_data| table_data.rows.iter().flat_map(|row| &row.cells))
+        .filter_map(|cell| cell.element_id.as_deref())
+}
+
+/// Stylesheet of an export: `BASE_CSS` adjusted for the print options
+fn export_css(options: &ExportOptions, papers: &[A4Paper]) -> String {
+    let mut css = BASE_CSS.to_string();
+    if !options.print_mode {
+        // Static output has nothing to click
+        css.push_str(INTERACTIVE_CSS);
+    }
+    if let Some(bleed) = options.bleed(papers) {
+        css.push_str(&print_bleed_css(bleed));
+    }
+    if options.flow_layout {
+        css.push_str(FLOW_CSS);
+    }
+    css
+}
+
+/// Whether the element is rendered on the given page under the assignment mode
+/// (also how `PaperManager::remove_paper_with_contents` decides what a page holds)
+pub(crate) fn is_element_on_page(element: &Element, page_index: usize, papers: &[A4Paper], mode: PaperAssignMode) -> bool {
+    if !overlaps_paper(element, &papers[page_index]) {
+        return false;
+    }
+    let first_overlapping = || papers.iter().position(|paper| overlaps_paper(element, paper));
+    let assigned = match mode {
+        PaperAssignMode::AllPapers => return true,
+        PaperAssignMode::FirstPaper => first_overlapping(),
+        PaperAssignMode::CenterPaper => {
+            let (center_x, center_y) = (element.x + element.width / 2.0, element.y + element.height / 2.0);
+            papers
+                .iter()
+                .position(|paper| utils::point_in_rect(center_x, center_y, paper.x, paper.y, paper.width, paper.height))
+                .or_else(first_overlapping)
+        }
+    };
+    assigned == Some(page_index)
+}
+
+/// ตรวจสอบว่า element อยู่ในขอบเขตของ paper หรือไม่
+fn overlaps_paper(element: &Element, paper: &A4Paper) -> bool {
+    let element_right = element.x + element.width;
+    let element_bottom = element.y + element.height;
+    let paper_right = paper.x + paper.width;
+    let paper_bottom = paper.y + paper.height;
+
+    // Element overlaps with paper if it's within the paper boundaries
+    element.x < paper_right && element_right > paper.x &&
+    element.y < paper_bottom && element_bottom > paper.y
+}
+
+/// Opening tag of the element every page is rendered into
+fn container_open(options: &ExportOptions) -> &'static str {
+    if options.flow_layout {
+        "<div class=\"paper-container flow-layout\">\n"
+    } else {
+        "<div class=\"paper-container\">\n"
+    }
+}
+
+/// Elements placed in table cells, which are drawn by their table rather than at their own position.
+/// Only tables that are themselves on the page count, so tables nested in a cycle stay on the page.
+fn nested_element_ids(elements: &[Element]) -> HashSet<&str> {
+    let referenced: HashSet<&str> = elements.iter().flat_map(cell_element_ids).collect();
+    let mut pending: Vec<&Element> = elements
+        .iter()
+        .filter(|element| !referenced.contains(element.id.as_str()))
+        .collect();
+    let mut nested = HashSet::new();
+    while let Some(element) = pending.pop() {
+        for element_id in cell_element_ids(element) {
+            if nested.insert(element_id) {
+                pending.extend(elements.iter().find(|candidate| candidate.id == element_id));
+            }
+        }
+    }
+    nested
+}
+
+/// Wrap ASCII case-insensitive matches of `needle` (already HTML-escaped) in `<mark>`.
+/// Tags are copied as-is and a match never starts or ends inside a character entity.
+fn highlight_matches(html: &str, needle: &str) -> String {
+    if needle.trim().is_empty() {
+        return html.to_string();
+    }
+    let needle = needle.to_ascii_lowercase();
+    let mut highlighted = String::with_capacity(html.len());
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let tag_end = rest.find('>').map_or(rest.len(), |index| index + 1);
+            highlighted.push_str(&rest[..tag_end]);
+            rest = &rest[tag_end..];
+            continue;
+        }
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        highlight_text(&rest[..text_end], &needle, &mut highlighted);
+        rest = &rest[text_end..];
+    }
+    highlighted
+}
+
+/// Highlight matches in a run of text between tags
+fn highlight_text(text: &str, needle: &str, out: &mut String) {
+    // Byte offsets after an entity's '&' up to and including its ';' cannot be match boundaries
+    let mut inside_entity = vec![false; text.len() + 1];
+    for (start, _) in text.match_indices('&') {
+        if let Some(length) = text[start..].find(';') {
+            inside_entity[start + 1..=start + length].iter_mut().for_each(|inside| *inside = true);
+        }
+    }
+
+    let lowered = text.to_ascii_lowercase();
+    let mut copied = 0;
+    let mut search_from = 0;
+    while let Some(offset) = lowered[search_from..].find(needle) {
+        let start = search_from + offset;
+        let end = start + needle.len();
+        if inside_entity[start] || inside_entity[end] {
+            search_from = start + lowered[start..].chars().next().map_or(1, char::len_utf8);
+            continue;
+        }
+        out.push_str(&text[copied..start]);
+        out.push_str("<mark>");
+        out.push_str(&text[start..end]);
+        out.push_str("</mark>");
+        copied = end;
+        search_from = end;
+    }
+    out.push_str(&text[copied..]);
+}
+
+/// Print rules that grow each A4 page by the bleed on every side; the trim box stays
+/// the positioning origin, so element coordinates are unchanged. Only used when every
+/// paper is A4 portrait, the size `.a4-paper` is laid out at
+fn print_bleed_css(bleed_mm: f64) -> String {
+    let (width, height) = PaperSize::A4.dimensions_mm();
+    format!(
+        "
+.crop-mark {{
+    display: none;
+}}
+
+@media print {{
+    @page {{ size: {page_width}mm {page_height}mm; margin: 0; }}
+    .a4-paper {{
+        box-sizing: content-box;
+        border: {bleed}mm solid transparent;
+        overflow: visible;
+    }}
+    .crop-mark {{ display: block; position: absolute; background: #000; }}
+    .crop-mark-h {{ width: {bleed}mm; height: 0.25mm; }}
+    .crop-mark-v {{ width: 0.25mm; height: {bleed}mm; }}
+}}
+",
+        page_width = width + 2.0 * bleed_mm,
+        page_height = height + 2.0 * bleed_mm,
+        bleed = bleed_mm
+    )
+}
+
+/// Horizontal and vertical crop mark at each trim corner, extending out into the bleed
+fn crop_marks_html(bleed_mm: f64) -> String {
+    let mut html = String::new();
+    for vertical in ["top", "bottom"] {
+        for horizontal in ["left", "right"] {
+            html.push_str(&format!(
+                "    <div class=\"crop-mark crop-mark-h\" style=\"{}: 0; {}: -{}mm;\"></div>\n",
+                vertical, horizontal, bleed_mm
+            ));
+            html.push_str(&format!(
+                "    <div class=\"crop-mark crop-mark-v\" style=\"{}: -{}mm; {}: 0;\"></div>\n",
+                vertical, bleed_mm, horizontal
+            ));
+        }
+    }
+    html
+}
+
+/// `line-height` and `letter-spacing` declarations for the values that are set
+fn typography_css(style: &ElementStyle) -> String {
+    let mut css = String::new();
+    if let Some(line_height) = style.line_height.filter(|line_height| line_height.is_finite() && *line_height > 0.0) {
+        css.push_str(&format!(" line-height: {};", line_height));
+    }
+    if let Some(letter_spacing) = style.letter_spacing.filter(|letter_spacing| letter_spacing.is_finite()) {
+        css.push_str(&format!(" letter-spacing: {}px;", letter_spacing));
+    }
+    css
+}
+
+/// Right-to-left text starts at the right edge, so the default left alignment becomes right
+fn directional_text_align<'a>(text_align: &'a str, direction: Option<&str>) -> &'a str {
+    match (text_align, direction) {
+        ("left", Some("rtl")) => "right",
+        _ => text_align,
+    }
+}
+
+/// Add a `tabindex` attribute to the outermost tag of an element's markup
+fn with_tab_index(element_html: &str, tab_index: i32) -> String {
+    let tag_start = element_html.find('<').map_or(0, |index| index + 1);
+    match element_html[tag_start..].find([' ', '>']) {
+        Some(offset) => {
+            let insert_at = tag_start + offset;
+            format!("{} tabindex=\"{}\"{}", &element_html[..insert_at], tab_index, &element_html[insert_at..])
+        }
+        None => element_html.to_string(),
+    }
+}
+
+/// Enabled fill as the background and enabled stroke as the border, for elements that are not shapes
+fn fill_stroke_css(style: &ElementStyle) -> String {
+    let mut css = String::new();
+    if style.fill.enabled {
+        css.push_str(&format!(" background-color: {};", style.fill.color));
+    }
+    if style.stroke.enabled && style.stroke.width > 0.0 {
+        let border_style = match style.stroke.style.as_str() {
+            "dashed" | "dotted" => style.stroke.style.as_str(),
+            _ => "solid",
+        };
+        css.push_str(&format!(" border: {}px {} {};", style.stroke.width, border_style, style.stroke.color));
+    }
+    css
+}
+
+/// Border of a table cell from its style (1px #cccccc unless a theme or edit changed it)
+fn cell_border_css(style: &ElementStyle) -> String {
+    if style.border_width > 0.0 {
+        format!("border: {}px solid {};", style.border_width, style.border_color)
+    } else {
+        "border: none;".to_string()
+    }
+}
+
+/// Append classes to the first `class` attribute of an element's markup
+fn with_extra_classes(element_html: &str, classes: &str) -> String {
+    const CLASS_ATTRIBUTE: &str = "class=\"";
+    match element_html.find(CLASS_ATTRIBUTE) {
+        Some(index) => {
+            let insert_at = element_html[index + CLASS_ATTRIBUTE.len()..]
+                .find('"')
+                .map_or(element_html.len(), |offset| index + CLASS_ATTRIBUTE.len() + offset);
+            format!("{} {}{}", &element_html[..insert_at], classes, &element_html[insert_at..])
+        }
+        None => element_html.to_string(),
+    }
+}
+
+/// Fill of a rectangle or circle, falling back to the background color when fill is disabled
+fn shape_fill_color(element: &Element) -> String {
+    if element.style.fill.enabled {
+        element.style.fill.color.clone()
+    } else if !element.style.background_color.is_empty() && element.style.background_color != "transparent" {
+        element.style.background_color.clone()
+    } else {
+        "transparent".to_string()
+    }
+}
+
+/// Page-space SVG markup for the leading run of elements that can share one `<svg>`
+fn batched_shape_run(elements: &[&Element], paper: &A4Paper) -> Vec<String> {
+    let Some(first) = elements.first() else { return Vec::new() };
+    elements
+        .iter()
+        .take_while(|element| element.element_type == first.element_type && element.z_index == first.z_index)
+        .map_while(|element| batched_shape_svg(element, element.x - paper.x, element.y - paper.y))
+        .collect()
+}
+
+/// SVG equivalent of a shape's `<div>` rendering, or `None` when it would not look identical
+/// (blend modes, dashed strokes and stroked circles keep their own element)
+fn batched_shape_svg(element: &Element, x: f64, y: f64) -> Option<String> {
+    if element.style.blend_mode.is_some() {
+        return None;
+    }
+    let fill_color = shape_fill_color(element);
+    let stroke = &element.style.stroke;
+    let stroked = stroke.enabled && stroke.width > 0.0;
+
+    match element.element_type.as_str() {
+        "rectangle" if stroked => {
+            if stroke.style != "solid" {
+                return None;
+            }
+            Some(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                x + stroke.width / 2.0, y + stroke.width / 2.0,
+                element.width - stroke.width, element.height - stroke.width,
+                fill_color, stroke.color, stroke.width
+            ))
+        }
+        "rectangle" => {
+            // Same clamping as CSS border-radius on a box with uniform corners
+            let radius = element.style.border_radius.max(0.0).min(element.width.min(element.height) / 2.0);
+            Some(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"{}\"/>",
+                x, y, element.width, element.height, radius, radius, fill_color
+            ))
+        }
+        "circle" if !stroked => Some(format!(
+            "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"{}\"/>",
+            x + element.width / 2.0, y + element.height / 2.0,
+            element.width / 2.0, element.height / 2.0, fill_color
+        )),
+        _ => None,
+    }
+}
+
+/// Quiet zone around a QR code, in modules
+const QR_QUIET_ZONE: usize = 4;
+
+/// Inline SVG of a QR code, one rect per horizontal run of dark modules
+fn qr_code_svg(payload: &str) -> Option<String> {
+    let code = QrCode::new(payload.as_bytes()).ok()?;
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = width + QR_QUIET_ZONE * 2;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {0}\" width=\"100%\" height=\"100%\" shape-rendering=\"crispEdges\"><rect width=\"{0}\" height=\"{0}\" fill=\"#ffffff\"/>",
+        size
+    );
+    for (y, row) in colors.chunks(width).enumerate() {
+        let mut x = 0;
+        while x < width {
+            if row[x] != Color::Dark {
+                x += 1;
+                continue;
+            }
+            let run_start = x;
+            while x < width && row[x] == Color::Dark {
+                x += 1;
+            }
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"#000000\"/>",
+                run_start + QR_QUIET_ZONE,
+                y + QR_QUIET_ZONE,
+                x - run_start
+            ));
+        }
+    }
+    svg.push_str("</svg>");
+    Some(svg)
+}
+
+/// Image sources allowed in export: http(s) URLs and inline `data:image/` payloads
+fn is_image_src(src: &str) -> bool {
+    let lower = src.to_ascii_lowercase();
+    ["data:image/", "http://", "https://"].iter().any(|prefix| lower.starts_with(prefix))
+}
+
+/// Replace `{{key}}` tokens with values from the context; unknown tokens are kept as-is
+fn substitute_placeholders(
+    text: &str,
+    context: &HashMap<String, String>,
+    format_value: impl Fn(&str) -> String,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else { break };
+        let token_end = start + 2 + end + 2;
+        result.push_str(&rest[..start]);
+        match context.get(rest[start + 2..start + 2 + end].trim()) {
+            Some(value) => result.push_str(&format_value(value)),
+            None => result.push_str(&rest[start..token_end]),
+        }
+        rest = &rest[token_end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::TableManager;
+    use crate::element::ElementManager;
+
+    fn export_manager_with(elements: Vec<Element>) -> ExportManager {
+        let paper = Paper::new("page-0".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 0.0);
+        ExportManager::new(Arc::new(Mutex::new(elements)), Arc::new(Mutex::new(vec![paper])))
+    }
+
+    fn exported_html(result: &str) -> String {
+        let parsed: serde_json::Value = serde_json::from_str(result).unwrap();
+        parsed["html"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_highlight_query_marks_text_and_table_cells() {
+        let mut summary = Element::new("summary".to_string(), "text".to_string(), "text".to_string());
+        summary.content = "<span title=\"total due\">Grand Total: 10 &amp; more</span>".to_string();
+        let mut table = Element::new("table".to_string(), "table".to_string(), "table".to_string());
+        table.y = 200.0;
+        table.create_default_table(2, 2);
+        table.table_data.as_mut().unwrap().rows[1].cells[0].content = "Subtotal".to_string();
+        let export_manager = export_manager_with(vec![summary, table]);
+
+        let html = exported_html(&export_manager.export_html(r#"{"highlightQuery":"total"}"#));
+        assert!(html.contains("<span title=\"total due\">Grand <mark>Total</mark>: 10 &amp; more</span>"));
+        assert!(html.contains(">Sub<mark>total</mark></td>"));
+        assert_eq!(html.matches("<mark>").count(), 2);
+
+        // Entities are never split
+        let html = exported_html(&export_manager.export_html(r#"{"highlightQuery":"amp"}"#));
+        assert!(!html.contains("<mark>"));
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(!html.contains("<mark>"));
+    }
+
+    #[test]
+    fn test_print_bleed_and_crop_marks() {
+        let export_manager = export_manager_with(vec![]);
+
+        let result: serde_json::Value =
+            serde_json::from_str(&export_manager.export_print_html(r#"{"bleedMm":3,"cropMarks":true}"#)).unwrap();
+        let css = result["css"].as_str().unwrap();
+        assert!(css.contains("@page { size: 216mm 303mm; margin: 0; }"));
+        assert!(css.contains("border: 3mm solid transparent;"));
+        let html = result["html"].as_str().unwrap();
+        assert_eq!(html.matches("class=\"crop-mark crop-mark-").count(), 8);
+        assert!(html.contains("<div class=\"crop-mark crop-mark-h\" style=\"bottom: 0; right: -3mm;\"></div>"));
+
+        // No bleed leaves nowhere to draw the marks
+        let result: serde_json::Value = serde_json::from_str(&export_manager.export_print_html(r#"{"cropMarks":true}"#)).unwrap();
+        assert!(!result["css"].as_str().unwrap().contains("@page"));
+        assert!(!result["html"].as_str().unwrap().contains("crop-mark"));
+
+        // An A4 @page would not fit an A5 or landscape sheet, so the bleed is skipped
+        for (size, orientation) in [(PaperSize::A5, PaperOrientation::Portrait), (PaperSize::A4, PaperOrientation::Landscape)] {
+            let papers = vec![
+                Paper::new("cover".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 0.0),
+                Paper::new("insert".to_string(), size, orientation, 0.0, 1200.0),
+            ];
+            let export_manager = ExportManager::new(Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(papers)));
+            let result = export_manager.render(&ExportOptions { bleed_mm: 3.0, crop_marks: true, ..ExportOptions::default() });
+            assert!(!result.css.contains("@page"));
+            assert!(!result.html.contains("crop-mark"));
+        }
+    }
+
+    #[test]
+    fn test_checkbox_nested_in_table_cell() {
+        let mut table = Element::new("form".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        let mut checkbox = Element::new("agree".to_string(), "checkbox".to_string(), "checkbox".to_string());
+        checkbox.x = 400.0;
+        checkbox.y = 600.0;
+        checkbox.content = r#"{"label":"I agree","checked":true}"#.to_string();
+        let mut inner = Element::new("inner".to_string(), "table".to_string(), "table".to_string());
+        inner.create_default_table(1, 1);
+        let export_manager = export_manager_with(vec![table, checkbox, inner]);
+        let table_manager = TableManager::new(export_manager.elements.clone());
+        assert!(table_manager.set_table_cell_element("form", 1, 1, "agree"));
+        assert!(!table_manager.set_table_cell_element("form", 1, 0, "form"));
+        assert!(!table_manager.set_table_cell_element("form", 1, 0, "missing"));
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        let cell_start = html.find("padding: 8px;\"><div class=\"cell-element\"").unwrap();
+        let cell = &html[cell_start..cell_start + html[cell_start..].find("</td>").unwrap()];
+        assert!(cell.contains("checkbox-element"));
+        assert!(cell.contains("I agree"));
+        assert!(cell.contains("left: 0px; top: 0px;"));
+        // Drawn inside the cell only, not again at its page position
+        assert!(!html.contains("left: 400px"));
+
+        // A table cannot end up inside itself through another table
+        assert!(table_manager.set_table_cell_element("form", 0, 0, "inner"));
+        assert!(!table_manager.set_table_cell_element("inner", 0, 0, "form"));
+
+        // A cycle from an older document stops at the table already being drawn
+        let mut elements = export_manager.elements.lock().unwrap();
+        let inner = elements.iter_mut().find(|element| element.id == "inner").unwrap();
+        inner.table_data.as_mut().unwrap().rows[0].cells[0].element_id = Some("form".to_string());
+        drop(elements);
+        let html = export_manager.export_table_html("form");
+        assert_eq!(html.matches("cell-element").count(), 2);
+        assert_eq!(html.matches("<table").count(), 2);
+
+        // and neither table in the cycle is dropped from the page: each is drawn with the other inside
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert_eq!(html.matches("<table").count(), 4);
+    }
+
+    #[test]
+    fn test_base_css_matches_export_stylesheet() {
+        let export_manager = export_manager_with(vec![]);
+        let css = export_manager.get_base_css();
+        assert!(css.contains(".a4-paper {"));
+        assert!(css.contains("@media print {"));
+
+        let exported: serde_json::Value = serde_json::from_str(&export_manager.export_html("{}")).unwrap();
+        assert!(exported["css"].as_str().unwrap().starts_with(&css));
+    }
+
+    #[test]
+    fn test_table_number_format_us_and_eu() {
+        let mut table = Element::new("table-1".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        if let Some(table_data) = table.table_data.as_mut() {
+            table_data.rows[0].cells[0].content = "2024".to_string();
+            table_data.rows[1].cells[0].content = "1234.5".to_string();
+            table_data.rows[1].cells[1].content = "Widget".to_string();
+        }
+        let export_manager = export_manager_with(vec![table]);
+        let table_manager = TableManager::new(export_manager.elements.clone());
+
+        assert!(table_manager.set_table_number_format("table-1", r#"{"thousandsSeparator":",","decimalSeparator":".","decimals":2}"#));
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(html.contains(">1,234.50</td>"));
+        assert!(html.contains(">2024</td>")); // Header rows are shown as stored
+        assert!(html.contains(">Widget</td>"));
+
+        assert!(table_manager.set_table_number_format("table-1", r#"{"thousandsSeparator":".","decimalSeparator":",","decimals":2}"#));
+        assert!(exported_html(&export_manager.export_html("{}")).contains(">1.234,50</td>"));
+        // The stored value stays numeric
+        assert_eq!(table_manager.table_data("table-1").unwrap().rows[1].cells[0].content, "1234.5");
+
+        assert!(table_manager.set_table_number_format("table-1", "null"));
+        assert!(exported_html(&export_manager.export_html("{}")).contains(">1234.5</td>"));
+        assert!(!table_manager.set_table_number_format("table-1", "{not json"));
+    }
+
+    #[test]
+    fn test_page_by_page_export_matches_full_export() {
+        let papers: Vec<Paper> = (0..3)
+            .map(|index| Paper::new(format!("page-{}", index), PaperSize::A4, PaperOrientation::Portrait, 0.0, index as f64 * 1200.0))
+            .collect();
+        let elements: Vec<Element> = (0..3)
+            .map(|index| {
+                let mut element = Element::new(format!("text-{}", index), "text".to_string(), "text".to_string());
+                element.y = index as f64 * 1200.0 + 100.0;
+                element.content = format!("Page {}", index + 1);
+                element
+            })
+            .collect();
+        let export_manager = ExportManager::new(Arc::new(Mutex::new(elements)), Arc::new(Mutex::new(papers)));
+        assert_eq!(export_manager.get_export_page_count(), 3);
+
+        for options in [
+            r#"{"autoTabOrder":true,"showPageTitles":true}"#,
+            r#"{"printMode":true,"bleedMm":3,"cropMarks":true}"#,
+        ] {
+            let pages: Vec<String> = (0..3).map(|page_index| export_manager.export_html_page(page_index, options)).collect();
+            assert!(pages[1].contains(">Page 2</div>") && !pages[1].contains("Page 1"));
+            let streamed = format!("<div class=\"paper-container\">\n{}</div>\n", pages.concat());
+            let result = export_manager.render(&ExportOptions::from_json(options));
+            assert_eq!(streamed, result.html);
+            assert_eq!(export_manager.get_export_css(options), result.css);
+            assert_eq!(export_manager.export_html_page(3, options), "");
+        }
+        assert!(export_manager.get_export_css(r#"{"bleedMm":3}"#).contains("@page"));
+    }
+
+    #[test]
+    fn test_fill_applies_to_text_background_when_enabled() {
+        let mut text = Element::new("text-1".to_string(), "text".to_string(), "text".to_string());
+        text.style.background_color = "#ffffff".to_string();
+        text.style.fill.enabled = true;
+        text.style.fill.color = "#fde68a".to_string();
+        text.style.stroke.enabled = true;
+        text.style.stroke.width = 2.0;
+        text.style.stroke.style = "dashed".to_string();
+        text.style.stroke.color = "#92400e".to_string();
+        let export_manager = export_manager_with(vec![text]);
+
+        let html = exported_html(&export_manager.export_html(r#"{"applyFillStroke":true}"#));
+        let style = html.lines().find(|line| line.contains("element-text")).unwrap();
+        assert!(style.ends_with("background-color: #fde68a; border: 2px dashed #92400e;\">Text Content</div>"), "{}", style);
+
+        // Off by default: the element keeps its background color
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(!html.contains("#fde68a"));
+    }
+
+    #[test]
+    fn test_printable_guide_outside_print_mode() {
+        let export_manager = export_manager_with(Vec::new());
+        let html = exported_html(&export_manager.export_html(r#"{"printableGuide":true,"printerMarginMm":10}"#));
+        let inset = 10.0 / 25.4 * 96.0;
+        assert!(html.contains(&format!("<div class=\"printable-guide\" style=\"left: {}px; top: {}px;", inset, inset)));
+
+        assert!(!exported_html(&export_manager.export_print_html(r#"{"printableGuide":true}"#)).contains("printable-guide\""));
+        assert!(!exported_html(&export_manager.export_html("{}")).contains("printable-guide\""));
+
+        // Without an override the guide follows the engine's printer margin
+        let engine = crate::HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        assert!(engine.set_printer_margin(10.0));
+        let html = exported_html(&engine.export_html(r#"{"printableGuide":true}"#));
+        assert!(html.contains(&format!("<div class=\"printable-guide\" style=\"left: {}px; top: {}px;", inset, inset)));
+    }
+
+    #[test]
+    fn test_page_break_before_in_flow_layout_print_css() {
+        let mut heading = Element::new("heading-1".to_string(), "heading".to_string(), "heading".to_string());
+        heading.content = "Chapter 2".to_string();
+        heading.y = 300.0;
+        let intro = Element::new("intro".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        let export_manager = export_manager_with(vec![heading, intro]);
+        let element_manager = ElementManager::new_with_data(export_manager.elements.clone());
+        assert!(element_manager.set_page_breaks("heading-1", true, false, false));
+        assert!(!element_manager.set_page_breaks("missing", true, false, false));
+
+        // Absolutely positioned elements cannot break pages, so the default export leaves the flag out
+        let result = export_manager.render(&ExportOptions::default());
+        assert!(!result.html.contains("page-break-before"));
+        assert!(!result.css.contains(".page-break-before"));
+
+        let result = export_manager.render(&ExportOptions { flow_layout: true, ..ExportOptions::default() });
+        assert!(result.html.starts_with("<div class=\"paper-container flow-layout\">"));
+        assert!(result.html.contains("<h1 class=\"element element-heading page-break-before\""));
+        // In reading order, so the break falls between the intro and the heading
+        assert!(result.html.find("element-paragraph").unwrap() < result.html.find("element-heading").unwrap());
+        assert!(result.css.contains(".flow-layout .element {\n    position: relative !important;"));
+        let print_css = &result.css[result.css.rfind("@media print").unwrap()..];
+        assert!(print_css.contains(".page-break-before { break-before: page;"));
+    }
+
+    #[test]
+    fn test_print_export_renders_static_button() {
+        let mut button = Element::new("btn".to_string(), "button".to_string(), "button".to_string());
+        button.x = 50.0;
+        button.y = 50.0;
+        button.content = "Submit".to_string();
+        let export_manager = export_manager_with(vec![button]);
+
+        let result = export_manager.export_print_html("{}");
+        let html = exported_html(&result);
+        assert!(!html.contains("<button"));
+        assert!(html.contains("<div class=\"element element-button\""));
+        assert!(html.contains(">Submit</div>"));
+
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let css = parsed["css"].as_str().unwrap();
+        assert!(!css.contains("cursor: pointer"));
+        assert!(css.contains("@media print"));
+        assert!(export_manager.get_export_css("{}").contains(".element-button {\n    cursor: pointer;"));
+    }
+
+    #[test]
+    fn test_standalone_export_embeds_fonts() {
+        let mut heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        heading.content = "Archive".to_string();
+        let export_manager = export_manager_with(vec![heading]);
+
+        let document = export_manager.export_standalone_html(
+            r#"{"fonts":[{"family":"Sarabun","data":"d09GMgABAAAAAA==","format":"woff2"}]}"#,
+        );
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert_eq!(document.matches("<style>").count(), 1);
+        assert!(document.contains("@font-face {\n    font-family: \"Sarabun\";\n    src: url(\"data:font/woff2;base64,d09GMgABAAAAAA==\") format(\"woff2\");"));
+        assert!(document.contains("Archive"));
+        assert!(document.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_standalone_export_drops_unsafe_fonts() {
+        let export_manager = export_manager_with(vec![]);
+        let options = serde_json::json!({"fonts": [
+            {"family": "Evil", "data": "AAAA\");} </style><script>alert(1)</script>"},
+            {"family": "Weight", "data": "AAAA", "weight": "bold;} </style>"},
+            {"family": "Style", "data": "AAAA", "style": "italic</style>"},
+            {"family": "</style><script>", "data": "AAAA"},
+            {"family": "Sarabun", "data": "AAAA", "weight": "700", "style": "italic"},
+        ]});
+
+        let document = export_manager.export_standalone_html(&options.to_string());
+        assert!(!document.contains("<script>"));
+        assert_eq!(document.matches("</style>").count(), 1);
+        assert_eq!(document.matches("@font-face").count(), 1);
+        assert!(document.contains("font-family: \"Sarabun\";"));
+        assert!(document.contains("font-weight: 700;\n    font-style: italic;"));
+    }
+
+    #[test]
+    fn test_rtl_direction_in_element_and_cell_style() {
+        let mut paragraph = Element::new("arabic".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        paragraph.content = "\u{645}\u{631}\u{62d}\u{628}\u{627}".to_string();
+        paragraph.style.direction = Some("rtl".to_string());
+        let mut table = Element::new("table".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        table.y = 200.0;
+        if let Some(table_data) = table.get_table_data_mut() {
+            table_data.rows[1].cells[0].style.direction = Some("rtl".to_string());
+        }
+        let export_manager = export_manager_with(vec![paragraph, table]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        let paragraph_line = html.lines().find(|line| line.contains("element-paragraph")).unwrap();
+        assert!(paragraph_line.contains("direction: rtl;"));
+        assert!(paragraph_line.contains("text-align: right;"));
+        let cells: Vec<&str> = html.lines().filter(|line| line.contains("<td")).collect();
+        assert!(cells[2].contains("text-align: right;") && cells[2].contains("direction: rtl;"));
+        assert!(!cells[3].contains("direction:"));
+    }
+
+    #[test]
+    fn test_line_height_and_letter_spacing_in_export() {
+        let mut paragraph = Element::new("spaced".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        paragraph.style.line_height = Some(1.5);
+        paragraph.style.letter_spacing = Some(2.0);
+        let mut table = Element::new("table".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        table.y = 200.0;
+        if let Some(table_data) = table.get_table_data_mut() {
+            table_data.rows[1].cells[1].style.line_height = Some(1.5);
+        }
+        let plain = Element::new("plain".to_string(), "text".to_string(), "text".to_string());
+        let export_manager = export_manager_with(vec![paragraph, table, plain]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        let paragraph_line = html.lines().find(|line| line.contains("element-paragraph")).unwrap();
+        assert!(paragraph_line.contains("line-height: 1.5; letter-spacing: 2px;"));
+        assert_eq!(html.lines().filter(|line| line.contains("<td") && line.contains("line-height: 1.5;")).count(), 1);
+        let plain_line = html.lines().find(|line| line.contains("element-text")).unwrap();
+        assert!(!plain_line.contains("line-height") && !plain_line.contains("letter-spacing"));
+    }
+
+    #[test]
+    fn test_overflow_modes_in_element_style() {
+        let mut truncated = Element::new("truncated".to_string(), "text".to_string(), "text".to_string());
+        truncated.content = "A caption that is much longer than its box".to_string();
+        truncated.style.overflow = Some("ellipsis".to_string());
+        let mut clipped = Element::new("clipped".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        clipped.style.overflow = Some("hidden".to_string());
+        clipped.y = 100.0;
+        let export_manager = export_manager_with(vec![truncated, clipped]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        let text_line = html.lines().find(|line| line.contains("element-text")).unwrap();
+        assert!(text_line.contains("overflow: hidden; text-overflow: ellipsis; white-space: nowrap;"));
+        let paragraph_line = html.lines().find(|line| line.contains("element-paragraph")).unwrap();
+        assert!(paragraph_line.contains("overflow: hidden;"));
+        assert!(!paragraph_line.contains("text-overflow"));
+    }
+
+    #[test]
+    fn test_blend_mode_in_element_style() {
+        let mut shape = Element::new("shape".to_string(), "text".to_string(), "text".to_string());
+        shape.style.blend_mode = Some("multiply".to_string());
+        let mut invalid = Element::new("invalid".to_string(), "text".to_string(), "text".to_string());
+        invalid.style.blend_mode = Some("bogus; color: red".to_string());
+        let export_manager = export_manager_with(vec![shape, invalid]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert_eq!(html.matches("mix-blend-mode").count(), 1);
+        assert!(html.contains("position: absolute; mix-blend-mode: multiply;"));
+    }
+
+    #[test]
+    fn test_export_html_with_context() {
+        let mut paragraph = Element::new("greeting".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        paragraph.content = "Hello {{name}}, order {{ order }} ships {{date}}".to_string();
+        let export_manager = export_manager_with(vec![paragraph]);
+
+        let html = exported_html(&export_manager.export_html_with_context("{}", r#"{"name":"Alice","order":42}"#));
+        assert!(html.contains("Hello Alice, order 42 ships {{date}}"));
+
+        let stored = utils::lock(&export_manager.elements);
+        assert_eq!(stored[0].content, "Hello {{name}}, order {{ order }} ships {{date}}");
+    }
+
+    #[test]
+    fn test_export_warnings_name_malformed_form_field() {
+        let mut field = Element::new("field".to_string(), "form_field".to_string(), "form_field".to_string());
+        field.content = "{\"label\":".to_string();
+        let heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        let export_manager = export_manager_with(vec![field, heading]);
+
+        let parsed: serde_json::Value = serde_json::from_str(&export_manager.export_html("{}")).unwrap();
+        let warnings = parsed["metadata"]["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0]["element_id"], "field");
+        assert_eq!(warnings[0]["kind"], "invalid_content");
+    }
+
+    #[test]
+    fn test_assign_mode_for_element_straddling_papers() {
+        let first = Paper::new("page-0".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 0.0);
+        let second = Paper::new("page-1".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, first.height);
+        // Mostly on the second page
+        let mut straddling = Element::new("straddling".to_string(), "text".to_string(), "text".to_string());
+        straddling.y = first.height - 10.0;
+        straddling.content = "Straddling".to_string();
+        let export_manager = ExportManager::new(
+            Arc::new(Mutex::new(vec![straddling])),
+            Arc::new(Mutex::new(vec![first, second])),
+        );
+
+        let pages_with_element = |options: &str| -> Vec<usize> {
+            let html = exported_html(&export_manager.export_html(options));
+            html.split("<div class=\"a4-paper\"")
+                .skip(1)
+                .enumerate()
+                .filter(|(_, page)| page.contains("Straddling"))
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        assert_eq!(pages_with_element("{}"), vec![1]);
+        assert_eq!(pages_with_element(r#"{"assignMode":"centerPaper"}"#), vec![1]);
+        assert_eq!(pages_with_element(r#"{"assignMode":"firstPaper"}"#), vec![0]);
+        assert_eq!(pages_with_element(r#"{"assignMode":"allPapers"}"#), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_image_element_renders_img() {
+        let src = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+        let mut image = Element::new("logo".to_string(), "image".to_string(), "image".to_string());
+        image.content = src.to_string();
+        let mut unsafe_image = Element::new("script".to_string(), "image".to_string(), "image".to_string());
+        unsafe_image.content = "javascript:alert(1)".to_string();
+        let export_manager = export_manager_with(vec![image, unsafe_image]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(html.contains(&format!("<img class=\"element element-image\" src=\"{}\"", src)));
+        assert!(html.contains("object-fit: contain;"));
+        assert_eq!(html.matches("<img").count(), 1);
+    }
+
+    #[test]
+    fn test_qrcode_element_renders_svg_modules() {
+        let mut qrcode = Element::new("ticket".to_string(), "qrcode".to_string(), "qrcode".to_string());
+        qrcode.content = "INV-2024-0001".to_string();
+        let export_manager = export_manager_with(vec![qrcode]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(html.contains("<div class=\"element element-qrcode\""));
+        assert!(html.contains("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(html.matches("<rect").count() > 10);
+        assert!(!html.contains("INV-2024-0001"));
+    }
+
+    #[test]
+    fn test_bar_chart_reads_table_column() {
+        let mut table = Element::new("sales".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(4, 2);
+        for (row, value) in ["10", "20", "40"].iter().enumerate() {
+            table.update_table_cell(row + 1, 1, value.to_string());
+        }
+        let mut chart = Element::new("chart".to_string(), "chart".to_string(), "chart".to_string());
+        chart.x = 20.0;
+        chart.y = 300.0;
+        chart.content = r#"{"sourceTableId":"sales","column":1,"kind":"bar"}"#.to_string();
+        let export_manager = export_manager_with(vec![table, chart]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        let svg = &html[html.find("<svg class=\"chart chart-bar\"").unwrap()..];
+        let svg = &svg[..svg.find("</svg>").unwrap()];
+        let heights: Vec<&str> = svg
+            .split("height=\"")
+            .skip(2) // The svg's own height attribute
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(heights, vec!["25", "50", "100"]);
+    }
+
+    #[test]
+    fn test_export_single_element_fragment() {
+        let mut table = Element::new("table".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        table.x = 120.0;
+        table.y = 340.0;
+        let heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        let export_manager = export_manager_with(vec![table.clone(), heading]);
+
+        let fragment = export_manager.export_element_html("table");
+        assert!(fragment.starts_with(&format!(
+            "<div class=\"element-fragment\" style=\"position: relative; width: {}px; height: {}px;\">",
+            table.width, table.height
+        )));
+        assert!(fragment.contains("<table"));
+        assert!(fragment.contains("left: 0px; top: 0px;"));
+        assert!(!fragment.contains("paper-container"));
+        assert!(!fragment.contains("Heading Text"));
+        assert_eq!(export_manager.export_element_html("missing"), "");
+    }
+
+    #[test]
+    fn test_export_size_estimate_is_close_to_actual() {
+        let types = ["text", "heading", "paragraph", "button", "rectangle", "circle", "line", "checkbox", "table", "barcode"];
+        let elements: Vec<Element> = (0..100)
+            .map(|index| {
+                let element_type = types[index % types.len()];
+                let mut element = Element::new(format!("element-{}", index), element_type.to_string(), element_type.to_string());
+                element.x = (index % 5) as f64 * 150.0;
+                element.y = (index / 5) as f64 * 50.0;
+                if element_type == "table" {
+                    element.create_default_table(3, 3);
+                }
+                element
+            })
+            .collect();
+        let export_manager = export_manager_with(elements);
+
+        // Within 2% of the rendered html + css, with the stylesheet exact
+        for options_json in ["{}", r#"{"printMode":true,"bleedMm":3,"cropMarks":true}"#] {
+            let options = ExportOptions::from_json(options_json);
+            let rendered = export_manager.render(&options);
+            let estimate: serde_json::Value = serde_json::from_str(&export_manager.estimate_export_size(options_json)).unwrap();
+            let (estimated, actual) = (estimate["total"].as_u64().unwrap() as f64, (rendered.html.len() + rendered.css.len()) as f64);
+            assert!((estimated - actual).abs() / actual < 0.02, "estimated {} for {} bytes", estimated, actual);
+            assert_eq!(estimate["css"].as_u64().unwrap() as usize, rendered.css.len());
+        }
+    }
+
+    #[test]
+    fn test_export_table_as_standalone_document() {
+        let mut table = Element::new("table".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(3, 3);
+        table.x = 300.0;
+        assert!(table.merge_table_cells(1, 0, 2, 1));
+        let heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        let export_manager = export_manager_with(vec![table, heading.clone()]);
+
+        let document = export_manager.export_table_html("table");
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("<style>\n\nbody {"));
+        assert!(document.contains("rowspan=\"2\" colspan=\"2\""));
+        assert!(!document.contains("left: 300px"));
+        assert!(!document.contains("paper-container"));
+        assert_eq!(export_manager.export_table_html(&heading.id), "");
+        assert_eq!(export_manager.export_table_html("missing"), "");
+    }
+
+    #[test]
+    fn test_barcode_element_renders_code128_bars() {
+        let mut barcode = Element::new("sku".to_string(), "barcode".to_string(), "barcode".to_string());
+        barcode.content = r#"{"data":"ABC","symbology":"code128"}"#.to_string();
+        let mut invalid = Element::new("invalid".to_string(), "barcode".to_string(), "barcode".to_string());
+        invalid.content = "caf\u{e9}".to_string();
+        let export_manager = export_manager_with(vec![barcode, invalid]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        // Background + 3 bars each for start, A, B, C and checksum + 4 stop bars
+        assert_eq!(html.matches("<rect").count(), 1 + 5 * 3 + 4);
+        assert!(html.contains(">caf\u{e9}</div>"));
+    }
+
+    #[test]
+    fn test_toc_lists_page_titles_with_numbers() {
+        let mut cover = Paper::new("cover".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 0.0);
+        cover.title = None;
+        let mut summary = Paper::new("summary".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 1200.0);
+        summary.title = Some("Summary".to_string());
+        let mut appendix = Paper::new("appendix".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 2400.0);
+        appendix.title = Some("Appendix & Notes".to_string());
+        let export_manager = ExportManager::new(Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(vec![cover, summary, appendix])));
+
+        let toc = export_manager.generate_toc();
+        assert!(toc.contains("<span class=\"toc-title\">Summary</span><span class=\"toc-page\">2</span>"));
+        assert!(toc.contains("<span class=\"toc-title\">Appendix &amp; Notes</span><span class=\"toc-page\">3</span>"));
+        assert_eq!(toc.matches("<li").count(), 2);
+
+        let html = exported_html(&export_manager.export_html(r#"{"showPageTitles":true}"#));
+        assert!(html.contains("<div class=\"page-title\">Summary</div>"));
+        assert!(!exported_html(&export_manager.export_html("{}")).contains("page-title"));
+    }
+
+    #[test]
+    fn test_page_content_bounds() {
+        let mut logo = Element::new("logo".to_string(), "rectangle".to_string(), "rectangle".to_string());
+        logo.x = 20.0;
+        logo.y = 30.0;
+        logo.width = 100.0;
+        logo.height = 50.0;
+        let mut title = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        title.x = 40.0;
+        title.y = 100.0;
+        title.width = 200.0;
+        title.height = 40.0;
+        let first = Paper::new("first".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 0.0);
+        let blank = Paper::new("blank".to_string(), PaperSize::A4, PaperOrientation::Portrait, 0.0, 1200.0);
+        let export_manager = ExportManager::new(Arc::new(Mutex::new(vec![logo, title])), Arc::new(Mutex::new(vec![first, blank])));
+
+        let pages: serde_json::Value = serde_json::from_str(&export_manager.get_page_content_bounds()).unwrap();
+        assert_eq!(pages[0]["paperId"], "first");
+        assert_eq!(pages[0]["isEmpty"], false);
+        assert_eq!(pages[0]["bounds"], serde_json::json!({ "x": 20.0, "y": 30.0, "width": 220.0, "height": 110.0 }));
+        assert!(pages[0]["bounds"]["width"].as_f64().unwrap() < 794.0);
+
+        assert_eq!(pages[1]["isEmpty"], true);
+        assert_eq!(pages[1]["bounds"]["y"], 1200.0);
+        assert_eq!(pages[1]["bounds"]["height"], 1123.0);
+    }
+
+    #[test]
+    fn test_batch_shapes_shares_one_svg() {
+        let rectangles: Vec<Element> = (0..3)
+            .map(|index| {
+                let mut rectangle = Element::new(format!("rect-{}", index), "rectangle".to_string(), "rectangle".to_string());
+                rectangle.x = 40.0 + index as f64 * 120.0;
+                rectangle.y = 40.0;
+                rectangle
+            })
+            .collect();
+        let export_manager = export_manager_with(rectangles);
+
+        let html = exported_html(&export_manager.export_html(r#"{"batchShapes":true}"#));
+        assert_eq!(html.matches("<svg").count(), 1);
+        assert_eq!(html.matches("<rect").count(), 3);
+        assert!(html.contains("<svg class=\"element element-shapes\""));
+        assert!(!html.contains("element-rectangle"));
+
+        let unbatched = exported_html(&export_manager.export_html("{}"));
+        assert_eq!(unbatched.matches("<svg").count(), 3);
+    }
+
+    #[test]
+    fn test_line_renders_cap_join_and_opacity() {
+        let mut line = Element::new("divider".to_string(), "line".to_string(), "line".to_string());
+        line.style.stroke.line_cap = Some("round".to_string());
+        line.style.stroke.line_join = Some("bevel".to_string());
+        line.style.stroke.opacity = 0.5;
+        let plain = Element::new("plain".to_string(), "line".to_string(), "line".to_string());
+        let export_manager = export_manager_with(vec![line, plain]);
+
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert!(html.contains("stroke-opacity=\"0.5\" stroke-linecap=\"round\" stroke-linejoin=\"bevel\""));
+        assert_eq!(html.matches("stroke-linecap").count(), 1);
+        assert!(html.contains("stroke-opacity=\"1\" stroke-dasharray"));
+    }
+
+    #[test]
+    fn test_email_export_uses_tables_without_positioning() {
+        let mut heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        heading.x = 40.0;
+        heading.y = 40.0;
+        heading.content = "Monthly newsletter".to_string();
+        let mut left = Element::new("left".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        left.x = 40.0;
+        left.y = 150.0;
+        left.content = "Left column".to_string();
+        let mut right = Element::new("right".to_string(), "paragraph".to_string(), "paragraph".to_string());
+        right.x = 460.0;
+        right.y = 160.0;
+        right.content = "Right column".to_string();
+        let export_manager = export_manager_with(vec![right, left, heading]);
+
+        let html = export_manager.export_email_html();
+        assert!(!html.contains("position:"));
+        assert!(!html.contains("<style"));
+        assert!(html.contains("<table role=\"presentation\""));
+        // Side-by-side paragraphs share a row, in left-to-right order
+        let title = html.find("Monthly newsletter").unwrap();
+        let left_column = html.find("Left column").unwrap();
+        let right_column = html.find("Right column").unwrap();
+        assert!(title < left_column && left_column < right_column);
+        assert_eq!(html.matches("<tr><td valign=\"top\">").count(), 2);
+    }
+
+    #[test]
+    fn test_email_export_renders_cell_elements_inside_their_cell() {
+        let mut table = Element::new("form".to_string(), "table".to_string(), "table".to_string());
+        table.create_default_table(2, 2);
+        let mut checkbox = Element::new("agree".to_string(), "checkbox".to_string(), "checkbox".to_string());
+        checkbox.x = 400.0;
+        checkbox.y = 600.0;
+        checkbox.content = r#"{"label":"I agree","checked":true}"#.to_string();
+        let export_manager = export_manager_with(vec![table, checkbox]);
+        let table_manager = TableManager::new(export_manager.elements.clone());
+        assert!(table_manager.set_table_cell_element("form", 1, 1, "agree"));
+
+        let html = export_manager.export_email_html();
+        assert_eq!(html.matches("I agree").count(), 1);
+        // One band for the table; the checkbox does not float below it
+        assert_eq!(html.matches("<tr><td valign=\"top\">").count(), 1);
+        let cell = html.find("&#9745; I agree").unwrap();
+        assert!(html[..cell].rfind("<td rowspan=\"1\" colspan=\"1\"").is_some());
+    }
+
+    #[test]
+    fn test_auto_tab_order_follows_reading_order() {
+        let input_at = |id: &str, x: f64, y: f64| {
+            let mut input = Element::new(id.to_string(), "input".to_string(), "input".to_string());
+            input.x = x;
+            input.y = y;
+            input.content = id.to_string();
+            input
+        };
+        let mut heading = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        heading.tab_index = Some(9);
+        let mut explicit = input_at("last", 300.0, 300.0);
+        explicit.tab_index = Some(5);
+        let export_manager = export_manager_with(vec![explicit, input_at("right", 400.0, 100.0), input_at("left", 50.0, 100.0), heading]);
+
+        let html = exported_html(&export_manager.export_html(r#"{"autoTabOrder":true}"#));
+        assert!(html.contains("<input tabindex=\"1\" class=\"element element-input\" type=\"text\" value=\"left\""));
+        assert!(html.contains("<input tabindex=\"2\" class=\"element element-input\" type=\"text\" value=\"right\""));
+        assert!(html.contains("<input tabindex=\"3\" class=\"element element-input\" type=\"text\" value=\"last\""));
+        assert_eq!(html.matches("tabindex").count(), 3);
+
+        // Without the option only explicit indexes on interactive elements are emitted
+        let html = exported_html(&export_manager.export_html("{}"));
+        assert_eq!(html.matches("tabindex").count(), 1);
+        assert!(html.contains("tabindex=\"5\""));
+    }
+}
@@ -12,6 +12,10 @@ mod table;
 mod export;
 mod spatial_index;
 mod style_history;
+mod op_log;
+mod barcode;
+mod chart;
+pub mod york_core;
 
 use types::*;
 use paper::PaperManager;
@@ -22,6 +26,11 @@ use table::TableManager;
 use export::ExportManager;
 use spatial_index::SpatialIndexManager;
 use style_history::StyleHistory;
+use op_log::{ChangeStream, OpLog};
+use utils::Throttler;
+
+/// Minimum time between auto-saves unless changed with `set_autosave_interval`
+const DEFAULT_AUTOSAVE_INTERVAL_MS: f64 = 2000.0;
 
 // Main HTML Builder Engine
 #[wasm_bindgen]
@@ -35,6 +44,13 @@ pub struct HTMLBuilderEngine {
     export_manager: ExportManager,
     spatial_index_manager: SpatialIndexManager,
     style_history: Arc<Mutex<StyleHistory>>,
+    op_log: Mutex<OpLog>,
+    change_stream: Mutex<ChangeStream>,
+    // Elements and papers captured by begin_transaction
+    transaction_snapshot: Mutex<Option<(Vec<Element>, Vec<A4Paper>)>>,
+    // Unsaved changes since the last mark_saved
+    dirty: Mutex<bool>,
+    autosave_throttle: Mutex<Throttler>,
 }
 
 #[wasm_bindgen(start)]
@@ -42,6 +58,13 @@ pub fn main() {
     // WASM module initialized
 }
 
+/// JSON array of `n` distinct hex colors with evenly spaced hues, for coloring categories
+/// (a free function so pivot tables and charts can use it without an engine instance)
+#[wasm_bindgen]
+pub fn generate_palette(n: usize) -> String {
+    serde_json::to_string(&utils::generate_palette(n)).unwrap_or_else(|_| "[]".to_string())
+}
+
 #[wasm_bindgen]
 impl HTMLBuilderEngine {
     #[wasm_bindgen(constructor)]
@@ -56,7 +79,8 @@ impl HTMLBuilderEngine {
         let drag_manager = DragManager::new();
         let transform_manager = TransformManager::new();
         let table_manager = TableManager::new(Arc::clone(&elements));
-        let export_manager = ExportManager::new(Arc::clone(&elements), Arc::clone(&papers));
+        let export_manager = ExportManager::new(Arc::clone(&elements), Arc::clone(&papers))
+            .with_printer_margin(paper_manager.printer_margin_handle());
         let spatial_index_manager = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 100.0);
         let style_history = Arc::new(Mutex::new(StyleHistory::new(50))); // 50 entries max
         
@@ -69,6 +93,11 @@ impl HTMLBuilderEngine {
             export_manager,
             spatial_index_manager,
             style_history,
+            op_log: Mutex::new(OpLog::default()),
+            change_stream: Mutex::new(ChangeStream::default()),
+            transaction_snapshot: Mutex::new(None),
+            dirty: Mutex::new(false),
+            autosave_throttle: Mutex::new(Throttler::new(DEFAULT_AUTOSAVE_INTERVAL_MS)),
         }
     }
 
@@ -76,13 +105,17 @@ impl HTMLBuilderEngine {
     /// สร้าง A4 paper ใหม่ (backward compatibility)
     #[wasm_bindgen]
     pub fn create_a4_paper(&self, x: f64, y: f64) -> String {
-        self.paper_manager.create_a4_paper(x, y)
+        let paper_json = self.paper_manager.create_a4_paper(x, y);
+        self.record_paper_created(&paper_json);
+        paper_json
     }
 
     /// สร้าง paper ใหม่ด้วยขนาดและทิศทางที่กำหนด
     #[wasm_bindgen]
     pub fn create_paper(&self, id: &str, size: &str, orientation: &str, x: f64, y: f64) -> String {
-        self.paper_manager.create_paper(id, size, orientation, x, y)
+        let paper_json = self.paper_manager.create_paper(id, size, orientation, x, y);
+        self.record_paper_created(&paper_json);
+        paper_json
     }
 
     /// ได้ papers ทั้งหมด
@@ -94,13 +127,55 @@ impl HTMLBuilderEngine {
     /// ลบ paper ตาม ID
     #[wasm_bindgen]
     pub fn remove_paper(&self, paper_id: &str) -> bool {
-        self.paper_manager.remove_paper(paper_id)
+        self.record_if(self.paper_manager.remove_paper(paper_id), "paper", paper_id)
+    }
+
+    /// ลบ paper พร้อม elements ที่ export ลงหน้านั้น คืนจำนวน elements ที่ถูกลบ (0 ถ้าไม่พบ paper)
+    #[wasm_bindgen]
+    pub fn remove_paper_with_contents(&self, paper_id: &str) -> usize {
+        self.remove_paper_and_contents(paper_id, false).map_or(0, |(removed, _)| removed.len())
+    }
+
+    /// ลบ paper แล้วย้าย elements ในหน้าไปหน้าก่อนหน้าโดยคงตำแหน่งสัมพันธ์ คืน JSON array ของ ids ที่ถูกย้าย
+    /// (หน้าแรกไม่มีหน้าก่อนหน้า elements จึงถูกลบเหมือน remove_paper_with_contents) หรือ "[]" ถ้าไม่พบ paper
+    #[wasm_bindgen]
+    pub fn remove_paper_with_reflow(&self, paper_id: &str) -> String {
+        let moved = self.remove_paper_and_contents(paper_id, true).map(|(_, moved)| moved).unwrap_or_default();
+        serde_json::to_string(&moved).unwrap_or_else(|_| "[]".to_string())
     }
 
     /// อัปเดตตำแหน่ง paper
     #[wasm_bindgen]
     pub fn update_paper_position(&self, paper_id: &str, x: f64, y: f64) -> bool {
-        self.paper_manager.update_paper_position(paper_id, x, y)
+        self.record_if(self.paper_manager.update_paper_position(paper_id, x, y), "paper", paper_id)
+    }
+
+    /// เปลี่ยนขนาด paper แล้วย้าย elements ที่ยึดขอบตามขนาดใหม่ คืน ids ที่ถูกย้าย
+    #[wasm_bindgen]
+    pub fn reflow_paper(&self, paper_id: &str, new_width: f64, new_height: f64) -> String {
+        let moved_ids = self.paper_manager.reflow_paper(paper_id, new_width, new_height, &self.element_manager);
+        let resized = self.paper_manager.all().iter().any(|paper| paper.id == paper_id && paper.width == new_width && paper.height == new_height);
+        self.record_if(resized, "paper", paper_id);
+        for element_id in &moved_ids {
+            self.record_op("move", element_id);
+        }
+        serde_json::to_string(&moved_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// วาง element เป็นเปอร์เซ็นต์ของ paper ที่ element อยู่ (คำนวณใหม่เมื่อ reflow_paper)
+    #[wasm_bindgen]
+    pub fn set_element_relative(&self, element_id: &str, pct_x: f64, pct_y: f64, pct_w: f64, pct_h: f64) -> bool {
+        let placed = self.paper_manager.set_element_relative(element_id, pct_x, pct_y, pct_w, pct_h, &self.element_manager);
+        if placed {
+            self.record_op("move", element_id);
+        }
+        placed
+    }
+
+    /// ตั้งชื่อ paper (ค่าว่าง = ลบชื่อ)
+    #[wasm_bindgen]
+    pub fn set_paper_title(&self, paper_id: &str, title: &str) -> bool {
+        self.record_if(self.paper_manager.set_paper_title(paper_id, title), "paper", paper_id)
     }
 
     /// นับจำนวน papers
@@ -127,41 +202,277 @@ impl HTMLBuilderEngine {
         self.paper_manager.fit_to_viewport(margin_percent)
     }
 
+    /// ตั้งค่า DPI สำหรับ papers ที่สร้างใหม่ (ค่าเริ่มต้น 96)
+    #[wasm_bindgen]
+    pub fn set_dpi(&self, dpi: f64) -> bool {
+        self.paper_manager.set_dpi(dpi)
+    }
+
+    /// ได้ค่า DPI ปัจจุบัน
+    #[wasm_bindgen]
+    pub fn get_dpi(&self) -> f64 {
+        self.paper_manager.get_dpi()
+    }
+
+    /// ตั้งค่าขอบที่เครื่องพิมพ์พิมพ์ไม่ถึง (มิลลิเมตร ค่าเริ่มต้น 5) ที่ใช้กับ get_printable_area และเส้น printableGuide ตอน export
+    #[wasm_bindgen]
+    pub fn set_printer_margin(&self, margin_mm: f64) -> bool {
+        self.paper_manager.set_printer_margin(margin_mm)
+    }
+
+    /// ได้ค่าขอบเครื่องพิมพ์ปัจจุบัน (มิลลิเมตร)
+    #[wasm_bindgen]
+    pub fn get_printer_margin(&self) -> f64 {
+        self.paper_manager.get_printer_margin()
+    }
+
+    /// พื้นที่พิมพ์ได้ของ paper `{x, y, width, height}` ("null" ถ้าไม่พบ paper)
+    #[wasm_bindgen]
+    pub fn get_printable_area(&self, paper_id: &str) -> String {
+        self.paper_manager.get_printable_area(paper_id)
+    }
+
+    /// ได้ตำแหน่ง element แบบสัมพันธ์กับ paper ที่ element อยู่
+    #[wasm_bindgen]
+    pub fn get_element_paper_coords(&self, element_id: &str) -> String {
+        self.paper_manager.get_element_paper_coords(element_id, &self.element_manager)
+    }
+
+    /// วาง element ที่ตำแหน่งสัมพันธ์ภายใน paper ที่กำหนด
+    #[wasm_bindgen]
+    pub fn set_element_paper_coords(&self, element_id: &str, paper_id: &str, rel_x: f64, rel_y: f64) -> bool {
+        self.record_if(self.paper_manager.set_element_paper_coords(element_id, paper_id, rel_x, rel_y, &self.element_manager), "move", element_id)
+    }
+
+    /// ย้าย element ไปยัง paper อื่น
+    #[wasm_bindgen]
+    pub fn move_element_to_page(&self, element_id: &str, target_paper_id: &str, keep_relative: bool) -> bool {
+        let moved = self.paper_manager.move_element_to_page(element_id, target_paper_id, keep_relative, &self.element_manager);
+        if moved {
+            self.sync_spatial_index(element_id);
+            self.record_op("move", element_id);
+        }
+        moved
+    }
+
+    /// elements บน paper ที่กำหนด (fully_contained = เฉพาะที่อยู่ในหน้าทั้งหมด ไม่รวมที่คร่อมขอบ)
+    #[wasm_bindgen]
+    pub fn get_elements_in_paper(&self, paper_id: &str, fully_contained: bool) -> String {
+        self.paper_manager.get_elements_in_paper(paper_id, fully_contained, &self.element_manager)
+    }
+
+    /// ตรวจหา elements ที่คร่อมขอบหน้าหรืออยู่นอกทุกหน้า
+    #[wasm_bindgen]
+    pub fn get_boundary_violations(&self) -> String {
+        self.paper_manager.get_boundary_violations(&self.element_manager)
+    }
+
     // Element management methods
     /// สร้าง element ใหม่ (working implementation with unique IDs)
     #[wasm_bindgen]
     pub fn create_element(&self, component_type: &str, x: f64, y: f64) -> String {
-        self.element_manager.create_element(component_type, x, y)
+        let element = self.element_manager.create(component_type, x, y);
+        self.record_op("create", &element.id);
+        serde_json::to_string(&element).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// สร้างตาราง rows x cols (รวมแถว header) คืน element JSON หรือ "null" ถ้า rows หรือ cols น้อยกว่า 1
+    #[wasm_bindgen]
+    pub fn create_table(&self, rows: u32, cols: u32, x: f64, y: f64) -> String {
+        match self.element_manager.create_table(rows, cols, x, y) {
+            Some(element) => {
+                self.record_op("create", &element.id);
+                serde_json::to_string(&element).unwrap_or_else(|_| "{}".to_string())
+            }
+            None => "null".to_string(),
+        }
+    }
+
+    /// สร้าง element ใหม่ด้วย id ที่กำหนด (คืน "null" ถ้า id ซ้ำ)
+    #[wasm_bindgen]
+    pub fn create_element_with_id(&self, element_id: &str, component_type: &str, x: f64, y: f64) -> String {
+        match self.element_manager.create_with_id(element_id, component_type, x, y) {
+            Some(element) => {
+                self.record_op("create", &element.id);
+                serde_json::to_string(&element).unwrap_or_else(|_| "{}".to_string())
+            }
+            None => "null".to_string(),
+        }
     }
 
     /// อัพเดทตำแหน่ง element (working implementation)
     #[wasm_bindgen]
     pub fn update_element_position(&self, element_id: &str, x: f64, y: f64) -> bool {
-        self.element_manager.update_element_position(element_id, x, y)
+        let moved = self.element_manager.update_element_position(element_id, x, y);
+        if moved {
+            self.record_op("move", element_id);
+        }
+        moved
     }
 
     /// อัพเดท element size (working implementation)
     #[wasm_bindgen]
     pub fn update_element_size(&self, element_id: &str, width: f64, height: f64) -> bool {
-        self.element_manager.update_element_size(element_id, width, height)
+        let resized = self.element_manager.update_element_size(element_id, width, height);
+        if resized {
+            self.record_op("resize", element_id);
+        }
+        resized
+    }
+
+    /// ปรับขนาด element ให้เท่ากับ element อื่นเมื่อต่างกันไม่เกิน tolerance px (0 = ปิด)
+    #[wasm_bindgen]
+    pub fn set_size_match_tolerance(&self, tolerance: f64) -> bool {
+        self.element_manager.set_size_match_tolerance(tolerance)
+    }
+
+    /// ปัดขนาด element เป็นทวีคูณของ increment เมื่อปรับขนาด (0 = ปิด)
+    #[wasm_bindgen]
+    pub fn set_size_snap(&self, increment: f64) -> bool {
+        self.element_manager.set_size_snap(increment)
+    }
+
+    /// ล็อกสัดส่วน element เมื่อปรับขนาด
+    #[wasm_bindgen]
+    pub fn set_element_lock_aspect(&self, element_id: &str, locked: bool) -> bool {
+        self.record_if(self.element_manager.set_lock_aspect(element_id, locked), "lock_aspect", element_id)
+    }
+
+    /// ตั้งลำดับ tabindex ของ element ใน export (null เพื่อล้างค่า)
+    #[wasm_bindgen]
+    pub fn set_element_tab_index(&self, element_id: &str, tab_index: Option<i32>) -> bool {
+        self.record_if(self.element_manager.set_tab_index(element_id, tab_index), "tab_index", element_id)
+    }
+
+    /// ตั้งค่าการยึด element กับขอบขวา/ล่าง/กึ่งกลางของ paper เมื่อ reflow
+    #[wasm_bindgen]
+    pub fn set_element_anchors(&self, element_id: &str, anchor_right: bool, anchor_bottom: bool, anchor_center: bool) -> bool {
+        self.record_if(self.element_manager.set_anchors(element_id, anchor_right, anchor_bottom, anchor_center), "anchors", element_id)
+    }
+
+    /// ตั้งค่าการขึ้นหน้าใหม่ก่อน/หลัง element และการไม่ตัดหน้ากลาง element เมื่อพิมพ์
+    /// (มีผลเฉพาะ export แบบ flowLayout ที่ elements เรียงต่อกันตามลำดับการอ่าน)
+    #[wasm_bindgen]
+    pub fn set_element_page_breaks(&self, element_id: &str, before: bool, after: bool, inside_avoid: bool) -> bool {
+        self.record_if(self.element_manager.set_page_breaks(element_id, before, after, inside_avoid), "page_breaks", element_id)
     }
 
     /// อัพเดท element content
     #[wasm_bindgen]
     pub fn update_element_content(&self, element_id: &str, content: &str) -> bool {
-        self.element_manager.update_element_content(element_id, content)
+        let updated = self.element_manager.update_element_content(element_id, content);
+        if updated {
+            self.record_op("content", element_id);
+            for linked_id in self.element_manager.propagate_linked_content(element_id) {
+                self.record_op("content", &linked_id);
+            }
+        }
+        updated
+    }
+
+    /// เชื่อม target ให้รับ content ของ source ทุกครั้งที่ update_element_content ของ source (false ถ้าทำให้เกิดวงวน)
+    #[wasm_bindgen]
+    pub fn link_elements(&self, source_id: &str, target_id: &str) -> bool {
+        self.record_if(self.element_manager.link_elements(source_id, target_id), "link", target_id)
+    }
+
+    /// ยกเลิกการเชื่อม content ของ target
+    #[wasm_bindgen]
+    pub fn unlink_element(&self, target_id: &str) -> bool {
+        self.record_if(self.element_manager.unlink_element(target_id), "link", target_id)
     }
 
     /// อัพเดท element style
     #[wasm_bindgen]
     pub fn update_element_style(&self, element_id: &str, style_json: &str) -> bool {
-        self.element_manager.update_element_style(element_id, style_json)
+        let styled = self.element_manager.update_element_style(element_id, style_json);
+        if styled {
+            self.record_op("style", element_id);
+        }
+        styled
+    }
+
+    /// อัพเดท style เดียวกันให้หลาย elements และบันทึก history เพียงครั้งเดียว
+    #[wasm_bindgen]
+    pub fn batch_update_style(&self, ids_json: &str, style_json: &str) -> String {
+        let updated_ids = self.element_manager.batch_update_style(ids_json, style_json);
+        for element_id in &updated_ids {
+            self.record_op("style", element_id);
+        }
+        if let Some(element) = updated_ids.first().and_then(|id| self.element_manager.find(id)) {
+            if let Ok(mut history) = self.style_history.lock() {
+                history.add_style(element.style);
+            }
+        }
+        serde_json::to_string(&updated_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// แยก elements ที่ทับกันด้วยวิธี "push" หรือ "grid" คืน JSON array ของตำแหน่งใหม่ `{id, x, y}`
+    #[wasm_bindgen]
+    pub fn resolve_overlaps(&self, ids_json: &str, strategy: &str) -> String {
+        let positions = self.element_manager.resolve_overlaps(ids_json, strategy);
+        for (element_id, _, _) in &positions {
+            self.record_op("move", element_id);
+        }
+        serde_json::Value::Array(
+            positions
+                .into_iter()
+                .map(|(id, x, y)| serde_json::json!({ "id": id, "x": x, "y": y }))
+                .collect(),
+        )
+        .to_string()
+    }
+
+    /// จัด elements เป็นตาราง (grid) เริ่มที่ (start_x, start_y) แล้วคืน ids ที่ถูกย้าย
+    #[wasm_bindgen]
+    pub fn arrange_grid(&self, ids_json: &str, cols: usize, gap: f64, start_x: f64, start_y: f64) -> String {
+        let updated_ids = self.element_manager.arrange_grid(ids_json, cols, gap, start_x, start_y);
+        for element_id in &updated_ids {
+            self.record_op("move", element_id);
+        }
+        serde_json::to_string(&updated_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// กำหนด slot key ให้ element สำหรับ template (ค่าว่าง = ยกเลิก)
+    #[wasm_bindgen]
+    pub fn set_element_slot(&self, element_id: &str, slot_key: &str) -> bool {
+        self.record_if(self.element_manager.set_slot_key(element_id, slot_key), "slot", element_id)
+    }
+
+    /// ได้รายการ template slots
+    #[wasm_bindgen]
+    pub fn get_slots(&self) -> String {
+        self.element_manager.get_slots()
+    }
+
+    /// เติมค่าลงใน slot ตาม key
+    #[wasm_bindgen]
+    pub fn fill_slot(&self, slot_key: &str, content: &str) -> bool {
+        self.mark_dirty_if(self.element_manager.fill_slot(slot_key, content))
+    }
+
+    /// Mail-merge: สร้างหน้าใหม่จากหน้าแรกต่อหนึ่ง record แล้วเติม slots
+    #[wasm_bindgen]
+    pub fn merge_data(&self, records_json: &str) -> String {
+        let existing: std::collections::HashSet<String> = self.element_manager.all().into_iter().map(|element| element.id).collect();
+        let new_paper_ids = self.paper_manager.merge_data(records_json, &self.element_manager);
+        for paper_id in serde_json::from_str::<Vec<String>>(&new_paper_ids).unwrap_or_default() {
+            self.record_op("paper", &paper_id);
+        }
+        for element in self.element_manager.all().into_iter().filter(|element| !existing.contains(&element.id)) {
+            self.record_op("create", &element.id);
+        }
+        new_paper_ids
     }
 
     /// ลบ element (working implementation)
     #[wasm_bindgen]
     pub fn delete_element(&self, element_id: &str) -> bool {
-        self.element_manager.delete_element(element_id)
+        let deleted = self.element_manager.delete_element(element_id);
+        if deleted {
+            self.record_op("delete", element_id);
+        }
+        deleted
     }
 
     /// ได้ element ตาม ID (working implementation)
@@ -176,6 +487,29 @@ impl HTMLBuilderEngine {
         self.element_manager.get_all_elements()
     }
 
+    /// ได้ elements ทั้งหมดเรียงตาม "created_desc", "type" หรือ "z_index"
+    #[wasm_bindgen]
+    pub fn get_all_elements_sorted(&self, by: &str) -> String {
+        self.element_manager.get_all_elements_sorted(by)
+    }
+
+    /// เรียง elements ที่เก็บไว้ตามลำดับคงที่ (z_index แล้ว id)
+    #[wasm_bindgen]
+    pub fn sort_elements(&self) {
+        self.element_manager.sort_elements()
+    }
+
+    /// จัด z_index ให้ต่อเนื่อง (0..n-1) โดยคงลำดับการแสดงผล คืน JSON object ของ id → z_index ใหม่
+    #[wasm_bindgen]
+    pub fn normalize_z_indices(&self) -> String {
+        let mut mapping = serde_json::Map::new();
+        for (element_id, z_index) in self.element_manager.normalize_z_indices() {
+            self.record_op("reorder", &element_id);
+            mapping.insert(element_id, serde_json::json!(z_index));
+        }
+        serde_json::Value::Object(mapping).to_string()
+    }
+
     /// ได้จำนวน elements
     #[wasm_bindgen]
     pub fn get_element_count(&self) -> usize {
@@ -195,7 +529,19 @@ impl HTMLBuilderEngine {
     /// อัพเดท drag operation (working implementation)
     #[wasm_bindgen]
     pub fn update_drag(&self, mouse_x: f64, mouse_y: f64, zoom: f64, pan_x: f64, pan_y: f64) -> String {
-        self.drag_manager.update_drag(mouse_x, mouse_y, zoom, pan_x, pan_y, &self.element_manager)
+        let result = self.drag_manager.update_drag(mouse_x, mouse_y, zoom, pan_x, pan_y, &self.element_manager);
+        if let Ok(update) = serde_json::from_str::<DragUpdateResult>(&result) {
+            if update.is_valid {
+                self.record_op("move", &update.element_id);
+            }
+        }
+        result
+    }
+
+    /// ล็อกการ drag ให้เคลื่อนที่แกนเดียว: "none", "x" หรือ "y"
+    #[wasm_bindgen]
+    pub fn set_drag_axis_lock(&self, axis: &str) -> bool {
+        self.drag_manager.set_drag_axis_lock(axis)
     }
 
     /// จบ drag operation (working implementation)
@@ -223,9 +569,18 @@ impl HTMLBuilderEngine {
         self.transform_manager.set_transform(zoom, pan_x, pan_y)
     }
 
-    /// Zoom ไปยังจุดที่กำหนด
+    /// เลื่อนและซูมให้ element อยู่กึ่งกลาง viewport และพอดีภายใน padding คืน transform ใหม่ หรือ "null" ถ้าไม่พบ element
     #[wasm_bindgen]
-    pub fn zoom_to_point(&self, screen_x: f64, screen_y: f64, zoom_delta: f64) -> f64 {
+    pub fn focus_element(&self, element_id: &str, viewport_width: f64, viewport_height: f64, padding: f64) -> String {
+        match self.element_manager.find(element_id) {
+            Some(element) => self.transform_manager.fit_rect(element.get_bounds(), viewport_width, viewport_height, padding),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Zoom ไปยังจุดที่กำหนด คืน JSON ของ transform ใหม่ `{zoom, pan_x, pan_y}`
+    #[wasm_bindgen]
+    pub fn zoom_to_point(&self, screen_x: f64, screen_y: f64, zoom_delta: f64) -> String {
         self.transform_manager.zoom_to_point(screen_x, screen_y, zoom_delta)
     }
 
@@ -242,10 +597,78 @@ impl HTMLBuilderEngine {
     }
 
     // Utility methods
-    /// ตรวจสอบการชน (minimal implementation)
+    /// ตรวจสอบการชน
     #[wasm_bindgen]
     pub fn check_collisions(&self, element_id: &str) -> String {
-        self.element_manager.check_collisions(element_id)
+        match self.element_collisions(element_id) {
+            Some(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+            None => self.element_manager.check_collisions(element_id),
+        }
+    }
+
+    /// วัดระยะห่างระหว่างสอง elements (horizontal/vertical gap และระยะระหว่างจุดศูนย์กลาง)
+    #[wasm_bindgen]
+    pub fn measure_between(&self, id_a: &str, id_b: &str) -> String {
+        self.element_manager.measure_between(id_a, id_b)
+    }
+
+    /// จุดยึด 8 จุดของ element (มุมและกึ่งกลางขอบ) ในพิกัด canvas
+    #[wasm_bindgen]
+    pub fn get_element_anchors(&self, element_id: &str) -> String {
+        self.element_manager.get_element_anchors(element_id)
+    }
+
+    /// ย้ายปลายเส้นและ snap เข้าจุดยึดของ element ที่ใกล้ที่สุด คืน `{x, y, snapped, elementId, anchor}`
+    #[wasm_bindgen]
+    pub fn snap_line_endpoint(&self, line_id: &str, which: &str, x: f64, y: f64, threshold: f64) -> String {
+        let result = self.element_manager.snap_line_endpoint(line_id, which, x, y, threshold);
+        if result != "null" {
+            // The line box is refitted around the new endpoints
+            self.record_op("resize", line_id);
+            self.record_op("content", line_id);
+        }
+        result
+    }
+
+    /// คัดลอก elements (ids เป็น JSON array) เป็น payload JSON ที่ใช้ paste ข้าม engine instance ได้
+    #[wasm_bindgen]
+    pub fn copy_elements(&self, ids_json: &str) -> String {
+        self.element_manager.copy_elements(ids_json)
+    }
+
+    /// วาง elements จาก payload ของ copy_elements ด้วย id ใหม่ เลื่อนไป (offset_x, offset_y)
+    /// คืน JSON array ของ elements ที่สร้าง ("[]" ถ้า payload ไม่ถูกต้อง)
+    #[wasm_bindgen]
+    pub fn paste_elements_from(&self, payload: &str, offset_x: f64, offset_y: f64) -> String {
+        let Some(pasted) = self.element_manager.paste_elements_from(payload, offset_x, offset_y) else {
+            return "[]".to_string();
+        };
+        for element in &pasted {
+            self.record_op("create", &element.id);
+        }
+        serde_json::to_string(&pasted).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// ตรวจการอ้างอิงถึง element ที่ไม่มีแล้ว คืน JSON array ของ `{elementId, kind, referenceId}`
+    #[wasm_bindgen]
+    pub fn check_integrity(&self) -> String {
+        self.element_manager.check_integrity()
+    }
+
+    /// หา elements ที่ซ้ำกันวางทับกัน คืน JSON array ของกลุ่ม ids
+    #[wasm_bindgen]
+    pub fn find_duplicate_elements(&self) -> String {
+        self.element_manager.find_duplicate_elements()
+    }
+
+    /// Estimated text size `{width, height}` in pixels, matching the engine's own autosizing
+    #[wasm_bindgen]
+    pub fn estimate_text_size(&self, text: &str, font_size: f64, font_family: &str, font_weight: &str, max_width: f64) -> String {
+        serde_json::json!({
+            "width": utils::estimate_text_width(text, font_size, font_family, font_weight),
+            "height": utils::estimate_text_height(text, font_size, font_family, font_weight, max_width),
+        })
+        .to_string()
     }
 
     /// หา elements ในพื้นที่ที่กำหนด (minimal implementation)
@@ -254,10 +677,15 @@ impl HTMLBuilderEngine {
         self.element_manager.get_elements_in_region(x, y, width, height)
     }
 
-    /// อัพเดทตำแหน่งหลาย elements พร้อมกัน (minimal implementation)
+    /// อัพเดทตำแหน่งหลาย elements พร้อมกัน (JSON array ของ `{element_id, x, y}`) คืน JSON array ของผลแต่ละรายการ
     #[wasm_bindgen]
     pub fn batch_update_positions(&self, updates_json: &str) -> String {
-        self.element_manager.batch_update_positions(updates_json)
+        let results = self.element_manager.batch_update_positions(updates_json);
+        for (element_id, _) in results.iter().filter(|(_, moved)| *moved) {
+            self.record_op("move", element_id);
+        }
+        let moved: Vec<bool> = results.into_iter().map(|(_, moved)| moved).collect();
+        serde_json::to_string(&moved).unwrap_or_else(|_| "[]".to_string())
     }
 
     /// Export HTML (complete implementation)
@@ -266,6 +694,85 @@ impl HTMLBuilderEngine {
         self.export_manager.export_html(options_json)
     }
 
+    /// Export HTML for print/PDF without interactive controls
+    #[wasm_bindgen]
+    pub fn export_print_html(&self, options_json: &str) -> String {
+        self.export_manager.export_print_html(options_json)
+    }
+
+    /// Export HTML with `{{key}}` placeholders resolved from a JSON object of values
+    #[wasm_bindgen]
+    pub fn export_html_with_context(&self, options_json: &str, context_json: &str) -> String {
+        self.export_manager.export_html_with_context(options_json, context_json)
+    }
+
+    /// Export email-safe HTML: layout tables with inline styles, no `<style>` or positioning
+    #[wasm_bindgen]
+    pub fn export_email_html(&self) -> String {
+        self.export_manager.export_email_html()
+    }
+
+    /// Per-page bounding box of the page's content, for thumbnails
+    #[wasm_bindgen]
+    pub fn get_page_content_bounds(&self) -> String {
+        self.export_manager.get_page_content_bounds()
+    }
+
+    /// Table of contents listing each titled page with its page number
+    #[wasm_bindgen]
+    pub fn generate_toc(&self) -> String {
+        self.export_manager.generate_toc()
+    }
+
+    /// Export one element's markup at (0, 0), sized to the element, for thumbnails and previews
+    #[wasm_bindgen]
+    pub fn export_element_html(&self, element_id: &str) -> String {
+        self.export_manager.export_element_html(element_id)
+    }
+
+    /// Number of pages for export_html_page
+    #[wasm_bindgen]
+    pub fn get_export_page_count(&self) -> usize {
+        self.export_manager.get_export_page_count()
+    }
+
+    /// HTML of one page, so large documents can be exported and streamed page by page
+    /// (the stylesheet comes once from get_export_css with the same options)
+    #[wasm_bindgen]
+    pub fn export_html_page(&self, page_index: usize, options_json: &str) -> String {
+        self.export_manager.export_html_page(page_index, options_json)
+    }
+
+    /// Base stylesheet of the export (without HTML), for injecting into the editor DOM
+    #[wasm_bindgen]
+    pub fn get_base_css(&self) -> String {
+        self.export_manager.get_base_css()
+    }
+
+    /// Full stylesheet of an export with these options, the `css` export_html would return
+    #[wasm_bindgen]
+    pub fn get_export_css(&self, options_json: &str) -> String {
+        self.export_manager.get_export_css(options_json)
+    }
+
+    /// Approximate byte counts `{html, css, total}` of an export, computed without rendering it
+    #[wasm_bindgen]
+    pub fn estimate_export_size(&self, options_json: &str) -> String {
+        self.export_manager.estimate_export_size(options_json)
+    }
+
+    /// Export a single table as its own styled HTML document (merges and cell styles kept)
+    #[wasm_bindgen]
+    pub fn export_table_html(&self, element_id: &str) -> String {
+        self.export_manager.export_table_html(element_id)
+    }
+
+    /// Export a self-contained HTML document (inline CSS and embedded fonts)
+    #[wasm_bindgen]
+    pub fn export_standalone_html(&self, options_json: &str) -> String {
+        self.export_manager.export_standalone_html(options_json)
+    }
+
     /// ได้สถิติการทำงาน (minimal implementation)
     #[wasm_bindgen]
     pub fn get_performance_stats(&self) -> String {
@@ -282,46 +789,119 @@ impl HTMLBuilderEngine {
     }
 
     // Table-specific methods
+    /// Set default cell padding, row height and column width for new rows/columns
+    #[wasm_bindgen]
+    pub fn set_table_defaults(&self, element_id: &str, cell_padding: f64, row_height: f64, col_width: f64) -> bool {
+        self.record_if(self.table_manager.set_table_defaults(element_id, cell_padding, row_height, col_width), "table", element_id)
+    }
+
+    /// Append a computed totals row (column sums) and/or totals column (row sums)
+    #[wasm_bindgen]
+    pub fn set_table_totals(&self, element_id: &str, totals_row: bool, totals_col: bool) -> bool {
+        self.record_if(self.table_manager.set_table_totals(element_id, totals_row, totals_col), "table", element_id)
+    }
+
     /// Add row to table
     #[wasm_bindgen]
     pub fn add_table_row(&self, element_id: &str, at_index: Option<usize>) -> bool {
-        self.table_manager.add_table_row(element_id, at_index)
+        self.record_if(self.table_manager.add_table_row(element_id, at_index), "table", element_id)
     }
 
     /// Remove row from table
     #[wasm_bindgen]
     pub fn remove_table_row(&self, element_id: &str, index: usize) -> bool {
-        self.table_manager.remove_table_row(element_id, index)
+        self.record_if(self.table_manager.remove_table_row(element_id, index), "table", element_id)
+    }
+
+    /// Insert a column numbering the data rows from start (at_index defaults to the first column)
+    #[wasm_bindgen]
+    pub fn add_table_sequence_column(&self, element_id: &str, start: i32, at_index: Option<usize>) -> bool {
+        self.record_if(self.table_manager.add_table_sequence_column(element_id, start, at_index), "table", element_id)
+    }
+
+    /// Set the number format numeric cells are exported with (thousands/decimal separators, decimals);
+    /// empty or "null" clears it
+    #[wasm_bindgen]
+    pub fn set_table_number_format(&self, element_id: &str, format_json: &str) -> bool {
+        self.record_if(self.table_manager.set_table_number_format(element_id, format_json), "table", element_id)
+    }
+
+    /// Restyle the whole table with a theme: "striped", "bordered", "minimal" or "header-dark"
+    #[wasm_bindgen]
+    pub fn apply_table_theme(&self, element_id: &str, theme: &str) -> bool {
+        self.record_if(self.table_manager.apply_table_theme(element_id, theme), "table", element_id)
+    }
+
+    /// Move a table row to another position, keeping its cells, styles and height
+    #[wasm_bindgen]
+    pub fn move_table_row(&self, element_id: &str, from: usize, to: usize) -> bool {
+        self.record_if(self.table_manager.move_table_row(element_id, from, to), "table", element_id)
+    }
+
+    /// Move a table column to another position, keeping its cells, styles and width
+    #[wasm_bindgen]
+    pub fn move_table_column(&self, element_id: &str, from: usize, to: usize) -> bool {
+        self.record_if(self.table_manager.move_table_column(element_id, from, to), "table", element_id)
     }
 
     /// Add column to table
     #[wasm_bindgen]
     pub fn add_table_column(&self, element_id: &str, at_index: Option<usize>) -> bool {
-        self.table_manager.add_table_column(element_id, at_index)
+        self.record_if(self.table_manager.add_table_column(element_id, at_index), "table", element_id)
     }
 
     /// Remove column from table
     #[wasm_bindgen]
     pub fn remove_table_column(&self, element_id: &str, index: usize) -> bool {
-        self.table_manager.remove_table_column(element_id, index)
+        self.record_if(self.table_manager.remove_table_column(element_id, index), "table", element_id)
     }
 
     /// Update table cell content
     #[wasm_bindgen]
     pub fn update_table_cell(&self, element_id: &str, row: usize, col: usize, content: &str) -> bool {
-        self.table_manager.update_table_cell(element_id, row, col, content)
+        self.record_if(self.table_manager.update_table_cell(element_id, row, col, content), "table", element_id)
+    }
+
+    /// Render an existing element inside a table cell instead of its text (empty element_id clears it)
+    #[wasm_bindgen]
+    pub fn set_table_cell_element(&self, table_id: &str, row: usize, col: usize, element_id: &str) -> bool {
+        self.record_if(self.table_manager.set_table_cell_element(table_id, row, col, element_id), "table", table_id)
     }
 
     /// Update table cell style
     #[wasm_bindgen]
     pub fn update_table_cell_style(&self, element_id: &str, row: usize, col: usize, style_json: &str) -> bool {
-        self.table_manager.update_table_cell_style(element_id, row, col, style_json)
+        self.record_if(self.table_manager.update_table_cell_style(element_id, row, col, style_json), "table", element_id)
     }
 
     /// Merge table cells
     #[wasm_bindgen]
     pub fn merge_table_cells(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> bool {
-        self.table_manager.merge_table_cells(element_id, start_row, start_col, end_row, end_col)
+        self.record_if(self.table_manager.merge_table_cells(element_id, start_row, start_col, end_row, end_col), "table", element_id)
+    }
+
+    /// Clear contents of a cell range
+    #[wasm_bindgen]
+    pub fn clear_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> bool {
+        self.record_if(self.table_manager.clear_table_range(element_id, start_row, start_col, end_row, end_col), "table", element_id)
+    }
+
+    /// Fill a cell range with the same value
+    #[wasm_bindgen]
+    pub fn fill_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize, value: &str) -> bool {
+        self.record_if(self.table_manager.fill_table_range(element_id, start_row, start_col, end_row, end_col, value), "table", element_id)
+    }
+
+    /// Copy a cell range (contents and styles) as JSON
+    #[wasm_bindgen]
+    pub fn copy_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> String {
+        self.table_manager.copy_table_range(element_id, start_row, start_col, end_row, end_col)
+    }
+
+    /// Paste a copied cell range anchored at the target cell
+    #[wasm_bindgen]
+    pub fn paste_table_range(&self, element_id: &str, target_row: usize, target_col: usize, block_json: &str) -> bool {
+        self.record_if(self.table_manager.paste_table_range(element_id, target_row, target_col, block_json), "table", element_id)
     }
 
     /// Get table data
@@ -333,13 +913,13 @@ impl HTMLBuilderEngine {
     /// Update table column width
     #[wasm_bindgen]
     pub fn update_table_column_width(&self, element_id: &str, column_index: usize, width: f64) -> bool {
-        self.table_manager.update_table_column_width(element_id, column_index, width)
+        self.record_if(self.table_manager.update_table_column_width(element_id, column_index, width), "table", element_id)
     }
 
     /// Update table row height
     #[wasm_bindgen]
     pub fn update_table_row_height(&self, element_id: &str, row_index: usize, height: f64) -> bool {
-        self.table_manager.update_table_row_height(element_id, row_index, height)
+        self.record_if(self.table_manager.update_table_row_height(element_id, row_index, height), "table", element_id)
     }
 
     /// Calculate sum of column (Excel-like function)
@@ -363,13 +943,13 @@ impl HTMLBuilderEngine {
     /// Auto-fit columns based on content (Excel-like function)
     #[wasm_bindgen]
     pub fn auto_fit_columns(&self, element_id: &str) -> bool {
-        self.table_manager.auto_fit_columns(element_id)
+        self.record_if(self.table_manager.auto_fit_columns(element_id), "table", element_id)
     }
 
     /// Unmerge table cells
     #[wasm_bindgen]
     pub fn unmerge_table_cells(&self, element_id: &str, row: usize, col: usize) -> bool {
-        self.table_manager.unmerge_table_cells(element_id, row, col)
+        self.record_if(self.table_manager.unmerge_table_cells(element_id, row, col), "table", element_id)
     }
 
     /// Check if a cell is merged
@@ -378,6 +958,12 @@ impl HTMLBuilderEngine {
         self.table_manager.is_cell_merged(element_id, row, col)
     }
 
+    /// Next editable cell from (row, col) in a direction ("right", "left", "up", "down", "tab")
+    #[wasm_bindgen]
+    pub fn table_next_cell(&self, element_id: &str, row: usize, col: usize, direction: &str) -> String {
+        self.table_manager.table_next_cell(element_id, row, col, direction)
+    }
+
     /// Get table dimensions for export
     #[wasm_bindgen]
     pub fn get_table_dimensions(&self, element_id: &str) -> String {
@@ -412,7 +998,11 @@ impl HTMLBuilderEngine {
     /// Batch update form field content efficiently
     #[wasm_bindgen]
     pub fn batch_update_form_field(&self, element_id: &str, updates_json: &str) -> bool {
-        self.element_manager.batch_update_form_field(element_id, updates_json)
+        let updated = self.element_manager.batch_update_form_field(element_id, updates_json);
+        if updated {
+            self.record_op("content", element_id);
+        }
+        updated
     }
 
     /// Get optimized element data for StylePanel
@@ -440,18 +1030,55 @@ impl HTMLBuilderEngine {
         self.spatial_index_manager.query_region(x, y, width, height)
     }
 
+    /// Serialize the spatial index for a warm restart
+    #[wasm_bindgen]
+    pub fn serialize_spatial_index(&self) -> String {
+        self.spatial_index_manager.serialize()
+    }
+
+    /// Restore a serialized spatial index instead of rebuilding it
+    #[wasm_bindgen]
+    pub fn restore_spatial_index(&self, json: &str) -> bool {
+        self.spatial_index_manager.deserialize(json)
+    }
+
+    /// Papers and elements visible in a screen-space viewport, z-sorted with screen coordinates
+    #[wasm_bindgen]
+    pub fn get_render_snapshot(&self, viewport_x: f64, viewport_y: f64, viewport_w: f64, viewport_h: f64) -> String {
+        let snapshot = self.render_snapshot(viewport_x, viewport_y, viewport_w, viewport_h);
+        serde_json::to_string(&snapshot).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Ids of elements in a selection rectangle; `mode` is "intersect" or "contain"
+    #[wasm_bindgen]
+    pub fn select_in_rect(&self, x: f64, y: f64, width: f64, height: f64, mode: &str) -> String {
+        self.spatial_index_manager.select_in_rect(x, y, width, height, mode)
+    }
+
     /// Find elements at a specific point using spatial indexing
     #[wasm_bindgen]
     pub fn find_elements_at_point(&self, x: f64, y: f64) -> String {
         self.spatial_index_manager.find_at_point(x, y)
     }
 
+    /// Find the element a click at the point should select (highest z-index)
+    #[wasm_bindgen]
+    pub fn find_topmost_at_point(&self, x: f64, y: f64) -> String {
+        self.element_manager.find_topmost_at_point(x, y)
+    }
+
     /// Find nearest element to a point using spatial indexing
     #[wasm_bindgen]
     pub fn find_nearest_element(&self, x: f64, y: f64, max_distance: f64) -> String {
         self.spatial_index_manager.find_nearest(x, y, max_distance)
     }
 
+    /// Find elements crossed by a line segment using spatial indexing
+    #[wasm_bindgen]
+    pub fn query_segment(&self, x1: f64, y1: f64, x2: f64, y2: f64) -> String {
+        self.spatial_index_manager.query_segment(x1, y1, x2, y2)
+    }
+
     /// Detect collisions for an element using spatial indexing
     #[wasm_bindgen]
     pub fn detect_element_collisions(&self, element_id: &str) -> String {
@@ -497,6 +1124,173 @@ impl HTMLBuilderEngine {
         self.spatial_index_manager.auto_optimize()
     }
 
+    /// Configure when auto-optimize rebuilds the spatial index
+    #[wasm_bindgen]
+    pub fn set_auto_optimize_thresholds(&self, min_elements: usize, max_avg_per_cell: f64, max_per_cell: usize) -> bool {
+        self.spatial_index_manager.set_auto_optimize_thresholds(min_elements, max_avg_per_cell, max_per_cell)
+    }
+
+    // Document methods
+    /// Export papers, elements and the current zoom/pan as a JSON document
+    #[wasm_bindgen]
+    pub fn export_document(&self) -> String {
+        serde_json::to_string(&self.document()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Replace the document with one from export_document, restoring its zoom/pan (the loaded document counts as saved).
+    /// Returns false, changing nothing, for a newer document version or repeated element/paper IDs
+    #[wasm_bindgen]
+    pub fn import_document(&self, document_json: &str) -> bool {
+        match serde_json::from_str::<york_core::Document>(document_json) {
+            Ok(document) => self.load_document(document),
+            Err(_) => false,
+        }
+    }
+
+    /// Full engine state as JSON: papers, elements, zoom/pan, snap and drag settings, spatial grid and style history
+    #[wasm_bindgen]
+    pub fn snapshot(&self) -> String {
+        serde_json::to_string(&self.engine_snapshot()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Replace the whole engine state with one from snapshot and rebuild the spatial index (the restored state counts as saved;
+    /// an open transaction, the op log and pending change-stream records are dropped)
+    #[wasm_bindgen]
+    pub fn restore(&self, snapshot_json: &str) -> bool {
+        match serde_json::from_str::<york_core::EngineSnapshot>(snapshot_json) {
+            Ok(snapshot) => self.restore_snapshot(snapshot),
+            Err(_) => false,
+        }
+    }
+
+    // Transaction methods
+    /// Snapshot elements and papers so a multi-step edit can be rolled back (nested transactions are rejected)
+    #[wasm_bindgen]
+    pub fn begin_transaction(&self) -> bool {
+        let mut snapshot = utils::lock(&self.transaction_snapshot);
+        if snapshot.is_some() {
+            return false;
+        }
+        *snapshot = Some((self.element_manager.all(), self.paper_manager.all()));
+        true
+    }
+
+    /// Keep the changes made since begin_transaction
+    #[wasm_bindgen]
+    pub fn commit_transaction(&self) -> bool {
+        utils::lock(&self.transaction_snapshot).take().is_some()
+    }
+
+    /// Restore elements and papers to their state at begin_transaction and rebuild the spatial index.
+    /// Records a "reset" op whose fields are the whole document (as export_document), so change-stream
+    /// peers replace their state instead of replaying the rolled-back edits
+    #[wasm_bindgen]
+    pub fn rollback_transaction(&self) -> bool {
+        match utils::lock(&self.transaction_snapshot).take() {
+            Some((elements, papers)) => {
+                let (bounds, cell_size) = self.spatial_index_manager.grid_geometry();
+                self.spatial_index_manager.rebuild(&elements, bounds, cell_size);
+                self.element_manager.replace_all(elements);
+                self.paper_manager.replace_all(papers);
+                self.record_op("reset", "");
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check whether a transaction is open
+    #[wasm_bindgen]
+    pub fn is_in_transaction(&self) -> bool {
+        utils::lock(&self.transaction_snapshot).is_some()
+    }
+
+    /// Reuse deleted element allocations when elements are rapidly added and removed
+    #[wasm_bindgen]
+    pub fn set_element_pooling(&self, enabled: bool) {
+        self.element_manager.set_element_pooling(enabled)
+    }
+
+    // Auto-save methods
+    /// Flag unsaved changes (every document and style history edit sets it automatically)
+    #[wasm_bindgen]
+    pub fn mark_dirty(&self) {
+        *utils::lock(&self.dirty) = true;
+    }
+
+    /// Check whether there are changes since the last mark_saved
+    #[wasm_bindgen]
+    pub fn is_dirty(&self) -> bool {
+        *utils::lock(&self.dirty)
+    }
+
+    /// Clear the dirty flag after the document has been saved
+    #[wasm_bindgen]
+    pub fn mark_saved(&self) {
+        *utils::lock(&self.dirty) = false;
+    }
+
+    /// Set the minimum time between auto-saves in milliseconds
+    #[wasm_bindgen]
+    pub fn set_autosave_interval(&self, interval_ms: f64) -> bool {
+        if !(interval_ms.is_finite() && interval_ms >= 0.0) {
+            return false;
+        }
+        utils::lock(&self.autosave_throttle).set_interval(interval_ms);
+        true
+    }
+
+    /// Check after an edit whether to save now: true when dirty and the auto-save interval has passed
+    #[wasm_bindgen]
+    pub fn should_autosave(&self) -> bool {
+        self.is_dirty() && utils::lock(&self.autosave_throttle).should_execute()
+    }
+
+    // Operation log methods
+    /// Enable or disable the diagnostic operation log
+    #[wasm_bindgen]
+    pub fn enable_op_log(&self, enabled: bool) {
+        if let Ok(mut op_log) = self.op_log.lock() {
+            op_log.set_enabled(enabled);
+        }
+    }
+
+    /// Get logged operations (JSON array of {op, elementId, timestamp}, oldest first)
+    #[wasm_bindgen]
+    pub fn get_op_log(&self) -> String {
+        if let Ok(op_log) = self.op_log.lock() {
+            serde_json::to_string(&op_log.entries()).unwrap_or_else(|_| "[]".to_string())
+        } else {
+            "[]".to_string()
+        }
+    }
+
+    /// Clear the operation log
+    #[wasm_bindgen]
+    pub fn clear_op_log(&self) {
+        if let Ok(mut op_log) = self.op_log.lock() {
+            op_log.clear();
+        }
+    }
+
+    /// Enable or disable the change stream used for live collaboration
+    #[wasm_bindgen]
+    pub fn enable_change_stream(&self, enabled: bool) {
+        if let Ok(mut change_stream) = self.change_stream.lock() {
+            change_stream.set_enabled(enabled);
+        }
+    }
+
+    /// Take pending changes (JSON array of {op, elementId, fields, timestamp}, oldest first) and clear them
+    #[wasm_bindgen]
+    pub fn drain_change_stream(&self) -> String {
+        if let Ok(mut change_stream) = self.change_stream.lock() {
+            serde_json::to_string(&change_stream.drain()).unwrap_or_else(|_| "[]".to_string())
+        } else {
+            "[]".to_string()
+        }
+    }
+
     // Style History methods
     /// Save style to history
     #[wasm_bindgen]
@@ -504,6 +1298,7 @@ impl HTMLBuilderEngine {
         if let Ok(style) = serde_json::from_str::<ElementStyle>(style_json) {
             if let Ok(mut history) = self.style_history.lock() {
                 history.add_style(style);
+                self.mark_dirty();
                 return true;
             }
         }
@@ -535,12 +1330,37 @@ impl HTMLBuilderEngine {
         }
     }
 
+    /// Step back in style history (returns the style now current, or null)
+    #[wasm_bindgen]
+    pub fn undo_style(&self) -> String {
+        if let Ok(mut history) = self.style_history.lock() {
+            if let Some(style) = history.undo_style() {
+                self.mark_dirty();
+                return serde_json::to_string(&style).unwrap_or_else(|_| "null".to_string());
+            }
+        }
+        "null".to_string()
+    }
+
+    /// Step forward in style history (returns the style now current, or null)
+    #[wasm_bindgen]
+    pub fn redo_style(&self) -> String {
+        if let Ok(mut history) = self.style_history.lock() {
+            if let Some(style) = history.redo_style() {
+                self.mark_dirty();
+                return serde_json::to_string(&style).unwrap_or_else(|_| "null".to_string());
+            }
+        }
+        "null".to_string()
+    }
+
     /// Clear style history
     #[wasm_bindgen]
     pub fn clear_style_history(&self) {
         if let Ok(mut history) = self.style_history.lock() {
             history.clear();
         }
+        self.mark_dirty();
     }
 
     /// Export style history as compressed base64 string
@@ -556,11 +1376,11 @@ impl HTMLBuilderEngine {
     /// Import style history from compressed base64 string
     #[wasm_bindgen]
     pub fn import_style_history(&self, data: &str) -> bool {
-        if let Ok(mut history) = self.style_history.lock() {
-            history.import_from_base64(data).is_ok()
-        } else {
-            false
-        }
+        let imported = match self.style_history.lock() {
+            Ok(mut history) => history.import_from_base64(data).is_ok(),
+            Err(_) => false,
+        };
+        self.mark_dirty_if(imported)
     }
 
     /// Get style history count
@@ -572,4 +1392,506 @@ impl HTMLBuilderEngine {
             0
         }
     }
-}
\ No newline at end of file
+}
+
+impl HTMLBuilderEngine {
+    /// Append to the diagnostic operation log and the change stream (each a no-op unless enabled)
+    fn record_op(&self, op: &str, element_id: &str) {
+        self.mark_dirty();
+        if let Ok(mut op_log) = self.op_log.lock() {
+            op_log.record(op, element_id);
+        }
+        if let Ok(mut change_stream) = self.change_stream.lock() {
+            if change_stream.is_enabled() {
+                change_stream.push(op, element_id, self.changed_fields(op, element_id));
+            }
+        }
+    }
+
+    /// Remove a paper with its contents (deleted, or moved to the previous page), keeping the spatial
+    /// index and op records in step; returns (removed ids, moved ids), or `None` when the paper is missing
+    fn remove_paper_and_contents(&self, paper_id: &str, reflow_to_previous: bool) -> Option<(Vec<String>, Vec<String>)> {
+        let (removed, moved) = self.paper_manager.remove_paper_with_contents(paper_id, reflow_to_previous, &self.element_manager)?;
+        self.record_op("paper", paper_id);
+        for element_id in &removed {
+            self.spatial_index_manager.remove_element(element_id);
+            self.record_op("delete", element_id);
+        }
+        for element_id in &moved {
+            self.sync_spatial_index(element_id);
+            self.record_op("move", element_id);
+        }
+        Some((removed, moved))
+    }
+
+    /// `record_op` when a mutation reports a change, passing the result through
+    fn record_if(&self, changed: bool, op: &str, element_id: &str) -> bool {
+        if changed {
+            self.record_op(op, element_id);
+        }
+        changed
+    }
+
+    /// Record the "paper" op for a paper returned as JSON by the paper manager
+    fn record_paper_created(&self, paper_json: &str) {
+        if let Ok(paper) = serde_json::from_str::<Paper>(paper_json) {
+            self.record_op("paper", &paper.id);
+        }
+    }
+
+    /// Mark the document dirty when a mutation reports a change, passing the result through
+    fn mark_dirty_if(&self, changed: bool) -> bool {
+        if changed {
+            self.mark_dirty();
+        }
+        changed
+    }
+
+    /// Fields an op changed, with their current values, in the element's serialized (camelCase) form.
+    /// The "paper" op carries a paper ID instead and returns the whole paper (null once removed);
+    /// "reset" has no ID and returns the whole document
+    fn changed_fields(&self, op: &str, element_id: &str) -> serde_json::Value {
+        if op == "reset" {
+            return serde_json::to_value(self.document()).unwrap_or_default();
+        }
+        if op == "paper" {
+            return self.paper_manager.all().into_iter()
+                .find(|paper| paper.id == element_id)
+                .and_then(|paper| serde_json::to_value(paper).ok())
+                .unwrap_or_default();
+        }
+        let Some(element) = self.element_manager.find(element_id) else {
+            return serde_json::Value::Null;
+        };
+        let serialized = serde_json::to_value(&element).unwrap_or_default();
+        let keys: &[&str] = match op {
+            "create" => return serialized,
+            "move" => &["x", "y"],
+            "resize" => &["x", "y", "width", "height"],
+            "style" => &["style"],
+            "content" => &["content"],
+            "reorder" => &["zIndex"],
+            "table" => &["width", "height", "tableData"],
+            "anchors" => &["anchorRight", "anchorBottom", "anchorCenter"],
+            "tab_index" => &["tabIndex"],
+            "page_breaks" => &["pageBreakBefore", "pageBreakAfter", "pageBreakInsideAvoid"],
+            "link" => &["linkedFrom", "content"],
+            "lock_aspect" => &["lockAspect"],
+            "slot" => &["slotKey"],
+            _ => &[],
+        };
+        // Cleared optional fields are sent as null
+        keys.iter()
+            .map(|key| (key.to_string(), serialized.get(*key).cloned().unwrap_or_default()))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Refresh an element's entry in the spatial index after it moved or resized
+    fn sync_spatial_index(&self, element_id: &str) {
+        let element_json = self.element_manager.get_element(element_id);
+        if let Ok(element) = serde_json::from_str::<Element>(&element_json) {
+            self.spatial_index_manager.update_element(element_id, &element);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linked_element_mirrors_source_content() {
+        let engine = HTMLBuilderEngine::new();
+        let name = engine.add_element("text", 0.0, 0.0);
+        let summary = engine.add_element("text", 0.0, 100.0);
+        let footer = engine.add_element("text", 0.0, 200.0);
+        assert!(engine.link_elements(&name.id, &summary.id));
+        assert!(engine.link_elements(&summary.id, &footer.id));
+        // Cycles are refused
+        assert!(!engine.link_elements(&footer.id, &name.id));
+        assert!(!engine.link_elements(&name.id, &name.id));
+
+        assert!(engine.update_element_content(&name.id, "Jane Doe"));
+        assert_eq!(engine.element(&summary.id).unwrap().content, "Jane Doe");
+        assert_eq!(engine.element(&footer.id).unwrap().content, "Jane Doe");
+
+        assert!(engine.unlink_element(&summary.id));
+        assert!(engine.update_element_content(&name.id, "John Roe"));
+        assert_eq!(engine.element(&summary.id).unwrap().content, "Jane Doe");
+    }
+
+    #[test]
+    fn test_paste_elements_into_another_engine() {
+        let source = HTMLBuilderEngine::new();
+        let table = source.add_element("table", 0.0, 0.0);
+        let checkbox = source.add_element("checkbox", 10.0, 10.0);
+        assert!(source.set_table_cell_element(&table.id, 1, 0, &checkbox.id));
+        let ids = serde_json::to_string(&[&table.id, &checkbox.id]).unwrap();
+        let payload = source.copy_elements(&ids);
+
+        let target = HTMLBuilderEngine::new();
+        let existing = target.add_element("text", 0.0, 0.0);
+        let pasted: Vec<Element> = serde_json::from_str(&target.paste_elements_from(&payload, 20.0, 30.0)).unwrap();
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(target.elements().len(), 3);
+        for (copy, original) in pasted.iter().zip([&table, &checkbox]) {
+            assert_ne!(copy.id, original.id);
+            assert_ne!(copy.id, existing.id);
+            assert_eq!((copy.x, copy.y), (original.x + 20.0, original.y + 30.0));
+            assert_eq!(target.element(&copy.id).unwrap().element_type, original.element_type);
+        }
+        // The cell points at the pasted checkbox, not the source instance's
+        let cell_element = target.table(&pasted[0].id).unwrap().rows[1].cells[0].element_id.clone();
+        assert_eq!(cell_element, Some(pasted[1].id.clone()));
+        assert!(target.is_dirty());
+
+        // Pasting twice makes a second set of copies
+        let again: Vec<Element> = serde_json::from_str(&target.paste_elements_from(&payload, 0.0, 0.0)).unwrap();
+        assert!(again.iter().all(|copy| pasted.iter().all(|first| first.id != copy.id)));
+        assert_eq!(target.paste_elements_from("{}", 0.0, 0.0), "[]");
+        assert_eq!(source.copy_elements("not json"), "null");
+    }
+
+    #[test]
+    fn test_batch_update_style_records_one_history_entry() {
+        let engine = HTMLBuilderEngine::new();
+        let ids: Vec<String> = (0..3)
+            .map(|i| engine.add_element("text", i as f64 * 100.0, 0.0).id)
+            .collect();
+        let untouched = engine.add_element("text", 400.0, 0.0);
+
+        let updated = engine.batch_update_style(&serde_json::to_string(&ids).unwrap(), r#"{"fontSize":24}"#);
+        let updated: Vec<String> = serde_json::from_str(&updated).unwrap();
+        assert_eq!(updated, ids);
+
+        for id in &ids {
+            assert_eq!(engine.element(id).unwrap().style.font_size, 24.0);
+        }
+        assert_ne!(engine.element(&untouched.id).unwrap().style.font_size, 24.0);
+        assert_eq!(engine.get_style_history_count(), 1);
+    }
+
+    #[test]
+    fn test_create_table_with_dimensions() {
+        let engine = HTMLBuilderEngine::new();
+        let element: Element = serde_json::from_str(&engine.create_table(5, 4, 10.0, 20.0)).unwrap();
+        assert_eq!((element.x, element.y), (10.0, 20.0));
+
+        let table_data: TableData = serde_json::from_str(&engine.get_table_data(&element.id)).unwrap();
+        assert_eq!(table_data.rows.len(), 5);
+        assert_eq!(table_data.columns, 4);
+        assert!(table_data.rows.iter().all(|row| row.cells.len() == 4));
+
+        assert_eq!(engine.create_table(0, 4, 0.0, 0.0), "null");
+        assert_eq!(engine.create_table(5, 0, 0.0, 0.0), "null");
+        assert_eq!(engine.get_element_count(), 1);
+    }
+
+    #[test]
+    fn test_op_log_records_mutations() {
+        let engine = HTMLBuilderEngine::new();
+        engine.create_element("text", 0.0, 0.0);
+        assert_eq!(engine.get_op_log(), "[]");
+
+        engine.enable_op_log(true);
+        let element: Element = serde_json::from_str(&engine.create_element("text", 0.0, 0.0)).unwrap();
+        assert!(engine.update_element_position(&element.id, 50.0, 60.0));
+        assert!(!engine.update_element_size("missing", 10.0, 10.0));
+        assert!(engine.delete_element(&element.id));
+
+        let log: Vec<serde_json::Value> = serde_json::from_str(&engine.get_op_log()).unwrap();
+        let ops: Vec<&str> = log.iter().map(|entry| entry["op"].as_str().unwrap()).collect();
+        assert_eq!(ops, vec!["create", "move", "delete"]);
+        assert!(log.iter().all(|entry| entry["elementId"] == element.id.as_str()));
+        assert!(log[0]["timestamp"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_drain_change_stream() {
+        let engine = HTMLBuilderEngine::new();
+        let element = engine.add_element("text", 0.0, 0.0);
+        engine.enable_change_stream(true);
+
+        assert!(engine.update_element_position(&element.id, 50.0, 60.0));
+        assert!(engine.update_element_content(&element.id, "Hello"));
+
+        let changes: Vec<serde_json::Value> = serde_json::from_str(&engine.drain_change_stream()).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0]["op"], "move");
+        assert_eq!(changes[0]["elementId"], element.id.as_str());
+        assert_eq!(changes[0]["fields"], serde_json::json!({ "x": 50.0, "y": 60.0 }));
+        assert_eq!(changes[1]["op"], "content");
+        assert_eq!(changes[1]["fields"], serde_json::json!({ "content": "Hello" }));
+
+        assert_eq!(engine.drain_change_stream(), "[]");
+    }
+
+    #[test]
+    fn test_change_stream_covers_table_paper_and_property_edits() {
+        let engine = HTMLBuilderEngine::new();
+        let table = engine.add_element("table", 0.0, 0.0);
+        let text = engine.add_element("text", 0.0, 300.0);
+        engine.enable_change_stream(true);
+        let drain = || -> Vec<serde_json::Value> { serde_json::from_str(&engine.drain_change_stream()).unwrap() };
+
+        assert!(engine.update_table_cell(&table.id, 1, 1, "42"));
+        let changes = drain();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0]["op"], "table");
+        assert_eq!(changes[0]["elementId"], table.id.as_str());
+        assert_eq!(changes[0]["fields"]["tableData"]["rows"][1]["cells"][1]["content"], "42");
+        assert!(!engine.update_table_cell("missing", 1, 1, "42"));
+        assert!(drain().is_empty());
+
+        assert!(engine.set_element_anchors(&text.id, true, false, false));
+        assert!(engine.set_element_tab_index(&text.id, Some(3)));
+        assert!(engine.set_element_tab_index(&text.id, None));
+        assert!(engine.set_element_page_breaks(&text.id, true, false, false));
+        assert!(engine.link_elements(&table.id, &text.id));
+        let ops: Vec<(String, serde_json::Value)> = drain()
+            .into_iter()
+            .map(|change| (change["op"].as_str().unwrap().to_string(), change["fields"].clone()))
+            .collect();
+        assert_eq!(ops[0].0, "anchors");
+        assert_eq!(ops[0].1["anchorRight"], true);
+        assert_eq!(ops[1], ("tab_index".to_string(), serde_json::json!({ "tabIndex": 3 })));
+        // Clearing an optional field sends null
+        assert_eq!(ops[2], ("tab_index".to_string(), serde_json::json!({ "tabIndex": null })));
+        assert_eq!(ops[3].0, "page_breaks");
+        assert_eq!(ops[3].1["pageBreakBefore"], true);
+        assert_eq!(ops[4].0, "link");
+        assert_eq!(ops[4].1["linkedFrom"], table.id.as_str());
+
+        let paper: Paper = serde_json::from_str(&engine.create_paper("page", "A4", "Portrait", 0.0, 0.0)).unwrap();
+        assert!(engine.set_paper_title(&paper.id, "Cover"));
+        assert!(engine.remove_paper(&paper.id));
+        let changes = drain();
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|change| change["op"] == "paper" && change["elementId"] == paper.id.as_str()));
+        assert_eq!(changes[1]["fields"]["title"], "Cover");
+        assert_eq!(changes[2]["fields"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_remove_paper_with_contents() {
+        let engine = HTMLBuilderEngine::new();
+        engine.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        engine.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+        let cover = engine.add_element("text", 40.0, 40.0);
+        let mut contents: Vec<Element> = [1300.0, 1500.0, 1700.0].iter().map(|&y| engine.add_element("text", 40.0, y)).collect();
+        // Straddling the page edge: exported with the page holding its center
+        let paper_height = engine.papers()[0].height;
+        let straddling_down = engine.add_element("text", 40.0, 1200.0 - 10.0);
+        let straddling_up = engine.add_element("text", 40.0, paper_height - 90.0);
+        contents.push(straddling_down.clone());
+        engine.rebuild_spatial_index(100.0);
+
+        assert_eq!(engine.remove_paper_with_contents("page-1"), 4);
+        assert_eq!(engine.get_paper_count(), 1);
+        for element in &contents {
+            assert!(engine.element(&element.id).is_none());
+        }
+        assert!(engine.elements_at_point(50.0, 1310.0).is_empty());
+        assert!(engine.element(&cover.id).is_some());
+        assert!(engine.element(&straddling_up.id).is_some());
+        assert_eq!(engine.remove_paper_with_contents("page-1"), 0);
+
+        // Reflow keeps the element's position relative to the page
+        engine.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+        let orphan = engine.add_element("text", 40.0, 1300.0);
+        assert_eq!(engine.remove_paper_with_reflow("page-1"), serde_json::json!([orphan.id.clone()]).to_string());
+        assert_eq!(engine.element(&orphan.id).map(|element| (element.x, element.y)), Some((40.0, 100.0)));
+    }
+
+    #[test]
+    fn test_dirty_flag_and_autosave_throttle() {
+        let engine = HTMLBuilderEngine::new();
+        assert!(!engine.is_dirty());
+        assert!(!engine.should_autosave());
+
+        engine.mark_dirty();
+        assert!(engine.is_dirty());
+        assert!(engine.set_autosave_interval(50.0));
+        assert!(engine.should_autosave());
+        // Within the interval
+        assert!(!engine.should_autosave());
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        assert!(engine.should_autosave());
+
+        engine.mark_saved();
+        assert!(!engine.is_dirty());
+        let table = engine.add_element("table", 0.0, 0.0);
+        engine.mark_saved();
+        assert!(engine.update_table_cell(&table.id, 1, 1, "edited"));
+        assert!(engine.is_dirty());
+        assert!(!engine.set_autosave_interval(f64::NAN));
+    }
+
+    #[test]
+    fn test_mutating_wrappers_mark_dirty() {
+        let engine = HTMLBuilderEngine::new();
+        engine.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        engine.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+        let button = engine.add_element("button", 10.0, 10.0);
+        let field = engine.add_element("form_field", 10.0, 100.0);
+        let document = engine.export_document();
+        let snapshot = engine.snapshot();
+
+        type Mutation<'a> = (&'static str, Box<dyn Fn() -> bool + 'a>);
+        let mutations: Vec<Mutation> = vec![
+            ("move_element_to_page", Box::new(|| engine.move_element_to_page(&button.id, "page-1", true))),
+            ("batch_update_positions", Box::new(|| {
+                let updates = serde_json::json!([{ "element_id": button.id, "x": 30.0, "y": 40.0 }, { "element_id": "missing", "x": 0.0, "y": 0.0 }]);
+                engine.batch_update_positions(&updates.to_string()) == "[true,false]"
+            })),
+            ("batch_update_form_field", Box::new(|| engine.batch_update_form_field(&field.id, r#"{"value":"Jane"}"#))),
+            ("set_element_tab_index", Box::new(|| engine.set_element_tab_index(&button.id, Some(2)))),
+            ("set_element_anchors", Box::new(|| engine.set_element_anchors(&button.id, true, false, false))),
+            ("set_element_lock_aspect", Box::new(|| engine.set_element_lock_aspect(&button.id, true))),
+            ("set_element_slot", Box::new(|| engine.set_element_slot(&button.id, "name"))),
+            ("update_drag", Box::new(|| {
+                engine.start_drag(&button.id, 0.0, 0.0);
+                let moved = engine.update_drag(50.0, 50.0, 1.0, 0.0, 0.0).contains("\"is_valid\":true");
+                engine.end_drag() && moved
+            })),
+            ("undo_style", Box::new(|| {
+                let mut style = button.style.clone();
+                engine.save_style_to_history(&serde_json::to_string(&style).unwrap());
+                style.font_size += 4.0;
+                engine.save_style_to_history(&serde_json::to_string(&style).unwrap());
+                engine.mark_saved();
+                engine.undo_style() != "null"
+            })),
+            ("rollback_transaction", Box::new(|| {
+                engine.begin_transaction();
+                engine.mark_saved();
+                engine.rollback_transaction()
+            })),
+        ];
+        for (name, mutate) in mutations {
+            engine.mark_saved();
+            assert!(mutate(), "{} did not apply", name);
+            assert!(engine.is_dirty(), "{} did not mark the document dirty", name);
+        }
+
+        // A freshly loaded document or snapshot has no unsaved changes
+        engine.mark_dirty();
+        assert!(engine.import_document(&document));
+        assert!(!engine.is_dirty());
+        engine.mark_dirty();
+        assert!(engine.restore(&snapshot));
+        assert!(!engine.is_dirty());
+    }
+
+    #[test]
+    fn test_geometry_edits_detach_relative_layout() {
+        let engine = HTMLBuilderEngine::new();
+        engine.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        engine.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+        let button = engine.add_element("button", 10.0, 10.0);
+        let other = engine.add_element("button", 20.0, 20.0);
+        let line = engine.add_element("line", 300.0, 300.0);
+        let ids = serde_json::to_string(&[&button.id, &other.id]).unwrap();
+
+        type Edit<'a> = (&'static str, &'a str, Box<dyn Fn() -> bool + 'a>);
+        let edits: Vec<Edit> = vec![
+            ("batch_update_positions", &button.id, Box::new(|| {
+                let updates = serde_json::json!([{ "element_id": button.id, "x": 30.0, "y": 40.0 }]);
+                engine.batch_update_positions(&updates.to_string()) == "[true]"
+            })),
+            ("update_drag", &button.id, Box::new(|| {
+                engine.start_drag(&button.id, 0.0, 0.0);
+                let result: serde_json::Value = serde_json::from_str(&engine.update_drag(50.0, 60.0, 1.0, 0.0, 0.0)).unwrap();
+                engine.end_drag();
+                result["is_valid"] == true
+            })),
+            ("arrange_grid", &button.id, Box::new(|| engine.arrange_grid(&ids, 2, 10.0, 0.0, 0.0) != "[]")),
+            ("resolve_overlaps", &other.id, Box::new(|| {
+                // Overlap without moving the element under test
+                let placed = engine.element(&other.id).unwrap();
+                engine.update_element_position(&button.id, placed.x, placed.y);
+                engine.resolve_overlaps(&ids, "push") != "[]"
+            })),
+            ("move_element_to_page", &button.id, Box::new(|| engine.move_element_to_page(&button.id, "page-1", true))),
+            ("snap_line_endpoint", &line.id, Box::new(|| engine.snap_line_endpoint(&line.id, "end", 400.0, 450.0, 0.0) != "null")),
+        ];
+        for (name, element_id, edit) in edits {
+            assert!(engine.set_element_relative(element_id, 10.0, 10.0, 10.0, 10.0), "{}", name);
+            assert!(edit(), "{}", name);
+            assert!(engine.element(element_id).unwrap().relative_layout.is_none(), "{} kept the relative layout", name);
+        }
+    }
+
+    #[test]
+    fn test_focus_element_centers_it_in_viewport() {
+        let engine = HTMLBuilderEngine::new();
+        let element = engine.add_element("rectangle", 1000.0, 2000.0);
+        assert!(engine.update_element_size(&element.id, 400.0, 300.0));
+
+        let transform: serde_json::Value = serde_json::from_str(&engine.focus_element(&element.id, 800.0, 600.0, 20.0)).unwrap();
+        // The height is the tighter fit: (600 - 2 * 20) / 300
+        let zoom = transform["zoom"].as_f64().unwrap();
+        assert!((zoom - 560.0 / 300.0).abs() < 1e-9);
+
+        let transform_manager = &engine.transform_manager;
+        let (center_x, center_y) = transform_manager.apply_inverse_transform(1200.0, 2150.0);
+        assert!((center_x - 400.0).abs() < 1e-9 && (center_y - 300.0).abs() < 1e-9);
+        let (top, bottom) = (transform_manager.apply_inverse_transform(1000.0, 2000.0).1, transform_manager.apply_inverse_transform(1400.0, 2300.0).1);
+        assert!((top - 20.0).abs() < 1e-9 && (bottom - 580.0).abs() < 1e-9);
+
+        assert_eq!(engine.focus_element("missing", 800.0, 600.0, 20.0), "null");
+    }
+
+    #[test]
+    fn test_rollback_transaction_restores_elements() {
+        let engine = HTMLBuilderEngine::new();
+        engine.create_a4_paper(0.0, 0.0);
+        let first = engine.add_element("text", 10.0, 10.0);
+        let second = engine.add_element("text", 20.0, 20.0);
+
+        assert!(engine.begin_transaction());
+        assert!(!engine.begin_transaction());
+        assert!(engine.delete_element(&first.id));
+        assert!(engine.delete_element(&second.id));
+        assert!(engine.remove_paper("a4-paper-0"));
+        assert_eq!(engine.get_element_count(), 0);
+
+        assert!(engine.rollback_transaction());
+        assert!(!engine.is_in_transaction());
+        let ids: Vec<String> = engine.elements().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![first.id.clone(), second.id.clone()]);
+        assert_eq!(engine.get_paper_count(), 1);
+
+        assert!(engine.begin_transaction());
+        assert!(engine.delete_element(&first.id));
+        assert!(engine.commit_transaction());
+        assert!(!engine.rollback_transaction());
+        assert_eq!(engine.get_element_count(), 1);
+    }
+
+    #[test]
+    fn test_rollback_rebuilds_spatial_index_and_streams_reset() {
+        let engine = HTMLBuilderEngine::new();
+        let text = engine.add_element("text", 10.0, 10.0);
+        engine.rebuild_spatial_index(100.0);
+        engine.enable_op_log(true);
+        engine.enable_change_stream(true);
+
+        assert!(engine.begin_transaction());
+        assert!(engine.update_element_position(&text.id, 500.0, 500.0));
+        assert!(engine.rollback_transaction());
+
+        // Region queries see the rolled-back geometry
+        assert_eq!(engine.elements_in_region(0.0, 0.0, 100.0, 100.0).len(), 1);
+        assert!(engine.elements_in_region(450.0, 450.0, 200.0, 200.0).is_empty());
+
+        let changes: Vec<serde_json::Value> = serde_json::from_str(&engine.drain_change_stream()).unwrap();
+        let reset = changes.last().unwrap();
+        assert_eq!(reset["op"], "reset");
+        let document: york_core::Document = serde_json::from_value(reset["fields"].clone()).unwrap();
+        assert_eq!((document.elements[0].x, document.elements[0].y), (10.0, 10.0));
+        let op_log: Vec<serde_json::Value> = serde_json::from_str(&engine.get_op_log()).unwrap();
+        assert_eq!(op_log.last().unwrap()["op"], "reset");
+    }
+}
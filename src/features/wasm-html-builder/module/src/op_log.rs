@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use crate::utils;
+
+/// Diagnostic record of one mutating engine call
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpLogEntry {
+    pub op: String, // "create", "delete", "move", "resize", "style", "content", "reorder", "table", "paper", "reset", ...
+    pub element_id: String,
+    pub timestamp: f64, // Unix timestamp in milliseconds
+}
+
+/// Bounded operation log for debugging (not an undo stack)
+#[derive(Debug)]
+pub struct OpLog {
+    enabled: bool,
+    entries: VecDeque<OpLogEntry>,
+    max_entries: usize,
+}
+
+impl OpLog {
+    pub fn new(max_entries: usize) -> Self {
+        OpLog {
+            enabled: false,
+            entries: VecDeque::with_capacity(max_entries),
+            max_entries,
+        }
+    }
+
+    /// Turn recording on or off (entries already recorded are kept)
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Record an operation if logging is enabled, dropping the oldest entry when full
+    pub fn record(&mut self, op: &str, element_id: &str) {
+        if !self.enabled {
+            return;
+        }
+        if self.entries.len() >= self.max_entries {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(OpLogEntry {
+            op: op.to_string(),
+            element_id: element_id.to_string(),
+            timestamp: utils::now_ms(),
+        });
+    }
+
+    pub fn entries(&self) -> Vec<OpLogEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for OpLog {
+    fn default() -> Self {
+        Self::new(500)
+    }
+}
+
+/// Compact record of one mutation for network sync: only the fields the op changed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeRecord {
+    pub op: String,
+    pub element_id: String, // Paper ID for the "paper" op, empty for "reset"
+    pub fields: serde_json::Value, // Changed fields with their new values (camelCase), null on delete
+    pub timestamp: f64,
+}
+
+/// Pending changes waiting to be drained by the caller, oldest dropped when full
+#[derive(Debug)]
+pub struct ChangeStream {
+    enabled: bool,
+    pending: VecDeque<ChangeRecord>,
+    max_pending: usize,
+}
+
+impl ChangeStream {
+    pub fn new(max_pending: usize) -> Self {
+        ChangeStream {
+            enabled: false,
+            pending: VecDeque::new(),
+            max_pending,
+        }
+    }
+
+    /// Turn the stream on or off (disabling drops pending changes)
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.pending.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn push(&mut self, op: &str, element_id: &str, fields: serde_json::Value) {
+        if !self.enabled {
+            return;
+        }
+        if self.pending.len() >= self.max_pending {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(ChangeRecord {
+            op: op.to_string(),
+            element_id: element_id.to_string(),
+            fields,
+            timestamp: utils::now_ms(),
+        });
+    }
+
+    /// Take all pending changes, oldest first
+    pub fn drain(&mut self) -> Vec<ChangeRecord> {
+        self.pending.drain(..).collect()
+    }
+}
+
+impl Default for ChangeStream {
+    fn default() -> Self {
+        Self::new(5000)
+    }
+}
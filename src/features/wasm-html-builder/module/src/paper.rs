@@ -1,21 +1,74 @@
 use serde_json;
 use std::sync::{Mutex, MutexGuard, Arc};
 use crate::types::*;
+use crate::utils;
+use crate::element::ElementManager;
+use crate::export::{is_element_on_page, PaperAssignMode};
+
+/// Vertical spacing between stacked papers (matches the editor's add-paper layout)
+const PAPER_SPACING: f64 = 50.0;
 
 /// Paper management module
 pub struct PaperManager {
     papers: Arc<Mutex<Vec<A4Paper>>>,
+    dpi: Mutex<f64>,
+    printer_margin_mm: Arc<Mutex<f64>>, // Shared with ExportManager for the printable-area guide
 }
 
 impl PaperManager {
     pub fn new() -> Self {
+        Self::new_with_data(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    pub fn new_with_data(papers: Arc<Mutex<Vec<A4Paper>>>) -> Self {
         Self {
-            papers: Arc::new(Mutex::new(Vec::new())),
+            papers,
+            dpi: Mutex::new(DEFAULT_DPI),
+            printer_margin_mm: Arc::new(Mutex::new(DEFAULT_PRINTER_MARGIN_MM)),
         }
     }
 
-    pub fn new_with_data(papers: Arc<Mutex<Vec<A4Paper>>>) -> Self {
-        Self { papers }
+    /// ตั้งค่า DPI สำหรับ papers ที่สร้างใหม่ (papers เดิมคงขนาดเดิม)
+    pub fn set_dpi(&self, dpi: f64) -> bool {
+        if !dpi.is_finite() || dpi <= 0.0 {
+            return false;
+        }
+        *utils::lock(&self.dpi) = dpi;
+        true
+    }
+
+    /// ได้ค่า DPI ปัจจุบัน
+    pub fn get_dpi(&self) -> f64 {
+        *utils::lock(&self.dpi)
+    }
+
+    /// ตั้งค่าขอบที่เครื่องพิมพ์พิมพ์ไม่ถึง (มิลลิเมตร ค่าเริ่มต้น 5)
+    pub fn set_printer_margin(&self, margin_mm: f64) -> bool {
+        if !margin_mm.is_finite() || margin_mm < 0.0 {
+            return false;
+        }
+        *utils::lock(&self.printer_margin_mm) = margin_mm;
+        true
+    }
+
+    /// ได้ค่าขอบเครื่องพิมพ์ปัจจุบัน (มิลลิเมตร)
+    pub fn get_printer_margin(&self) -> f64 {
+        *utils::lock(&self.printer_margin_mm)
+    }
+
+    /// ค่าขอบเครื่องพิมพ์ที่แชร์ให้ manager อื่นอ่าน (เช่น ExportManager)
+    pub fn printer_margin_handle(&self) -> Arc<Mutex<f64>> {
+        Arc::clone(&self.printer_margin_mm)
+    }
+
+    /// พื้นที่พิมพ์ได้ของ paper (paper หักขอบเครื่องพิมพ์) เป็น JSON `{x, y, width, height}` หรือ "null" ถ้าไม่พบ paper
+    pub fn get_printable_area(&self, paper_id: &str) -> String {
+        let margin_mm = *utils::lock(&self.printer_margin_mm);
+        let papers = utils::lock(&self.papers);
+        match papers.iter().find(|paper| paper.id == paper_id) {
+            Some(paper) => serde_json::to_string(&paper.printable_area(margin_mm)).unwrap_or_else(|_| "null".to_string()),
+            None => "null".to_string(),
+        }
     }
 
     /// สร้าง A4 paper ใหม่ (backward compatibility)
@@ -25,7 +78,13 @@ impl PaperManager {
 
     /// สร้าง paper ใหม่ด้วยขนาดและทิศทางที่กำหนด
     pub fn create_paper(&self, id: &str, size: &str, orientation: &str, x: f64, y: f64) -> String {
-        let mut papers = self.papers.lock().unwrap();
+        let paper = self.create(id, size, orientation, x, y);
+        serde_json::to_string(&paper).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// สร้าง paper ใหม่และคืนค่าเป็น Paper
+    pub fn create(&self, id: &str, size: &str, orientation: &str, x: f64, y: f64) -> A4Paper {
+        let mut papers = utils::lock(&self.papers);
         
         // Parse size and orientation
         let paper_size = match size.to_uppercase().as_str() {
@@ -40,32 +99,77 @@ impl PaperManager {
             _ => PaperOrientation::Portrait
         };
         
-        let paper_id = format!("{}-{}", id, papers.len());
-        let paper = Paper::new(paper_id.clone(), paper_size, paper_orientation, x, y);
+        let paper_id = unique_paper_id(&papers, id);
+        let paper = Paper::new_with_dpi(paper_id, paper_size, paper_orientation, x, y, self.get_dpi());
         
         papers.push(paper.clone());
         
-        serde_json::to_string(&paper).unwrap_or_else(|_| "{}".to_string())
+        paper
     }
 
     /// ได้ papers ทั้งหมด
     pub fn get_a4_papers(&self) -> String {
-        let papers = self.papers.lock().unwrap();
+        let papers = utils::lock(&self.papers);
         serde_json::to_string(&*papers).unwrap_or_else(|_| "[]".to_string())
     }
 
+    /// ได้สำเนาของ papers ทั้งหมด
+    pub fn all(&self) -> Vec<A4Paper> {
+        utils::lock(&self.papers).clone()
+    }
+
     /// ลบ paper ตาม ID
     pub fn remove_paper(&self, paper_id: &str) -> bool {
-        let mut papers = self.papers.lock().unwrap();
+        let mut papers = utils::lock(&self.papers);
         let initial_len = papers.len();
         papers.retain(|paper| paper.id != paper_id);
         
         papers.len() < initial_len
     }
 
+    /// ลบ paper พร้อม elements ที่ export ลงหน้านั้น (กฎเดียวกับ export แบบ CenterPaper: จุดกึ่งกลางอยู่ใน paper
+    /// หรือซ้อนทับ paper นี้เป็นหน้าแรก) คืน (ids ที่ถูกลบ, ids ที่ถูกย้าย) หรือ None ถ้าไม่พบ paper
+    /// reflow_to_previous = ย้าย elements ไปหน้าก่อนหน้าโดยคงตำแหน่งสัมพันธ์ แทนการลบ (หน้าแรกไม่มีหน้าก่อนหน้า จึงลบเสมอ)
+    pub fn remove_paper_with_contents(&self, paper_id: &str, reflow_to_previous: bool, element_manager: &ElementManager) -> Option<(Vec<String>, Vec<String>)> {
+        let (paper, previous, contents) = {
+            let elements = element_manager.get_elements_ref();
+            let mut papers = utils::lock(&self.papers);
+            let index = papers.iter().position(|p| p.id == paper_id)?;
+            let mut on_page: Vec<&Element> = elements
+                .iter()
+                .filter(|element| is_element_on_page(element, index, &papers, PaperAssignMode::default()))
+                .collect();
+            on_page.sort_by(|a, b| a.stable_cmp(b));
+            let contents: Vec<String> = on_page.into_iter().map(|element| element.id.clone()).collect();
+            let previous = index.checked_sub(1).map(|previous| papers[previous].clone());
+            (papers.remove(index), previous, contents)
+        };
+
+        match previous.filter(|_| reflow_to_previous) {
+            Some(previous) => {
+                let mut elements = element_manager.get_elements_ref();
+                for element in elements.iter_mut().filter(|element| contents.contains(&element.id)) {
+                    element.x += previous.x - paper.x;
+                    element.y += previous.y - paper.y;
+                    element.relative_layout = None;
+                }
+                Some((Vec::new(), contents))
+            }
+            None => {
+                for element_id in &contents {
+                    element_manager.delete_element(element_id);
+                }
+                Some((contents, Vec::new()))
+            }
+        }
+    }
+
     /// อัปเดตตำแหน่ง paper
     pub fn update_paper_position(&self, paper_id: &str, x: f64, y: f64) -> bool {
-        let mut papers = self.papers.lock().unwrap();
+        if !utils::all_finite(&[x, y]) {
+            return false;
+        }
+        let mut papers = utils::lock(&self.papers);
         
         if let Some(paper) = papers.iter_mut().find(|p| p.id == paper_id) {
             paper.update_position(x, y);
@@ -75,15 +179,27 @@ impl PaperManager {
         }
     }
 
+    /// ตั้งชื่อ paper (ค่าว่าง = ลบชื่อ)
+    pub fn set_paper_title(&self, paper_id: &str, title: &str) -> bool {
+        let mut papers = utils::lock(&self.papers);
+
+        if let Some(paper) = papers.iter_mut().find(|p| p.id == paper_id) {
+            paper.title = if title.is_empty() { None } else { Some(title.to_string()) };
+            true
+        } else {
+            false
+        }
+    }
+
     /// นับจำนวน papers
     pub fn get_paper_count(&self) -> usize {
-        let papers = self.papers.lock().unwrap();
+        let papers = utils::lock(&self.papers);
         papers.len()
     }
 
     /// ค้นหา paper ตาม ID
     pub fn get_paper_by_id(&self, paper_id: &str) -> String {
-        let papers = self.papers.lock().unwrap();
+        let papers = utils::lock(&self.papers);
         
         if let Some(paper) = papers.iter().find(|p| p.id == paper_id) {
             serde_json::to_string(paper).unwrap_or_else(|_| "{}".to_string())
@@ -114,14 +230,485 @@ impl PaperManager {
         element.y < paper_bottom && element_bottom > paper.y
     }
 
+    /// หา paper ที่ element อยู่ และคืนตำแหน่งสัมพันธ์กับมุมซ้ายบนของ paper
+    pub fn get_element_paper_coords(&self, element_id: &str, element_manager: &ElementManager) -> String {
+        if let Some((paper_id, rel_x, rel_y)) = self.find_element_paper_coords(element_id, element_manager) {
+            serde_json::json!({
+                "paperId": paper_id,
+                "relX": rel_x,
+                "relY": rel_y
+            }).to_string()
+        } else {
+            "null".to_string()
+        }
+    }
+
+    /// Returns (paper_id, rel_x, rel_y) for the first paper the element overlaps
+    fn find_element_paper_coords(&self, element_id: &str, element_manager: &ElementManager) -> Option<(String, f64, f64)> {
+        let element_json = element_manager.get_element(element_id);
+        let element = serde_json::from_str::<Element>(&element_json).ok()?;
+
+        let papers = utils::lock(&self.papers);
+        papers.iter()
+            .find(|p| self.is_element_in_paper(&element, p))
+            .map(|paper| (paper.id.clone(), element.x - paper.x, element.y - paper.y))
+    }
+
+    /// วาง element ที่ offset (rel_x, rel_y) ภายใน paper ที่กำหนด
+    pub fn set_element_paper_coords(&self, element_id: &str, paper_id: &str, rel_x: f64, rel_y: f64, element_manager: &ElementManager) -> bool {
+        let origin = {
+            let papers = utils::lock(&self.papers);
+            papers.iter().find(|p| p.id == paper_id).map(|p| (p.x, p.y))
+        };
+
+        match origin {
+            Some((paper_x, paper_y)) => element_manager.update_element_position(element_id, paper_x + rel_x, paper_y + rel_y),
+            None => false,
+        }
+    }
+
+    /// ย้าย element ไปยัง paper อื่น (keep_relative = คงตำแหน่งสัมพันธ์จากหน้าเดิม)
+    pub fn move_element_to_page(&self, element_id: &str, target_paper_id: &str, keep_relative: bool, element_manager: &ElementManager) -> bool {
+        let (rel_x, rel_y) = if keep_relative {
+            self.find_element_paper_coords(element_id, element_manager)
+                .map(|(_, rel_x, rel_y)| (rel_x, rel_y))
+                .unwrap_or((0.0, 0.0))
+        } else {
+            (0.0, 0.0)
+        };
+
+        self.set_element_paper_coords(element_id, target_paper_id, rel_x, rel_y, element_manager)
+    }
+
+    /// เปลี่ยนขนาด paper แล้วย้าย elements ที่ยึด (anchor) ขอบขวา/ล่าง/กึ่งกลาง ตามขอบใหม่ คืน ids ที่ถูกย้าย
+    pub fn reflow_paper(&self, paper_id: &str, new_width: f64, new_height: f64, element_manager: &ElementManager) -> Vec<String> {
+        if !(new_width.is_finite() && new_height.is_finite() && new_width > 0.0 && new_height > 0.0) {
+            return Vec::new();
+        }
+
+        let old_paper = {
+            let mut papers = utils::lock(&self.papers);
+            match papers.iter_mut().find(|p| p.id == paper_id) {
+                Some(paper) => {
+                    let old_paper = paper.clone();
+                    paper.width = new_width;
+                    paper.height = new_height;
+                    old_paper
+                }
+                None => return Vec::new(),
+            }
+        };
+        let delta_x = new_width - old_paper.width;
+        let delta_y = new_height - old_paper.height;
+
+        let new_paper = A4Paper { width: new_width, height: new_height, ..old_paper.clone() };
+
+        let mut moved_ids = Vec::new();
+        let mut elements = element_manager.get_elements_ref();
+        for element in elements.iter_mut() {
+            // Percentage layouts are recomputed; anchors apply to the rest of the paper's elements
+            if element.apply_relative_layout(&new_paper) {
+                moved_ids.push(element.id.clone());
+                continue;
+            }
+            if !self.is_element_in_paper(element, &old_paper) {
+                continue;
+            }
+            // An edge anchor wins over centering on the same axis
+            let shift_x = if element.anchor_right { delta_x } else if element.anchor_center { delta_x / 2.0 } else { 0.0 };
+            let shift_y = if element.anchor_bottom { delta_y } else if element.anchor_center { delta_y / 2.0 } else { 0.0 };
+            if shift_x != 0.0 || shift_y != 0.0 {
+                element.x += shift_x;
+                element.y += shift_y;
+                moved_ids.push(element.id.clone());
+            }
+        }
+        moved_ids
+    }
+
+    /// วาง element เป็นเปอร์เซ็นต์ (0-100) ของ paper ที่ element อยู่ และคำนวณใหม่เมื่อ paper เปลี่ยนขนาด
+    pub fn set_element_relative(&self, element_id: &str, pct_x: f64, pct_y: f64, pct_w: f64, pct_h: f64, element_manager: &ElementManager) -> bool {
+        if !utils::all_finite(&[pct_x, pct_y, pct_w, pct_h]) || pct_w <= 0.0 || pct_h <= 0.0 {
+            return false;
+        }
+        let mut elements = element_manager.get_elements_ref();
+        let papers = utils::lock(&self.papers);
+        let Some(element) = elements.iter_mut().find(|e| e.id == element_id) else {
+            return false;
+        };
+        let Some(paper) = papers.iter().find(|p| self.is_element_in_paper(element, p)) else {
+            return false;
+        };
+
+        element.relative_layout = Some(RelativeLayout {
+            paper_id: paper.id.clone(),
+            x: pct_x,
+            y: pct_y,
+            width: pct_w,
+            height: pct_h,
+        });
+        element.apply_relative_layout(paper)
+    }
+
+    /// elements บน paper ที่กำหนด (fully_contained = เฉพาะที่อยู่ใน paper ทั้งหมด) คืน JSON array หรือ "[]" ถ้าไม่พบ paper
+    pub fn get_elements_in_paper(&self, paper_id: &str, fully_contained: bool, element_manager: &ElementManager) -> String {
+        let elements = self.elements_in_paper(paper_id, fully_contained, element_manager).unwrap_or_default();
+        serde_json::to_string(&elements).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// elements ที่อยู่บน paper เรียงตาม z_index แล้ว id หรือ None ถ้าไม่พบ paper
+    pub fn elements_in_paper(&self, paper_id: &str, fully_contained: bool, element_manager: &ElementManager) -> Option<Vec<Element>> {
+        let elements = element_manager.get_elements_ref();
+        let papers = utils::lock(&self.papers);
+        let paper = papers.iter().find(|p| p.id == paper_id)?;
+
+        let mut contained: Vec<Element> = elements
+            .iter()
+            .filter(|element| {
+                if fully_contained {
+                    paper.contains_element(element)
+                } else {
+                    self.is_element_in_paper(element, paper)
+                }
+            })
+            .cloned()
+            .collect();
+        contained.sort_by(|a, b| a.stable_cmp(b));
+        Some(contained)
+    }
+
+    /// ตรวจหา elements ที่คร่อมขอบ paper หรืออยู่นอก paper ทั้งหมด
+    pub fn get_boundary_violations(&self, element_manager: &ElementManager) -> String {
+        let elements = element_manager.get_elements_ref();
+        let papers = utils::lock(&self.papers);
+
+        let mut violations = Vec::new();
+        for element in elements.iter() {
+            if papers.iter().any(|paper| paper.contains_element(element)) {
+                continue;
+            }
+
+            let overlapping: Vec<&str> = papers.iter()
+                .filter(|paper| self.is_element_in_paper(element, paper))
+                .map(|paper| paper.id.as_str())
+                .collect();
+
+            let violation = if overlapping.is_empty() { "outsideAllPapers" } else { "crossesPageEdge" };
+            violations.push(serde_json::json!({
+                "elementId": element.id,
+                "violation": violation,
+                "paperIds": overlapping
+            }));
+        }
+
+        serde_json::Value::Array(violations).to_string()
+    }
+
+    /// Mail-merge: clone the first paper once per record and fill each clone's slots
+    pub fn merge_data(&self, records_json: &str, element_manager: &ElementManager) -> String {
+        let records = match serde_json::from_str::<Vec<serde_json::Value>>(records_json) {
+            Ok(records) => records,
+            Err(_) => return "[]".to_string(),
+        };
+
+        let template = {
+            let papers = utils::lock(&self.papers);
+            match papers.first() {
+                Some(paper) => paper.clone(),
+                None => return "[]".to_string(),
+            }
+        };
+
+        let template_elements: Vec<Element> = element_manager.get_elements_ref()
+            .iter()
+            .filter(|element| self.is_element_in_paper(element, &template))
+            .cloned()
+            .collect();
+
+        let mut new_paper_ids = Vec::with_capacity(records.len());
+        for record in &records {
+            let paper = self.clone_paper_below(&template);
+            element_manager.merge_record(&template_elements, paper.x - template.x, paper.y - template.y, record);
+            new_paper_ids.push(paper.id);
+        }
+
+        serde_json::to_string(&new_paper_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Append a copy of the paper below the lowest existing paper
+    fn clone_paper_below(&self, template: &Paper) -> Paper {
+        let mut papers = utils::lock(&self.papers);
+        let max_bottom = papers.iter()
+            .map(|p| p.y + p.height)
+            .fold(template.y + template.height, f64::max);
+
+        let mut paper = template.clone();
+        paper.id = unique_paper_id(&papers, &template.id);
+        paper.y = max_bottom + PAPER_SPACING;
+        papers.push(paper.clone());
+        paper
+    }
+
+    /// แทนที่ papers ทั้งหมด (ใช้ตอน rollback)
+    pub fn replace_all(&self, new_papers: Vec<A4Paper>) {
+        *utils::lock(&self.papers) = new_papers;
+    }
+
     /// ได้ papers reference สำหรับ export
     pub fn get_papers_ref(&self) -> MutexGuard<Vec<A4Paper>> {
-        self.papers.lock().unwrap()
+        utils::lock(&self.papers)
     }
 
     /// Clear all papers
     pub fn clear(&self) {
-        let mut papers = self.papers.lock().unwrap();
+        let mut papers = utils::lock(&self.papers);
         papers.clear();
     }
 }
+
+/// `{prefix}-{n}` not used by any paper, counting up from the paper count
+fn unique_paper_id(papers: &[A4Paper], prefix: &str) -> String {
+    let mut counter = papers.len();
+    loop {
+        let paper_id = format!("{}-{}", prefix, counter);
+        if !papers.iter().any(|paper| paper.id == paper_id) {
+            return paper_id;
+        }
+        counter += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_printable_area_is_inset_by_printer_margin() {
+        let paper_manager = PaperManager::new();
+        let paper = paper_manager.create("page", "A4", "Portrait", 100.0, 50.0);
+        let area = |paper_manager: &PaperManager| -> Bounds {
+            let area: serde_json::Value = serde_json::from_str(&paper_manager.get_printable_area(&paper.id)).unwrap();
+            let field = |name: &str| area[name].as_f64().unwrap();
+            Bounds::new(field("x"), field("y"), field("width"), field("height"))
+        };
+
+        // 5 mm at 96 DPI
+        let inset = 5.0 / 25.4 * 96.0;
+        let printable = area(&paper_manager);
+        assert!((printable.x - (paper.x + inset)).abs() < 1e-9);
+        assert!((printable.y - (paper.y + inset)).abs() < 1e-9);
+        assert!((printable.width - (paper.width - inset * 2.0)).abs() < 1e-9);
+        assert!((printable.height - (paper.height - inset * 2.0)).abs() < 1e-9);
+
+        assert!(paper_manager.set_printer_margin(10.0));
+        assert!((area(&paper_manager).x - (paper.x + inset * 2.0)).abs() < 1e-9);
+        assert!(!paper_manager.set_printer_margin(-1.0));
+        assert_eq!(paper_manager.get_printable_area("missing"), "null");
+    }
+
+    #[test]
+    fn test_paper_dimensions_scale_with_dpi() {
+        let paper_manager = PaperManager::new();
+        let standard = paper_manager.create("page", "A4", "Portrait", 0.0, 0.0);
+        assert_eq!((standard.width, standard.height), (794.0, 1123.0));
+        assert_eq!(standard.dpi, 96.0);
+
+        assert!(paper_manager.set_dpi(192.0));
+        let high_res = paper_manager.create("page", "A4", "Portrait", 0.0, 0.0);
+        assert_eq!((high_res.width, high_res.height), (standard.width * 2.0, standard.height * 2.0));
+
+        let landscape = paper_manager.create("page", "A5", "Landscape", 0.0, 0.0);
+        assert_eq!((landscape.width, landscape.height), (794.0 * 2.0, 559.0 * 2.0));
+
+        assert!(!paper_manager.set_dpi(0.0));
+        assert_eq!(paper_manager.get_dpi(), 192.0);
+    }
+
+    #[test]
+    fn test_reflow_moves_right_anchored_elements() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Landscape", 0.0, 0.0);
+
+        let mut logo = Element::new("logo".to_string(), "image".to_string(), "image".to_string());
+        logo.x = 900.0;
+        logo.y = 20.0;
+        logo.anchor_right = true;
+        let mut title = Element::new("title".to_string(), "heading".to_string(), "heading".to_string());
+        title.x = 20.0;
+        title.y = 20.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![logo, title])));
+
+        // Landscape A4 is 1123px wide; switch to portrait width
+        let moved = paper_manager.reflow_paper("page-0", 794.0, 1123.0, &element_manager);
+        assert_eq!(moved, vec!["logo".to_string()]);
+
+        let logo: Element = serde_json::from_str(&element_manager.get_element("logo")).unwrap();
+        assert_eq!((logo.x, logo.y), (900.0 - (1123.0 - 794.0), 20.0));
+        let title: Element = serde_json::from_str(&element_manager.get_element("title")).unwrap();
+        assert_eq!((title.x, title.y), (20.0, 20.0));
+
+        let paper: A4Paper = serde_json::from_str(&paper_manager.get_paper_by_id("page-0")).unwrap();
+        assert_eq!((paper.width, paper.height), (794.0, 1123.0));
+        assert!(paper_manager.reflow_paper("page-0", 0.0, 100.0, &element_manager).is_empty());
+    }
+
+    #[test]
+    fn test_relative_layout_follows_paper_size() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let mut stamp = Element::new("stamp".to_string(), "text".to_string(), "text".to_string());
+        stamp.x = 10.0;
+        stamp.y = 10.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![stamp])));
+
+        assert!(paper_manager.set_element_relative("stamp", 50.0, 50.0, 10.0, 10.0, &element_manager));
+        let placed: Element = serde_json::from_str(&element_manager.get_element("stamp")).unwrap();
+        assert_eq!((placed.x, placed.y), (794.0 / 2.0, 1123.0 / 2.0));
+        assert_eq!((placed.width, placed.height), (79.4, 112.3));
+
+        // Switching to landscape recomputes from the new dimensions
+        assert_eq!(paper_manager.reflow_paper("page-0", 1123.0, 794.0, &element_manager), vec!["stamp".to_string()]);
+        let reflowed: Element = serde_json::from_str(&element_manager.get_element("stamp")).unwrap();
+        assert_eq!((reflowed.x, reflowed.y), (1123.0 / 2.0, 794.0 / 2.0));
+
+        assert!(!paper_manager.set_element_relative("stamp", 50.0, 50.0, 0.0, 10.0, &element_manager));
+        assert!(!paper_manager.set_element_relative("missing", 50.0, 50.0, 10.0, 10.0, &element_manager));
+    }
+
+    #[test]
+    fn test_set_element_paper_coords_on_second_paper() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+
+        let element = Element::new("el-1".to_string(), "text".to_string(), "text".to_string());
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![element])));
+
+        assert!(paper_manager.set_element_paper_coords("el-1", "page-1", 10.0, 10.0, &element_manager));
+
+        let moved: Element = serde_json::from_str(&element_manager.get_element("el-1")).unwrap();
+        assert_eq!(moved.x, 10.0);
+        assert_eq!(moved.y, 1210.0);
+
+        let coords: serde_json::Value = serde_json::from_str(&paper_manager.get_element_paper_coords("el-1", &element_manager)).unwrap();
+        assert_eq!(coords["paperId"], "page-1");
+        assert_eq!(coords["relX"], 10.0);
+        assert_eq!(coords["relY"], 10.0);
+    }
+
+    #[test]
+    fn test_move_element_to_page_keeps_relative_offset() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+
+        let mut element = Element::new("el-1".to_string(), "text".to_string(), "text".to_string());
+        element.x = 40.0;
+        element.y = 75.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![element])));
+
+        assert!(paper_manager.move_element_to_page("el-1", "page-1", true, &element_manager));
+
+        let coords: serde_json::Value = serde_json::from_str(&paper_manager.get_element_paper_coords("el-1", &element_manager)).unwrap();
+        assert_eq!(coords["paperId"], "page-1");
+        assert_eq!(coords["relX"], 40.0);
+        assert_eq!(coords["relY"], 75.0);
+    }
+
+    #[test]
+    fn test_get_elements_in_paper() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 1200.0);
+
+        let placed = [("cover-title", 100.0), ("cover-note", 500.0), ("body", 1300.0), ("straddling", 1100.0)];
+        let elements = placed
+            .iter()
+            .map(|&(id, y)| {
+                let mut element = Element::new(id.to_string(), "text".to_string(), "text".to_string());
+                element.x = 50.0;
+                element.y = y;
+                element
+            })
+            .collect();
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(elements)));
+
+        let ids = |paper_id: &str, fully_contained: bool| -> Vec<String> {
+            let elements: Vec<Element> =
+                serde_json::from_str(&paper_manager.get_elements_in_paper(paper_id, fully_contained, &element_manager)).unwrap();
+            elements.into_iter().map(|element| element.id).collect()
+        };
+        assert_eq!(ids("page-0", true), vec!["cover-note", "cover-title"]);
+        assert_eq!(ids("page-1", true), vec!["body"]);
+        // Partially overlapping elements are included on request
+        assert_eq!(ids("page-0", false), vec!["cover-note", "cover-title", "straddling"]);
+        assert_eq!(ids("missing", false), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_get_boundary_violations() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+
+        let mut inside = Element::new("inside".to_string(), "text".to_string(), "text".to_string());
+        inside.x = 100.0;
+        inside.y = 100.0;
+        let mut straddling = Element::new("straddling".to_string(), "text".to_string(), "text".to_string());
+        straddling.x = 700.0;
+        straddling.y = 100.0;
+        let mut off_page = Element::new("off-page".to_string(), "text".to_string(), "text".to_string());
+        off_page.x = 2000.0;
+        off_page.y = 2000.0;
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![inside, straddling, off_page])));
+
+        let violations: Vec<serde_json::Value> = serde_json::from_str(&paper_manager.get_boundary_violations(&element_manager)).unwrap();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0]["elementId"], "straddling");
+        assert_eq!(violations[0]["violation"], "crossesPageEdge");
+        assert_eq!(violations[0]["paperIds"][0], "page-0");
+        assert_eq!(violations[1]["elementId"], "off-page");
+        assert_eq!(violations[1]["violation"], "outsideAllPapers");
+    }
+
+    #[test]
+    fn test_merge_data_creates_page_per_record() {
+        let paper_manager = PaperManager::new();
+        paper_manager.create_paper("page", "A4", "Portrait", 0.0, 0.0);
+
+        let mut name = Element::new("name".to_string(), "text".to_string(), "text".to_string());
+        name.x = 20.0;
+        name.y = 30.0;
+        name.slot_key = Some("name".to_string());
+        let element_manager = ElementManager::new_with_data(Arc::new(Mutex::new(vec![name])));
+
+        let records = r#"[{"name":"Alice"},{"name":"Bob"}]"#;
+        let paper_ids: Vec<String> = serde_json::from_str(&paper_manager.merge_data(records, &element_manager)).unwrap();
+        assert_eq!(paper_ids.len(), 2);
+        assert_eq!(paper_manager.get_paper_count(), 3);
+
+        let elements: Vec<Element> = serde_json::from_str(&element_manager.get_all_elements()).unwrap();
+        assert_eq!(elements.len(), 3);
+
+        for (paper_id, expected) in paper_ids.iter().zip(["Alice", "Bob"]) {
+            let paper: Paper = serde_json::from_str(&paper_manager.get_paper_by_id(paper_id)).unwrap();
+            let on_page: Vec<&Element> = elements.iter()
+                .filter(|e| paper.contains_element(e))
+                .collect();
+            assert_eq!(on_page.len(), 1);
+            assert_eq!(on_page[0].content, expected);
+            assert_ne!(on_page[0].id, "name");
+            assert_eq!(on_page[0].x - paper.x, 20.0);
+            assert_eq!(on_page[0].y - paper.y, 30.0);
+        }
+
+        // Removing a page frees its count, not its id: the next clone must not reuse a live id
+        assert!(paper_manager.remove_paper(&paper_ids[0]));
+        let next_ids: Vec<String> = serde_json::from_str(&paper_manager.merge_data(r#"[{"name":"Carol"}]"#, &element_manager)).unwrap();
+        assert_ne!(next_ids[0], paper_ids[1]);
+        let papers = paper_manager.all();
+        let mut ids: Vec<&str> = papers.iter().map(|paper| paper.id.as_str()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+}
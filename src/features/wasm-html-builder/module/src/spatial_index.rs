@@ -3,6 +3,7 @@ use std::sync::Mutex;
 use serde_json;
 use serde::{Serialize, Deserialize};
 use crate::types::*;
+use crate::utils;
 
 /// Spatial grid cell containing element IDs
 #[derive(Debug, Clone)]
@@ -18,6 +19,13 @@ impl GridCell {
     }
 }
 
+/// Cell size used when the requested one is zero, negative or not finite
+const DEFAULT_CELL_SIZE: f64 = 100.0;
+/// Grid width/height used when the requested bounds are empty or not finite
+const DEFAULT_GRID_EXTENT: f64 = 2000.0;
+/// Upper bound on rows * cols; larger grids get coarser cells instead
+const MAX_GRID_CELLS: usize = 250_000;
+
 /// Spatial grid for fast element queries
 #[derive(Debug)]
 pub struct SpatialGrid {
@@ -33,8 +41,17 @@ pub struct SpatialGrid {
 impl SpatialGrid {
     pub fn new(bounds: (f64, f64, f64, f64), cell_size: f64) -> Self {
         let (x, y, width, height) = bounds;
-        let cols = (width / cell_size).ceil() as usize;
-        let rows = (height / cell_size).ceil() as usize;
+        let positive_or = |value: f64, fallback: f64| if value.is_finite() && value > 0.0 { value } else { fallback };
+        let width = positive_or(width, DEFAULT_GRID_EXTENT);
+        let height = positive_or(height, DEFAULT_GRID_EXTENT);
+        let mut cell_size = positive_or(cell_size, DEFAULT_CELL_SIZE);
+
+        let grid_size = |cell_size: f64| ((width / cell_size).ceil() as usize, (height / cell_size).ceil() as usize);
+        let (mut cols, mut rows) = grid_size(cell_size);
+        while cols.saturating_mul(rows) > MAX_GRID_CELLS {
+            cell_size *= 2.0;
+            (cols, rows) = grid_size(cell_size);
+        }
         
         let mut cells = Vec::with_capacity(rows);
         for _ in 0..rows {
@@ -88,6 +105,72 @@ impl SpatialGrid {
         cells
     }
     
+    /// Get the cells a line segment passes through, in order from start to end
+    pub fn get_segment_cells(&self, x1: f64, y1: f64, x2: f64, y2: f64) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        let (bounds_x, bounds_y, _, _) = self.bounds;
+        if self.rows == 0 || self.cols == 0 {
+            return cells;
+        }
+
+        // Work in grid units and clip the segment to the grid first
+        let (gx1, gy1) = ((x1 - bounds_x) / self.cell_size, (y1 - bounds_y) / self.cell_size);
+        let (gx2, gy2) = ((x2 - bounds_x) / self.cell_size, (y2 - bounds_y) / self.cell_size);
+        let (dx, dy) = (gx2 - gx1, gy2 - gy1);
+        let (t_enter, t_exit) = match clip_segment(gx1, gy1, gx2, gy2, &Bounds::new(0.0, 0.0, self.cols as f64, self.rows as f64)) {
+            Some(range) => range,
+            None => return cells,
+        };
+        let (sx, sy) = (gx1 + dx * t_enter, gy1 + dy * t_enter);
+        let (ex, ey) = (gx1 + dx * t_exit, gy1 + dy * t_exit);
+
+        let last_col = self.cols as isize - 1;
+        let last_row = self.rows as isize - 1;
+        let mut col = (sx.floor() as isize).clamp(0, last_col);
+        let mut row = (sy.floor() as isize).clamp(0, last_row);
+        let end_col = (ex.floor() as isize).clamp(0, last_col);
+        let end_row = (ey.floor() as isize).clamp(0, last_row);
+
+        // Amanatides-Woo traversal: step into whichever cell boundary the segment reaches first
+        let step_col = if dx > 0.0 { 1 } else { -1 };
+        let step_row = if dy > 0.0 { 1 } else { -1 };
+        let t_delta_x = if dx != 0.0 { 1.0 / dx.abs() } else { f64::INFINITY };
+        let t_delta_y = if dy != 0.0 { 1.0 / dy.abs() } else { f64::INFINITY };
+        let mut t_max_x = if dx > 0.0 {
+            (col as f64 + 1.0 - sx) / dx
+        } else if dx < 0.0 {
+            (sx - col as f64) / -dx
+        } else {
+            f64::INFINITY
+        };
+        let mut t_max_y = if dy > 0.0 {
+            (row as f64 + 1.0 - sy) / dy
+        } else if dy < 0.0 {
+            (sy - row as f64) / -dy
+        } else {
+            f64::INFINITY
+        };
+
+        for _ in 0..=(self.rows + self.cols) {
+            cells.push((row as usize, col as usize));
+            if col == end_col && row == end_row {
+                break;
+            }
+            if t_max_x < t_max_y {
+                col += step_col;
+                t_max_x += t_delta_x;
+            } else {
+                row += step_row;
+                t_max_y += t_delta_y;
+            }
+            if col < 0 || col > last_col || row < 0 || row > last_row {
+                break;
+            }
+        }
+
+        cells
+    }
+    
     /// Add element to grid
     pub fn add_element(&mut self, element_id: &str, x: f64, y: f64, width: f64, height: f64) {
         let cells = self.get_intersecting_cells(x, y, width, height);
@@ -130,11 +213,40 @@ pub struct SpatialIndexStats {
     pub last_query_time_ms: f64,
 }
 
+/// When `auto_optimize` rebuilds the grid: enough elements and too dense a grid
+#[derive(Debug, Clone, Copy)]
+struct AutoOptimizeThresholds {
+    min_elements: usize,
+    max_average_per_cell: f64,
+    max_per_cell: usize,
+}
+
+impl Default for AutoOptimizeThresholds {
+    fn default() -> Self {
+        Self {
+            min_elements: 1000,
+            max_average_per_cell: 100.0,
+            max_per_cell: 200,
+        }
+    }
+}
+
+/// Serialized spatial index: grid geometry, occupied cells and indexed elements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpatialIndexSnapshot {
+    bounds: (f64, f64, f64, f64),
+    cell_size: f64,
+    cells: Vec<(usize, usize, Vec<String>)>, // (row, col, element ids), occupied cells only
+    elements: Vec<Element>,
+}
+
 /// Spatial index manager for fast element queries
 pub struct SpatialIndexManager {
     grid: Mutex<SpatialGrid>,
     element_map: Mutex<HashMap<String, Element>>,
     stats: Mutex<SpatialIndexStats>,
+    thresholds: Mutex<AutoOptimizeThresholds>,
 }
 
 impl SpatialIndexManager {
@@ -154,16 +266,31 @@ impl SpatialIndexManager {
                 memory_usage_bytes: total_cells * 8, // Rough estimate
                 last_query_time_ms: 0.0,
             }),
+            thresholds: Mutex::new(AutoOptimizeThresholds::default()),
         }
     }
+
+    /// Tune when `auto_optimize` fires: more than `min_elements` and a cell density above either limit
+    pub fn set_auto_optimize_thresholds(&self, min_elements: usize, max_avg_per_cell: f64, max_per_cell: usize) -> bool {
+        if !max_avg_per_cell.is_finite() || max_avg_per_cell < 0.0 {
+            return false;
+        }
+        *utils::lock(&self.thresholds) = AutoOptimizeThresholds {
+            min_elements,
+            max_average_per_cell: max_avg_per_cell,
+            max_per_cell,
+        };
+        true
+    }
     
     /// Add element to spatial index
     pub fn add_element(&self, element: &Element) -> bool {
-        let mut grid = self.grid.lock().unwrap();
-        let mut element_map = self.element_map.lock().unwrap();
+        let mut grid = utils::lock(&self.grid);
+        let mut element_map = utils::lock(&self.element_map);
         
         grid.add_element(&element.id, element.x, element.y, element.width, element.height);
         element_map.insert(element.id.clone(), element.clone());
+        drop((grid, element_map));
         
         self.update_stats();
         true
@@ -171,11 +298,12 @@ impl SpatialIndexManager {
     
     /// Remove element from spatial index
     pub fn remove_element(&self, element_id: &str) -> bool {
-        let mut grid = self.grid.lock().unwrap();
-        let mut element_map = self.element_map.lock().unwrap();
+        let mut grid = utils::lock(&self.grid);
+        let mut element_map = utils::lock(&self.element_map);
         
         grid.remove_element(element_id);
         element_map.remove(element_id);
+        drop((grid, element_map));
         
         self.update_stats();
         true
@@ -183,8 +311,8 @@ impl SpatialIndexManager {
     
     /// Update element in spatial index
     pub fn update_element(&self, element_id: &str, new_element: &Element) -> bool {
-        let mut grid = self.grid.lock().unwrap();
-        let mut element_map = self.element_map.lock().unwrap();
+        let mut grid = utils::lock(&self.grid);
+        let mut element_map = utils::lock(&self.element_map);
         
         if let Some(old_element) = element_map.get(element_id) {
             grid.update_element(
@@ -193,6 +321,7 @@ impl SpatialIndexManager {
                 new_element.x, new_element.y, new_element.width, new_element.height
             );
             element_map.insert(element_id.to_string(), new_element.clone());
+            drop((grid, element_map));
             self.update_stats();
             true
         } else {
@@ -202,10 +331,16 @@ impl SpatialIndexManager {
     
     /// Query elements in region
     pub fn query_region(&self, x: f64, y: f64, width: f64, height: f64) -> String {
-        let start_time = std::time::Instant::now();
+        let result_elements = self.elements_in_region(x, y, width, height);
+        serde_json::to_string(&result_elements).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Query elements in region as typed values
+    pub fn elements_in_region(&self, x: f64, y: f64, width: f64, height: f64) -> Vec<Element> {
+        let start_time = utils::get_performance_now();
         
-        let grid = self.grid.lock().unwrap();
-        let element_map = self.element_map.lock().unwrap();
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
         
         let cells = grid.get_intersecting_cells(x, y, width, height);
         let mut result_elements = Vec::new();
@@ -231,20 +366,48 @@ impl SpatialIndexManager {
             }
         }
         
-        let query_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        let query_time = utils::get_performance_now() - start_time;
         self.update_query_time(query_time);
         
-        serde_json::to_string(&result_elements).unwrap_or_else(|_| "[]".to_string())
+        result_elements
     }
     
+    /// Rubber-band selection: ids of elements intersecting (`"intersect"`) or fully inside (`"contain"`) a rectangle
+    pub fn select_in_rect(&self, x: f64, y: f64, width: f64, height: f64, mode: &str) -> String {
+        let contain = match mode {
+            "intersect" => false,
+            "contain" => true,
+            _ => return "[]".to_string(),
+        };
+        let ids = self.ids_in_rect(x, y, width, height, contain);
+        serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Ids of elements in a rectangle, ordered by z_index then id
+    pub fn ids_in_rect(&self, x: f64, y: f64, width: f64, height: f64, contain: bool) -> Vec<String> {
+        let rect = Bounds::new(x, y, width, height);
+        let mut elements = self.elements_in_region(x, y, width, height);
+        if contain {
+            elements.retain(|element| rect.contains(&element.get_bounds()));
+        }
+        elements.sort_by(|a, b| a.stable_cmp(b));
+        elements.into_iter().map(|element| element.id).collect()
+    }
+
     /// Find elements at point
     pub fn find_at_point(&self, x: f64, y: f64) -> String {
-        let grid = self.grid.lock().unwrap();
-        let element_map = self.element_map.lock().unwrap();
+        let result_elements = self.elements_at_point(x, y);
+        serde_json::to_string(&result_elements).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Find elements at point as typed values
+    pub fn elements_at_point(&self, x: f64, y: f64) -> Vec<Element> {
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
+        let mut result_elements = Vec::new();
         
         if let Some((row, col)) = grid.get_cell_coords(x, y) {
             let cell = &grid.cells[row][col];
-            let mut result_elements = Vec::new();
             
             for element_id in &cell.elements {
                 if let Some(element) = element_map.get(element_id) {
@@ -253,17 +416,15 @@ impl SpatialIndexManager {
                     }
                 }
             }
-            
-            serde_json::to_string(&result_elements).unwrap_or_else(|_| "[]".to_string())
-        } else {
-            "[]".to_string()
         }
+        
+        result_elements
     }
     
     /// Find nearest element to point
     pub fn find_nearest(&self, x: f64, y: f64, max_distance: f64) -> String {
-        let grid = self.grid.lock().unwrap();
-        let element_map = self.element_map.lock().unwrap();
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
         
         let start_cell = grid.get_cell_coords(x, y);
         if start_cell.is_none() {
@@ -318,10 +479,42 @@ impl SpatialIndexManager {
         }
     }
     
+    /// Find elements crossed by a line segment (e.g. a connector)
+    pub fn query_segment(&self, x1: f64, y1: f64, x2: f64, y2: f64) -> String {
+        let result_elements = self.elements_on_segment(x1, y1, x2, y2);
+        serde_json::to_string(&result_elements).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Find elements crossed by a line segment as typed values
+    pub fn elements_on_segment(&self, x1: f64, y1: f64, x2: f64, y2: f64) -> Vec<Element> {
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
+        
+        let mut result_elements = Vec::new();
+        let mut seen_ids = HashSet::new();
+        
+        for (row, col) in grid.get_segment_cells(x1, y1, x2, y2) {
+            let cell = &grid.cells[row][col];
+            for element_id in &cell.elements {
+                if !seen_ids.insert(element_id.clone()) {
+                    continue;
+                }
+                
+                if let Some(element) = element_map.get(element_id) {
+                    if clip_segment(x1, y1, x2, y2, &element.get_bounds()).is_some() {
+                        result_elements.push(element.clone());
+                    }
+                }
+            }
+        }
+        
+        result_elements
+    }
+    
     /// Detect collisions for an element
     pub fn detect_collisions(&self, element: &Element) -> String {
-        let grid = self.grid.lock().unwrap();
-        let element_map = self.element_map.lock().unwrap();
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
         
         let cells = grid.get_intersecting_cells(element.x, element.y, element.width, element.height);
         let mut collisions = Vec::new();
@@ -349,14 +542,84 @@ impl SpatialIndexManager {
     
     /// Get spatial index statistics
     pub fn get_stats(&self) -> String {
-        let stats = self.stats.lock().unwrap();
+        let stats = utils::lock(&self.stats);
         serde_json::to_string(&*stats).unwrap_or_else(|_| "{}".to_string())
     }
     
+    /// Serialize the grid and element map so the index can be restored without a rebuild
+    pub fn serialize(&self) -> String {
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
+
+        let mut cells = Vec::new();
+        for (row_index, row) in grid.cells.iter().enumerate() {
+            for (col_index, cell) in row.iter().enumerate() {
+                if !cell.elements.is_empty() {
+                    let mut ids: Vec<String> = cell.elements.iter().cloned().collect();
+                    ids.sort();
+                    cells.push((row_index, col_index, ids));
+                }
+            }
+        }
+        let mut elements: Vec<Element> = element_map.values().cloned().collect();
+        elements.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let snapshot = SpatialIndexSnapshot {
+            bounds: grid.bounds,
+            cell_size: grid.cell_size,
+            cells,
+            elements,
+        };
+        serde_json::to_string(&snapshot).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Bounds and cell size of the current grid
+    pub fn grid_geometry(&self) -> ((f64, f64, f64, f64), f64) {
+        let grid = utils::lock(&self.grid);
+        (grid.bounds, grid.cell_size)
+    }
+
+    /// Restore an index produced by `serialize`; the current index is kept if the snapshot is inconsistent
+    pub fn deserialize(&self, json: &str) -> bool {
+        let snapshot: SpatialIndexSnapshot = match serde_json::from_str(json) {
+            Ok(snapshot) => snapshot,
+            Err(_) => return false,
+        };
+        let (_, _, width, height) = snapshot.bounds;
+        if !(snapshot.cell_size.is_finite() && snapshot.cell_size > 0.0 && width.is_finite() && height.is_finite()) {
+            return false;
+        }
+
+        let mut restored = SpatialGrid::new(snapshot.bounds, snapshot.cell_size);
+        let element_map: HashMap<String, Element> = snapshot
+            .elements
+            .into_iter()
+            .map(|element| (element.id.clone(), element))
+            .collect();
+        for (row, col, ids) in snapshot.cells {
+            if row >= restored.rows || col >= restored.cols || ids.iter().any(|id| !element_map.contains_key(id)) {
+                return false;
+            }
+            restored.cells[row][col].elements.extend(ids);
+        }
+        // Every element must be present in each cell its bounds map to
+        for element in element_map.values() {
+            let mapped = restored.get_intersecting_cells(element.x, element.y, element.width, element.height);
+            if mapped.iter().any(|&(row, col)| !restored.cells[row][col].elements.contains(&element.id)) {
+                return false;
+            }
+        }
+
+        *utils::lock(&self.grid) = restored;
+        *utils::lock(&self.element_map) = element_map;
+        self.update_stats();
+        true
+    }
+
     /// Rebuild spatial index with new elements and dynamic optimization
     pub fn rebuild(&self, elements: &[Element], bounds: (f64, f64, f64, f64), cell_size: f64) {
-        let mut grid = self.grid.lock().unwrap();
-        let mut element_map = self.element_map.lock().unwrap();
+        let mut grid = utils::lock(&self.grid);
+        let mut element_map = utils::lock(&self.element_map);
         
         // Calculate optimal cell size if not provided
         let final_cell_size = if cell_size <= 0.0 {
@@ -374,6 +637,8 @@ impl SpatialIndexManager {
             grid.add_element(&element.id, element.x, element.y, element.width, element.height);
             element_map.insert(element.id.clone(), element.clone());
         }
+        // update_stats takes these locks again
+        drop((grid, element_map));
         
         self.update_stats();
     }
@@ -407,10 +672,11 @@ impl SpatialIndexManager {
         let stats_json = self.get_stats();
         if let Ok(stats) = serde_json::from_str::<SpatialIndexStats>(&stats_json) {
             // Only optimize if really needed and we have enough elements
-            if stats.total_elements > 1000 && 
-               (stats.average_elements_per_cell > 100.0 || stats.max_elements_per_cell > 200) {
+            let thresholds = *utils::lock(&self.thresholds);
+            if stats.total_elements > thresholds.min_elements && 
+               (stats.average_elements_per_cell > thresholds.max_average_per_cell || stats.max_elements_per_cell > thresholds.max_per_cell) {
                 // Get current elements
-                let element_map = self.element_map.lock().unwrap();
+                let element_map = utils::lock(&self.element_map);
                 let elements: Vec<Element> = element_map.values().cloned().collect();
                 drop(element_map);
                 
@@ -427,11 +693,11 @@ impl SpatialIndexManager {
     /// Update grid bounds
     pub fn update_bounds(&self, bounds: (f64, f64, f64, f64)) {
         let elements: Vec<Element> = {
-            let element_map = self.element_map.lock().unwrap();
+            let element_map = utils::lock(&self.element_map);
             element_map.values().cloned().collect()
         };
         
-        let grid = self.grid.lock().unwrap();
+        let grid = utils::lock(&self.grid);
         let cell_size = grid.cell_size;
         drop(grid);
         
@@ -455,9 +721,9 @@ impl SpatialIndexManager {
     }
     
     fn update_stats(&self) {
-        let grid = self.grid.lock().unwrap();
-        let element_map = self.element_map.lock().unwrap();
-        let mut stats = self.stats.lock().unwrap();
+        let grid = utils::lock(&self.grid);
+        let element_map = utils::lock(&self.element_map);
+        let mut stats = utils::lock(&self.stats);
         
         let mut occupied_cells = 0;
         let mut total_elements_in_cells = 0;
@@ -487,7 +753,160 @@ impl SpatialIndexManager {
     }
     
     fn update_query_time(&self, time_ms: f64) {
-        let mut stats = self.stats.lock().unwrap();
+        let mut stats = utils::lock(&self.stats);
         stats.last_query_time_ms = time_ms;
     }
 }
+
+/// Clip a segment against a rectangle (Liang-Barsky)
+///
+/// Returns the entry and exit parameters along the segment, or None if it misses the rectangle.
+fn clip_segment(x1: f64, y1: f64, x2: f64, y2: f64, rect: &Bounds) -> Option<(f64, f64)> {
+    let (rx, ry, rw, rh) = (rect.x, rect.y, rect.width, rect.height);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let mut t_enter: f64 = 0.0;
+    let mut t_exit: f64 = 1.0;
+    
+    for (p, q) in [(-dx, x1 - rx), (dx, rx + rw - x1), (-dy, y1 - ry), (dy, ry + rh - y1)] {
+        if p == 0.0 {
+            // Parallel to this edge: either fully inside its slab or fully outside
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t_enter = t_enter.max(t);
+            } else {
+                t_exit = t_exit.min(t);
+            }
+        }
+    }
+    
+    if t_enter <= t_exit {
+        Some((t_enter, t_exit))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element_at(id: &str, x: f64, y: f64, width: f64, height: f64) -> Element {
+        let mut element = Element::new(id.to_string(), "button".to_string(), "button".to_string());
+        element.x = x;
+        element.y = y;
+        element.width = width;
+        element.height = height;
+        element
+    }
+
+    #[test]
+    fn test_invalid_cell_size_falls_back_to_bounded_grid() {
+        let spatial_index = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 100.0);
+        spatial_index.rebuild(&[], (0.0, 0.0, 2000.0, 2000.0), 0.0);
+        assert_eq!(spatial_index.query_region(0.0, 0.0, 100.0, 100.0), "[]");
+
+        for cell_size in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            let grid = SpatialGrid::new((0.0, 0.0, 2000.0, 2000.0), cell_size);
+            assert_eq!(grid.cell_size, DEFAULT_CELL_SIZE);
+            assert_eq!((grid.cols, grid.rows), (20, 20));
+        }
+
+        // Tiny cells over a huge area are coarsened instead of allocating billions of cells
+        let grid = SpatialGrid::new((0.0, 0.0, 1e7, 1e7), 0.001);
+        assert!(grid.cols * grid.rows <= MAX_GRID_CELLS);
+        let grid = SpatialGrid::new((0.0, 0.0, -5.0, f64::NAN), 100.0);
+        assert_eq!((grid.width, grid.height), (DEFAULT_GRID_EXTENT, DEFAULT_GRID_EXTENT));
+    }
+
+    #[test]
+    fn test_query_segment_only_returns_crossed_elements() {
+        let spatial_index = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 100.0);
+        let crossed = element_at("crossed", 100.0, 100.0, 50.0, 50.0);
+        // Inside the segment's bounding box but away from the diagonal
+        let missed = element_at("missed", 300.0, 50.0, 50.0, 50.0);
+        spatial_index.rebuild(&[crossed, missed], (0.0, 0.0, 2000.0, 2000.0), 100.0);
+
+        let region: Vec<Element> = serde_json::from_str(&spatial_index.query_region(0.0, 0.0, 400.0, 400.0)).unwrap();
+        assert_eq!(region.len(), 2);
+
+        let hits: Vec<Element> = serde_json::from_str(&spatial_index.query_segment(0.0, 0.0, 400.0, 400.0)).unwrap();
+        let ids: Vec<&str> = hits.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["crossed"]);
+
+        // Reversed direction gives the same result
+        assert_eq!(spatial_index.elements_on_segment(400.0, 400.0, 0.0, 0.0).len(), 1);
+    }
+
+    #[test]
+    fn test_segment_cells_follow_the_line() {
+        let grid = SpatialGrid::new((0.0, 0.0, 500.0, 500.0), 100.0);
+        assert_eq!(grid.get_segment_cells(50.0, 50.0, 450.0, 50.0), vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(grid.get_segment_cells(10.0, 10.0, 290.0, 290.0), vec![(0, 0), (1, 0), (1, 1), (2, 1), (2, 2)]);
+        assert!(grid.get_segment_cells(-100.0, -100.0, -50.0, 600.0).is_empty());
+    }
+
+    #[test]
+    fn test_select_in_rect_modes() {
+        let spatial_index = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 100.0);
+        let inside = element_at("inside", 20.0, 20.0, 50.0, 50.0);
+        let partial = element_at("partial", 150.0, 150.0, 100.0, 100.0);
+        let outside = element_at("outside", 500.0, 500.0, 50.0, 50.0);
+        spatial_index.rebuild(&[inside, partial, outside], (0.0, 0.0, 2000.0, 2000.0), 100.0);
+
+        let intersecting: Vec<String> = serde_json::from_str(&spatial_index.select_in_rect(0.0, 0.0, 200.0, 200.0, "intersect")).unwrap();
+        assert_eq!(intersecting, vec!["inside", "partial"]);
+
+        let contained: Vec<String> = serde_json::from_str(&spatial_index.select_in_rect(0.0, 0.0, 200.0, 200.0, "contain")).unwrap();
+        assert_eq!(contained, vec!["inside"]);
+
+        assert_eq!(spatial_index.select_in_rect(0.0, 0.0, 200.0, 200.0, "touch"), "[]");
+    }
+
+    #[test]
+    fn test_serialize_round_trip_preserves_queries() {
+        let spatial_index = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 100.0);
+        let elements: Vec<Element> = (0..20)
+            .map(|i| element_at(&format!("e{}", i), (i * 90) as f64, (i * 45) as f64, 120.0, 60.0))
+            .collect();
+        spatial_index.rebuild(&elements, (0.0, 0.0, 2000.0, 2000.0), 100.0);
+        let json = spatial_index.serialize();
+
+        let restored = SpatialIndexManager::new((0.0, 0.0, 500.0, 500.0), 50.0);
+        assert!(restored.deserialize(&json));
+        assert_eq!(restored.serialize(), json);
+        assert_eq!(restored.select_in_rect(100.0, 100.0, 600.0, 300.0, "intersect"), spatial_index.select_in_rect(100.0, 100.0, 600.0, 300.0, "intersect"));
+        let stats = |index: &SpatialIndexManager| -> SpatialIndexStats { serde_json::from_str(&index.get_stats()).unwrap() };
+        assert_eq!(stats(&restored).occupied_cells, stats(&spatial_index).occupied_cells);
+
+        // An element that no longer maps into its recorded cells is rejected
+        let mut snapshot: serde_json::Value = serde_json::from_str(&json).unwrap();
+        snapshot["elements"][0]["x"] = serde_json::json!(1500.0);
+        assert!(!restored.deserialize(&snapshot.to_string()));
+        assert!(!restored.deserialize("not json"));
+        assert_eq!(restored.serialize(), json);
+    }
+
+    #[test]
+    fn test_auto_optimize_uses_configured_thresholds() {
+        let spatial_index = SpatialIndexManager::new((0.0, 0.0, 2000.0, 2000.0), 500.0);
+        let elements: Vec<Element> = (0..10)
+            .map(|i| element_at(&format!("e{}", i), (i * 10) as f64, 10.0, 20.0, 20.0))
+            .collect();
+        spatial_index.rebuild(&elements, (0.0, 0.0, 2000.0, 2000.0), 500.0);
+
+        // Defaults need more than 1000 elements
+        assert!(!spatial_index.auto_optimize());
+
+        assert!(spatial_index.set_auto_optimize_thresholds(5, 4.0, 8));
+        assert!(spatial_index.auto_optimize());
+        let stats: SpatialIndexStats = serde_json::from_str(&spatial_index.get_stats()).unwrap();
+        assert_eq!(stats.total_elements, 10);
+        assert!(utils::lock(&spatial_index.grid).cell_size < 500.0);
+
+        assert!(!spatial_index.set_auto_optimize_thresholds(5, f64::NAN, 8));
+    }
+}
@@ -5,6 +5,7 @@ use flate2::write::DeflateEncoder;
 use flate2::read::DeflateDecoder;
 use base64::{Engine as _, engine::general_purpose};
 use crate::types::ElementStyle;
+use crate::utils;
 
 /// Style history entry with compression
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +19,7 @@ pub struct StyleHistoryEntry {
 pub struct StyleHistory {
     entries: Vec<StyleHistoryEntry>,
     max_entries: usize,
+    position: usize, // Undo cursor: entries[..position] are the applied history
 }
 
 impl StyleHistory {
@@ -26,12 +28,17 @@ impl StyleHistory {
         StyleHistory {
             entries: Vec::with_capacity(max_entries),
             max_entries,
+            position: 0,
         }
     }
 
-    /// Add a style to history (auto-deduplicates)
+    /// Add a style to history (auto-deduplicates, discards any redo entries)
     pub fn add_style(&mut self, style: ElementStyle) {
-        let timestamp = js_sys::Date::now();
+        let timestamp = utils::now_ms();
+        
+        // Adding after an undo starts a new branch
+        self.entries.truncate(self.position);
+        self.position = self.entries.len();
         
         // Check if the last entry is identical (avoid duplicates)
         if let Some(last_entry) = self.entries.last() {
@@ -49,6 +56,25 @@ impl StyleHistory {
         if self.entries.len() > self.max_entries {
             self.entries.remove(0);
         }
+        self.position = self.entries.len();
+    }
+
+    /// Step the cursor back and return the style now current
+    pub fn undo_style(&mut self) -> Option<ElementStyle> {
+        if self.position <= 1 {
+            return None;
+        }
+        self.position -= 1;
+        Some(self.entries[self.position - 1].style.clone())
+    }
+
+    /// Step the cursor forward and return the style now current
+    pub fn redo_style(&mut self) -> Option<ElementStyle> {
+        if self.position >= self.entries.len() {
+            return None;
+        }
+        self.position += 1;
+        Some(self.entries[self.position - 1].style.clone())
     }
 
     /// Get the most recent style
@@ -78,6 +104,7 @@ impl StyleHistory {
     /// Clear all history
     pub fn clear(&mut self) {
         self.entries.clear();
+        self.position = 0;
     }
 
     /// Get history size
@@ -133,6 +160,7 @@ impl StyleHistory {
             let start = self.entries.len() - self.max_entries;
             self.entries = self.entries[start..].to_vec();
         }
+        self.position = self.entries.len();
 
         Ok(())
     }
@@ -157,7 +185,15 @@ impl StyleHistory {
         a.stroke.color == b.stroke.color &&
         a.stroke.opacity == b.stroke.opacity &&
         a.stroke.width == b.stroke.width &&
-        a.stroke.enabled == b.stroke.enabled
+        a.stroke.enabled == b.stroke.enabled &&
+        a.stroke.line_cap == b.stroke.line_cap &&
+        a.stroke.line_join == b.stroke.line_join &&
+        a.blend_mode == b.blend_mode &&
+        a.vertical_align == b.vertical_align &&
+        a.direction == b.direction &&
+        a.line_height == b.line_height &&
+        a.letter_spacing == b.letter_spacing &&
+        a.overflow == b.overflow
     }
 }
 
@@ -229,4 +265,25 @@ mod tests {
         assert_eq!(imported_style.font_size, 20.0);
         assert_eq!(imported_style.color, "#ff0000");
     }
+
+    #[test]
+    fn test_undo_redo_cursor() {
+        let mut history = StyleHistory::new(10);
+        for i in 0..4 {
+            history.add_style(ElementStyle { font_size: 10.0 + i as f64, ..ElementStyle::default() });
+        }
+
+        assert_eq!(history.undo_style().unwrap().font_size, 12.0);
+        assert_eq!(history.undo_style().unwrap().font_size, 11.0);
+        assert_eq!(history.redo_style().unwrap().font_size, 12.0);
+        assert_eq!(history.undo_style().unwrap().font_size, 11.0);
+
+        // A new style after undoing discards the forward history
+        history.add_style(ElementStyle { font_size: 20.0, ..ElementStyle::default() });
+        assert_eq!(history.len(), 3);
+        assert!(history.redo_style().is_none());
+        assert_eq!(history.undo_style().unwrap().font_size, 11.0);
+        assert_eq!(history.undo_style().unwrap().font_size, 10.0);
+        assert!(history.undo_style().is_none());
+    }
 }
@@ -1,6 +1,26 @@
 use serde_json;
 use std::sync::{Mutex, Arc};
 use crate::types::*;
+use crate::utils;
+
+/// Whether `target` is `element_id` or sits somewhere in its chain of cell elements
+fn nests(elements: &[Element], element_id: &str, target: &str) -> bool {
+    let mut pending = vec![element_id];
+    let mut seen = std::collections::HashSet::new();
+    while let Some(current) = pending.pop() {
+        if current == target {
+            return true;
+        }
+        if !seen.insert(current) {
+            continue;
+        }
+        let Some(table_data) = elements.iter().find(|element| element.id == current).and_then(|element| element.table_data.as_ref()) else {
+            continue;
+        };
+        pending.extend(table_data.rows.iter().flat_map(|row| &row.cells).filter_map(|cell| cell.element_id.as_deref()));
+    }
+    false
+}
 
 /// Table management module
 pub struct TableManager {
@@ -12,9 +32,31 @@ impl TableManager {
         Self { elements }
     }
 
+    /// Set defaults (cell padding, row height, column width) for newly added rows/columns
+    pub fn set_table_defaults(&self, element_id: &str, cell_padding: f64, row_height: f64, col_width: f64) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        for element in elements.iter_mut() {
+            if element.id == element_id && element.is_table() {
+                return element.set_table_defaults(cell_padding, row_height, col_width);
+            }
+        }
+        false
+    }
+
+    /// Show or hide the computed totals row/column
+    pub fn set_table_totals(&self, element_id: &str, totals_row: bool, totals_col: bool) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        for element in elements.iter_mut() {
+            if element.id == element_id && element.is_table() {
+                return element.set_table_totals(totals_row, totals_col);
+            }
+        }
+        false
+    }
+
     /// Add row to table
     pub fn add_table_row(&self, element_id: &str, at_index: Option<usize>) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.add_table_row(at_index);
@@ -25,7 +67,7 @@ impl TableManager {
 
     /// Remove row from table
     pub fn remove_table_row(&self, element_id: &str, index: usize) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.remove_table_row(index);
@@ -36,7 +78,7 @@ impl TableManager {
 
     /// Add column to table
     pub fn add_table_column(&self, element_id: &str, at_index: Option<usize>) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.add_table_column(at_index);
@@ -45,9 +87,62 @@ impl TableManager {
         false
     }
 
+    /// Restyle the whole table with a theme: "striped", "bordered", "minimal" or "header-dark"
+    pub fn apply_table_theme(&self, element_id: &str, theme: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == element_id && element.is_table()) {
+            Some(element) => element.apply_table_theme(theme),
+            None => false,
+        }
+    }
+
+    /// Move a row to another position, keeping its cells, styles and height
+    pub fn move_table_row(&self, element_id: &str, from: usize, to: usize) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == element_id && element.is_table()) {
+            Some(element) => element.move_table_row(from, to),
+            None => false,
+        }
+    }
+
+    /// Move a column to another position, keeping its cells, styles and width
+    pub fn move_table_column(&self, element_id: &str, from: usize, to: usize) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == element_id && element.is_table()) {
+            Some(element) => element.move_table_column(from, to),
+            None => false,
+        }
+    }
+
+    /// Insert a column numbering the data rows from start, renumbered as rows are added or removed
+    pub fn add_table_sequence_column(&self, element_id: &str, start: i32, at_index: Option<usize>) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == element_id && element.is_table()) {
+            Some(element) => element.add_table_sequence_column(start, at_index),
+            None => false,
+        }
+    }
+
+    /// Set the number format numeric cells are exported with, e.g.
+    /// `{"thousandsSeparator": ".", "decimalSeparator": ",", "decimals": 2}`; empty or `null` clears it
+    pub fn set_table_number_format(&self, element_id: &str, format_json: &str) -> bool {
+        let number_format = match format_json.trim() {
+            "" | "null" => None,
+            json => match serde_json::from_str::<NumberFormat>(json) {
+                Ok(number_format) => Some(number_format),
+                Err(_) => return false,
+            },
+        };
+        let mut elements = utils::lock(&self.elements);
+        match elements.iter_mut().find(|element| element.id == element_id && element.is_table()) {
+            Some(element) => element.set_table_number_format(number_format),
+            None => false,
+        }
+    }
+
     /// Remove column from table
     pub fn remove_table_column(&self, element_id: &str, index: usize) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.remove_table_column(index);
@@ -58,7 +153,7 @@ impl TableManager {
 
     /// Update table cell content
     pub fn update_table_cell(&self, element_id: &str, row: usize, col: usize, content: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.update_table_cell(row, col, content.to_string());
@@ -67,9 +162,33 @@ impl TableManager {
         false
     }
 
+    /// Put an existing element inside a cell (empty element_id clears it); the table cannot end up inside itself
+    pub fn set_table_cell_element(&self, table_id: &str, row: usize, col: usize, element_id: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        if !element_id.is_empty()
+            && (!elements.iter().any(|element| element.id == element_id) || nests(&elements, element_id, table_id))
+        {
+            return false;
+        }
+        let Some(cell) = elements
+            .iter_mut()
+            .find(|element| element.id == table_id && element.is_table())
+            .and_then(|table| table.table_data.as_mut())
+            .and_then(|table_data| table_data.rows.get_mut(row))
+            .and_then(|table_row| table_row.cells.get_mut(col))
+        else {
+            return false;
+        };
+        if cell.computed {
+            return false;
+        }
+        cell.element_id = Some(element_id.to_string()).filter(|element_id| !element_id.is_empty());
+        true
+    }
+
     /// Update table cell style
     pub fn update_table_cell_style(&self, element_id: &str, row: usize, col: usize, style_json: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 if let Some(ref mut table_data) = element.table_data {
@@ -90,15 +209,47 @@ impl TableManager {
                             if let Some(font_style) = style_update.get("fontStyle").and_then(|v| v.as_str()) {
                                 cell.style.font_style = font_style.to_string();
                             }
-                            if let Some(color) = style_update.get("color").and_then(|v| v.as_str()) {
+                            if let Some(color) = style_update.get("color").and_then(|v| v.as_str()).filter(|c| utils::is_valid_css_color(c)) {
                                 cell.style.color = color.to_string();
                             }
-                            if let Some(bg_color) = style_update.get("backgroundColor").and_then(|v| v.as_str()) {
+                            if let Some(bg_color) = style_update.get("backgroundColor").and_then(|v| v.as_str()).filter(|c| utils::is_valid_css_color(c)) {
                                 cell.style.background_color = bg_color.to_string();
                             }
                             if let Some(text_align) = style_update.get("textAlign").and_then(|v| v.as_str()) {
                                 cell.style.text_align = text_align.to_string();
                             }
+                            match style_update.get("verticalAlign") {
+                                Some(serde_json::Value::Null) => cell.style.vertical_align = None,
+                                Some(serde_json::Value::String(vertical_align)) if VERTICAL_ALIGNS.contains(&vertical_align.as_str()) => {
+                                    cell.style.vertical_align = Some(vertical_align.clone());
+                                }
+                                _ => {}
+                            }
+                            match style_update.get("direction") {
+                                Some(serde_json::Value::Null) => cell.style.direction = None,
+                                Some(serde_json::Value::String(direction)) if TEXT_DIRECTIONS.contains(&direction.as_str()) => {
+                                    cell.style.direction = Some(direction.clone());
+                                }
+                                _ => {}
+                            }
+                            match style_update.get("lineHeight") {
+                                Some(serde_json::Value::Null) => cell.style.line_height = None,
+                                Some(value) => {
+                                    if let Some(line_height) = value.as_f64().filter(|line_height| line_height.is_finite() && *line_height > 0.0) {
+                                        cell.style.line_height = Some(line_height);
+                                    }
+                                }
+                                None => {}
+                            }
+                            match style_update.get("letterSpacing") {
+                                Some(serde_json::Value::Null) => cell.style.letter_spacing = None,
+                                Some(value) => {
+                                    if let Some(letter_spacing) = value.as_f64().filter(|letter_spacing| letter_spacing.is_finite()) {
+                                        cell.style.letter_spacing = Some(letter_spacing);
+                                    }
+                                }
+                                None => {}
+                            }
                             
                             return true;
                         }
@@ -112,7 +263,7 @@ impl TableManager {
 
     /// Merge table cells
     pub fn merge_table_cells(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.merge_table_cells(start_row, start_col, end_row, end_col);
@@ -121,22 +272,77 @@ impl TableManager {
         false
     }
 
-    /// Get table data
-    pub fn get_table_data(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+    /// Clear cell contents in a range
+    pub fn clear_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        for element in elements.iter_mut() {
+            if element.id == element_id && element.is_table() {
+                return element.clear_table_range(start_row, start_col, end_row, end_col);
+            }
+        }
+        false
+    }
+
+    /// Fill a range of cells with the same value
+    pub fn fill_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize, value: &str) -> bool {
+        let mut elements = utils::lock(&self.elements);
+        for element in elements.iter_mut() {
+            if element.id == element_id && element.is_table() {
+                return element.fill_table_range(start_row, start_col, end_row, end_col, value);
+            }
+        }
+        false
+    }
+
+    /// Copy a cell range (contents and styles) as JSON
+    pub fn copy_table_range(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> String {
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
-                if let Some(table_data) = element.get_table_data() {
-                    return serde_json::to_string(table_data).unwrap_or_else(|_| "null".to_string());
+                if let Some(block) = element.copy_table_range(start_row, start_col, end_row, end_col) {
+                    return serde_json::to_string(&block).unwrap_or_else(|_| "null".to_string());
                 }
             }
         }
         "null".to_string()
     }
 
+    /// Paste a block produced by `copy_table_range` anchored at the target cell
+    pub fn paste_table_range(&self, element_id: &str, target_row: usize, target_col: usize, block_json: &str) -> bool {
+        let block = match serde_json::from_str::<TableRangeBlock>(block_json) {
+            Ok(block) => block,
+            Err(_) => return false,
+        };
+
+        let mut elements = utils::lock(&self.elements);
+        for element in elements.iter_mut() {
+            if element.id == element_id && element.is_table() {
+                return element.paste_table_range(target_row, target_col, &block);
+            }
+        }
+        false
+    }
+
+    /// Get table data
+    pub fn get_table_data(&self, element_id: &str) -> String {
+        match self.table_data(element_id) {
+            Some(table_data) => serde_json::to_string(&table_data).unwrap_or_else(|_| "null".to_string()),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Get a copy of the table data
+    pub fn table_data(&self, element_id: &str) -> Option<TableData> {
+        let elements = utils::lock(&self.elements);
+        elements
+            .iter()
+            .find(|element| element.id == element_id && element.is_table())
+            .and_then(|element| element.get_table_data().cloned())
+    }
+
     /// Update table column width
     pub fn update_table_column_width(&self, element_id: &str, column_index: usize, width: f64) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 if let Some(ref mut table_data) = element.table_data {
@@ -152,7 +358,7 @@ impl TableManager {
 
     /// Update table row height
     pub fn update_table_row_height(&self, element_id: &str, row_index: usize, height: f64) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 if let Some(ref mut table_data) = element.table_data {
@@ -168,7 +374,7 @@ impl TableManager {
 
     /// Calculate sum of column (Excel-like function)
     pub fn calculate_column_sum(&self, element_id: &str, col_index: usize) -> f64 {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 return element.calculate_column_sum(col_index);
@@ -179,7 +385,7 @@ impl TableManager {
 
     /// Calculate sum of row (Excel-like function)
     pub fn calculate_row_sum(&self, element_id: &str, row_index: usize) -> f64 {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 return element.calculate_row_sum(row_index);
@@ -190,7 +396,7 @@ impl TableManager {
 
     /// Calculate average of range (Excel-like function)
     pub fn calculate_average(&self, element_id: &str, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> f64 {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 return element.calculate_average(start_row, start_col, end_row, end_col);
@@ -201,7 +407,7 @@ impl TableManager {
 
     /// Auto-fit columns based on content (Excel-like function)
     pub fn auto_fit_columns(&self, element_id: &str) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.auto_fit_columns();
@@ -212,7 +418,7 @@ impl TableManager {
 
     /// Unmerge table cells
     pub fn unmerge_table_cells(&self, element_id: &str, row: usize, col: usize) -> bool {
-        let mut elements = self.elements.lock().unwrap();
+        let mut elements = utils::lock(&self.elements);
         for element in elements.iter_mut() {
             if element.id == element_id && element.is_table() {
                 return element.unmerge_table_cells(row, col);
@@ -223,7 +429,7 @@ impl TableManager {
 
     /// Check if a cell is merged
     pub fn is_cell_merged(&self, element_id: &str, row: usize, col: usize) -> bool {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 return element.is_cell_merged(row, col);
@@ -232,9 +438,20 @@ impl TableManager {
         false
     }
 
+    /// Next editable cell for keyboard navigation, as `{"row","col"}` (or "null" at the table edge)
+    pub fn table_next_cell(&self, element_id: &str, row: usize, col: usize, direction: &str) -> String {
+        let elements = utils::lock(&self.elements);
+        elements
+            .iter()
+            .find(|element| element.id == element_id && element.is_table())
+            .and_then(|element| element.next_table_cell(row, col, direction))
+            .map(|(row, col)| serde_json::json!({ "row": row, "col": col }).to_string())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
     /// Get table dimensions for export
     pub fn get_table_dimensions(&self, element_id: &str) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 if let Some(ref table_data) = element.table_data {
@@ -258,7 +475,7 @@ impl TableManager {
 
     /// Get table cell style
     pub fn get_table_cell_style(&self, element_id: &str, row: usize, col: usize) -> String {
-        let elements = self.elements.lock().unwrap();
+        let elements = utils::lock(&self.elements);
         for element in elements.iter() {
             if element.id == element_id && element.is_table() {
                 if let Some(ref table_data) = element.table_data {
@@ -272,7 +489,11 @@ impl TableManager {
                             "fontStyle": cell.style.font_style,
                             "color": cell.style.color,
                             "backgroundColor": cell.style.background_color,
-                            "textAlign": cell.style.text_align
+                            "textAlign": cell.style.text_align,
+                            "verticalAlign": cell.style.vertical_align.as_deref().unwrap_or("top"),
+                            "direction": cell.style.direction,
+                            "lineHeight": cell.style.line_height,
+                            "letterSpacing": cell.style.letter_spacing
                         });
                         
                         return cell_style.to_string();
@@ -289,8 +510,326 @@ impl TableManager {
             "fontStyle": "normal",
             "color": "#000000",
             "backgroundColor": "#ffffff",
-            "textAlign": "left"
+            "textAlign": "left",
+            "verticalAlign": "top"
         });
         default_style.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_manager_with_table(rows: u32, cols: u32) -> TableManager {
+        let mut element = Element::new("table-1".to_string(), "table".to_string(), "table".to_string());
+        element.create_default_table(rows, cols);
+        TableManager::new(Arc::new(Mutex::new(vec![element])))
+    }
+
+    fn table_data(table_manager: &TableManager) -> TableData {
+        serde_json::from_str(&table_manager.get_table_data("table-1")).unwrap()
+    }
+
+    #[test]
+    fn test_sequence_column_numbers_data_rows() {
+        let table_manager = table_manager_with_table(4, 2);
+        assert!(table_manager.add_table_sequence_column("table-1", 1, Some(0)));
+        assert!(!table_manager.add_table_sequence_column("table-1", 1, Some(0)));
+
+        let numbers = |table_manager: &TableManager| -> Vec<String> {
+            table_data(table_manager).rows.iter().map(|row| row.cells[0].content.clone()).collect()
+        };
+        assert_eq!(numbers(&table_manager), vec!["#", "1", "2", "3"]);
+        assert_eq!(table_data(&table_manager).sequence_col, Some(0));
+
+        // Renumbered when rows change
+        assert!(table_manager.remove_table_row("table-1", 1));
+        assert!(table_manager.add_table_row("table-1", None));
+        assert!(table_manager.add_table_row("table-1", Some(1)));
+        assert_eq!(numbers(&table_manager), vec!["#", "1", "2", "3", "4"]);
+
+        // Row sums skip the numbers
+        assert!(table_manager.update_table_cell("table-1", 1, 1, "10"));
+        assert_eq!(table_manager.calculate_row_sum("table-1", 1), 10.0);
+
+        // Follows column moves and is dropped with its column
+        assert!(table_manager.add_table_column("table-1", Some(0)));
+        assert_eq!(table_data(&table_manager).sequence_col, Some(1));
+        assert!(table_manager.remove_table_column("table-1", 1));
+        assert_eq!(table_data(&table_manager).sequence_col, None);
+    }
+
+    #[test]
+    fn test_table_defaults_apply_to_new_rows_and_columns() {
+        let table_manager = table_manager_with_table(2, 2);
+        assert!(table_manager.set_table_defaults("table-1", 2.0, 16.0, 48.0));
+        assert!(!table_manager.set_table_defaults("table-1", 2.0, 0.0, 48.0));
+
+        assert!(table_manager.add_table_row("table-1", None));
+        assert!(table_manager.add_table_column("table-1", None));
+
+        let data = table_data(&table_manager);
+        let new_row = data.rows.last().unwrap();
+        assert_eq!(new_row.height, 16.0);
+        assert_eq!(new_row.cells[0].style.padding, 2.0);
+        assert_eq!(*data.column_widths.last().unwrap(), 48.0);
+        assert_eq!(data.rows[0].cells[2].style.padding, 2.0);
+        // Existing cells keep their padding
+        assert_eq!(data.rows[0].cells[0].style.padding, 8.0);
+    }
+
+    #[test]
+    fn test_auto_fit_columns_without_data_rows() {
+        let table_manager = table_manager_with_table(3, 2);
+        assert!(table_manager.remove_table_row("table-1", 2));
+        assert!(table_manager.remove_table_row("table-1", 1));
+        assert_eq!(table_data(&table_manager).rows.len(), 1);
+        assert!(table_manager.auto_fit_columns("table-1"));
+
+        // Bold header text is measured wider than the same text in a data cell
+        let table_manager = table_manager_with_table(2, 2);
+        let header = "Quarterly revenue total";
+        assert!(table_manager.update_table_cell("table-1", 0, 0, header));
+        assert!(table_manager.update_table_cell("table-1", 1, 1, header));
+        assert!(table_manager.auto_fit_columns("table-1"));
+        let widths = table_data(&table_manager).column_widths;
+        assert!(widths[0] > widths[1]);
+
+        // A table with no rows at all must not panic
+        let mut element = Element::new("empty".to_string(), "table".to_string(), "table".to_string());
+        element.create_default_table(1, 2);
+        element.table_data.as_mut().unwrap().rows.clear();
+        assert!(element.auto_fit_columns());
+    }
+
+    #[test]
+    fn test_clear_table_range() {
+        let table_manager = table_manager_with_table(3, 3);
+
+        assert!(table_manager.clear_table_range("table-1", 1, 0, 2, 1));
+
+        let data = table_data(&table_manager);
+        for row in 1..=2 {
+            assert_eq!(data.rows[row].cells[0].content, "");
+            assert_eq!(data.rows[row].cells[1].content, "");
+            assert_eq!(data.rows[row].cells[2].content, "Data");
+        }
+        assert_eq!(data.rows[0].cells[0].content, "Header");
+    }
+
+    #[test]
+    fn test_fill_table_range_skips_merged_placeholders() {
+        let table_manager = table_manager_with_table(4, 3);
+        assert!(table_manager.merge_table_cells("table-1", 1, 1, 2, 1));
+
+        assert!(table_manager.fill_table_range("table-1", 1, 1, 3, 1, "X"));
+
+        let data = table_data(&table_manager);
+        let main = &data.rows[1].cells[1];
+        assert_eq!(main.content, "X");
+        assert_eq!((main.row_span, main.col_span), (2, 1));
+
+        let placeholder = &data.rows[2].cells[1];
+        assert_eq!(placeholder.content, "");
+        assert_eq!((placeholder.row_span, placeholder.col_span), (0, 0));
+
+        assert_eq!(data.rows[3].cells[1].content, "X");
+    }
+
+    #[test]
+    fn test_striped_theme_alternates_row_backgrounds() {
+        let table_manager = table_manager_with_table(5, 2);
+        assert!(table_manager.apply_table_theme("table-1", "striped"));
+
+        let data = table_data(&table_manager);
+        let backgrounds: Vec<&str> = data.rows.iter().map(|row| row.cells[0].style.background_color.as_str()).collect();
+        assert_eq!(backgrounds, vec!["#f3f4f6", "#ffffff", "#f9fafb", "#ffffff", "#f9fafb"]);
+        assert!(data.rows.iter().all(|row| row.cells[0].style.background_color == row.cells[1].style.background_color));
+        assert_eq!(data.rows[0].cells[0].style.font_weight, "bold");
+
+        assert!(table_manager.apply_table_theme("table-1", "minimal"));
+        assert_eq!(table_data(&table_manager).rows[2].cells[1].style.border_width, 0.0);
+        assert!(!table_manager.apply_table_theme("table-1", "neon"));
+    }
+
+    #[test]
+    fn test_move_table_row_keeps_contents_and_styles() {
+        let table_manager = table_manager_with_table(4, 3);
+        table_manager.update_table_cell("table-1", 1, 0, "First");
+        table_manager.update_table_cell("table-1", 3, 0, "Last");
+        table_manager.update_table_cell_style("table-1", 1, 1, r##"{"backgroundColor":"#ffee00"}"##);
+        table_manager.update_table_row_height("table-1", 1, 44.0);
+        let before = table_data(&table_manager);
+
+        assert!(table_manager.move_table_row("table-1", 1, 3));
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows.len(), 4);
+        assert_eq!(data.rows[3].cells[0].content, "First");
+        assert_eq!(data.rows[3].cells[1].style.background_color, "#ffee00");
+        assert_eq!(data.rows[3].height, 44.0);
+        assert_eq!(data.rows[2].cells[0].content, "Last");
+        assert!(data.rows.iter().all(|row| row.cells.len() == data.columns));
+        assert_eq!(data.column_widths, before.column_widths);
+
+        // Rows crossed by a vertical merge stay put
+        assert!(table_manager.merge_table_cells("table-1", 1, 0, 2, 0));
+        assert!(!table_manager.move_table_row("table-1", 3, 1));
+        assert!(!table_manager.move_table_row("table-1", 0, 4));
+    }
+
+    #[test]
+    fn test_sequence_column_survives_removing_header_rows() {
+        let table_manager = table_manager_with_table(2, 2);
+        assert!(table_manager.set_table_totals("table-1", true, false));
+        assert!(table_manager.add_table_sequence_column("table-1", 1, Some(0)));
+
+        assert!(table_manager.remove_table_row("table-1", 0));
+        let data = table_data(&table_manager);
+        assert_eq!(data.header_rows, 0);
+        assert_eq!(data.rows[0].cells[0].content, "1");
+        assert!(table_manager.remove_table_row("table-1", 0));
+        assert_eq!(table_data(&table_manager).rows.len(), 1);
+        assert!(table_data(&table_manager).rows[0].cells.iter().all(|cell| cell.computed));
+    }
+
+    #[test]
+    fn test_move_table_column_keeps_widths_and_sequence() {
+        let table_manager = table_manager_with_table(3, 3);
+        table_manager.update_table_cell("table-1", 1, 0, "A");
+        table_manager.update_table_cell_style("table-1", 1, 0, r##"{"color":"#ff0000"}"##);
+        table_manager.update_table_column_width("table-1", 0, 200.0);
+        assert!(table_manager.add_table_sequence_column("table-1", 1, Some(2)));
+
+        assert!(table_manager.move_table_column("table-1", 0, 3));
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows[1].cells[3].content, "A");
+        assert_eq!(data.rows[1].cells[3].style.color, "#ff0000");
+        assert_eq!(data.column_widths[3], 200.0);
+        assert_eq!(data.column_widths.len(), data.columns);
+        assert_eq!(data.sequence_col, Some(1));
+        assert_eq!(data.rows[2].cells[1].content, "2");
+
+        assert!(table_manager.merge_table_cells("table-1", 0, 2, 0, 3));
+        assert!(!table_manager.move_table_column("table-1", 0, 2));
+        assert!(table_manager.move_table_column("table-1", 1, 0));
+        assert_eq!(table_data(&table_manager).sequence_col, Some(0));
+    }
+
+    #[test]
+    fn test_copy_paste_table_range_preserves_styles() {
+        let table_manager = table_manager_with_table(3, 3);
+        table_manager.update_table_cell("table-1", 1, 0, "A");
+        table_manager.update_table_cell("table-1", 1, 1, "B");
+        table_manager.update_table_cell("table-1", 2, 0, "C");
+        table_manager.update_table_cell("table-1", 2, 1, "D");
+        table_manager.update_table_cell_style("table-1", 1, 0, r##"{"color":"#ff0000","fontWeight":"bold"}"##);
+        table_manager.update_table_cell_style("table-1", 2, 1, r##"{"backgroundColor":"#00ff00"}"##);
+
+        let block = table_manager.copy_table_range("table-1", 1, 0, 2, 1);
+        assert!(table_manager.paste_table_range("table-1", 3, 2, &block));
+
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows.len(), 5);
+        assert_eq!(data.columns, 4);
+        assert_eq!(data.rows[3].cells[2].content, "A");
+        assert_eq!(data.rows[3].cells[3].content, "B");
+        assert_eq!(data.rows[4].cells[2].content, "C");
+        assert_eq!(data.rows[4].cells[3].content, "D");
+        assert_eq!(data.rows[3].cells[2].style.color, "#ff0000");
+        assert_eq!(data.rows[3].cells[2].style.font_weight, "bold");
+        assert_eq!(data.rows[4].cells[3].style.background_color, "#00ff00");
+    }
+
+    #[test]
+    fn test_paste_table_range_grows_above_totals() {
+        let table_manager = table_manager_with_table(3, 2);
+        table_manager.update_table_cell("table-1", 1, 0, "1");
+        table_manager.update_table_cell("table-1", 2, 0, "2");
+        assert!(table_manager.set_table_totals("table-1", true, true));
+
+        let block = table_manager.copy_table_range("table-1", 1, 0, 2, 0);
+        assert!(table_manager.paste_table_range("table-1", 2, 1, &block));
+
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows.len(), 5);
+        assert_eq!(data.columns, 3);
+        assert_eq!(data.rows[2].cells[1].content, "1");
+        assert_eq!(data.rows[3].cells[1].content, "2");
+        // Totals stay last and include the pasted block
+        assert!(data.rows[4].cells.iter().all(|cell| cell.computed));
+        assert!(data.rows.iter().all(|row| row.cells[2].computed));
+        assert_eq!(data.rows[4].cells[1].content, "3");
+    }
+
+    #[test]
+    fn test_table_next_cell_wraps_and_skips_merged() {
+        let table_manager = table_manager_with_table(3, 3);
+        assert!(table_manager.merge_table_cells("table-1", 1, 0, 1, 1));
+
+        // Tab from the last cell of a row goes to the first cell of the next row
+        assert_eq!(table_manager.table_next_cell("table-1", 0, 2, "tab"), r#"{"col":0,"row":1}"#);
+        // Moving right out of the merged cell skips its placeholder
+        assert_eq!(table_manager.table_next_cell("table-1", 1, 0, "right"), r#"{"col":2,"row":1}"#);
+        assert_eq!(table_manager.table_next_cell("table-1", 1, 2, "left"), r#"{"col":0,"row":1}"#);
+        // Moving up into the placeholder lands on its merged cell
+        assert_eq!(table_manager.table_next_cell("table-1", 2, 1, "up"), r#"{"col":0,"row":1}"#);
+
+        assert_eq!(table_manager.table_next_cell("table-1", 2, 2, "tab"), "null");
+        assert_eq!(table_manager.table_next_cell("table-1", 0, 0, "up"), "null");
+        assert_eq!(table_manager.table_next_cell("table-1", 0, 0, "diagonal"), "null");
+    }
+
+    #[test]
+    fn test_table_next_cell_enters_row_span_at_main_cell() {
+        let table_manager = table_manager_with_table(4, 3);
+        assert!(table_manager.merge_table_cells("table-1", 1, 0, 2, 1));
+
+        assert_eq!(table_manager.table_next_cell("table-1", 3, 1, "up"), r#"{"col":0,"row":1}"#);
+        assert_eq!(table_manager.table_next_cell("table-1", 0, 1, "down"), r#"{"col":0,"row":1}"#);
+        // Leaving the merged cell starts past its last row
+        assert_eq!(table_manager.table_next_cell("table-1", 1, 0, "down"), r#"{"col":0,"row":3}"#);
+        assert_eq!(table_manager.table_next_cell("table-1", 1, 0, "up"), r#"{"col":0,"row":0}"#);
+        assert_eq!(table_manager.table_next_cell("table-1", 3, 2, "up"), r#"{"col":2,"row":2}"#);
+    }
+
+    #[test]
+    fn test_cell_style_ignores_invalid_colors() {
+        let table_manager = table_manager_with_table(3, 3);
+        assert!(table_manager.update_table_cell_style("table-1", 1, 1, r#"{"color":"red","backgroundColor":"rgba(0,0,0,0.5)"}"#));
+        assert!(table_manager.update_table_cell_style("table-1", 1, 1, r##"{"color":"reddish","backgroundColor":"#12"}"##));
+
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows[1].cells[1].style.color, "red");
+        assert_eq!(data.rows[1].cells[1].style.background_color, "rgba(0,0,0,0.5)");
+    }
+
+    #[test]
+    fn test_totals_row_recalculates_on_cell_update() {
+        let table_manager = table_manager_with_table(3, 2);
+        table_manager.update_table_cell("table-1", 1, 0, "10");
+        table_manager.update_table_cell("table-1", 2, 0, "5");
+        assert!(table_manager.set_table_totals("table-1", true, false));
+
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows.len(), 4);
+        assert_eq!(data.rows[3].cells[0].content, "15");
+        assert!(data.rows[3].cells.iter().all(|cell| cell.computed));
+
+        assert!(table_manager.update_table_cell("table-1", 2, 0, "20"));
+        assert!(!table_manager.update_table_cell("table-1", 3, 0, "99"));
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows[3].cells[0].content, "30");
+
+        // New rows go above the totals row
+        assert!(table_manager.add_table_row("table-1", None));
+        let data = table_data(&table_manager);
+        assert!(data.rows[4].cells[0].computed);
+        assert!(!data.rows[3].cells[0].computed);
+
+        assert!(table_manager.set_table_totals("table-1", false, false));
+        let data = table_data(&table_manager);
+        assert_eq!(data.rows.len(), 4);
+        assert!(data.rows.iter().flat_map(|row| &row.cells).all(|cell| !cell.computed));
+    }
+}
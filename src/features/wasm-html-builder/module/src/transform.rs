@@ -1,4 +1,26 @@
+use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
+use crate::types::Bounds;
+use crate::utils;
+
+/// Zoom and pan of the canvas view, saved with the document
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransformState {
+    pub zoom: f64,
+    pub pan_x: f64,
+    pub pan_y: f64,
+}
+
+impl Default for TransformState {
+    fn default() -> Self {
+        TransformState { zoom: 1.0, pan_x: 0.0, pan_y: 0.0 }
+    }
+}
+
+/// Smallest zoom step: -100% would collapse the zoom to zero before clamping
+const MIN_ZOOM_DELTA: f64 = -0.9;
+/// Largest zoom step, enough to go from the minimum (0.1) to the maximum (5.0) zoom at once
+const MAX_ZOOM_DELTA: f64 = 49.0;
 
 /// Transform management module for zoom and pan operations
 pub struct TransformManager {
@@ -18,6 +40,11 @@ impl TransformManager {
 
     /// ตั้งค่า transform
     pub fn set_transform(&self, zoom: f64, pan_x: f64, pan_y: f64) -> String {
+        // Non-finite input leaves the transform unchanged
+        if !utils::all_finite(&[zoom, pan_x, pan_y]) {
+            return self.get_transform_state();
+        }
+
         // Clamp zoom to reasonable values
         let clamped_zoom = zoom.max(0.1).min(5.0);
         
@@ -35,8 +62,12 @@ impl TransformManager {
         format!(r#"{{"zoom":{},"pan_x":{},"pan_y":{}}}"#, clamped_zoom, pan_x, pan_y)
     }
 
-    /// Zoom ไปยังจุดที่กำหนด
-    pub fn zoom_to_point(&self, screen_x: f64, screen_y: f64, zoom_delta: f64) -> f64 {
+    /// Zoom ไปยังจุดที่กำหนด คืน transform ใหม่ทั้งหมด (zoom และ pan)
+    pub fn zoom_to_point(&self, screen_x: f64, screen_y: f64, zoom_delta: f64) -> String {
+        if !utils::all_finite(&[screen_x, screen_y, zoom_delta]) {
+            return self.get_transform_state();
+        }
+
         let current_zoom = if let Ok(zoom_mutex) = self.zoom.lock() {
             *zoom_mutex
         } else {
@@ -55,7 +86,8 @@ impl TransformManager {
             0.0
         };
         
-        // Calculate new zoom
+        // Calculate new zoom from a bounded step, so it never reaches zero or flips sign
+        let zoom_delta = zoom_delta.clamp(MIN_ZOOM_DELTA, MAX_ZOOM_DELTA);
         let new_zoom = (current_zoom * (1.0 + zoom_delta)).max(0.1).min(5.0);
         
         // Calculate new pan to zoom towards the point
@@ -74,7 +106,29 @@ impl TransformManager {
             *pan_y_mutex = new_pan_y;
         }
         
-        new_zoom
+        format!(r#"{{"zoom":{},"pan_x":{},"pan_y":{}}}"#, new_zoom, new_pan_x, new_pan_y)
+    }
+
+    /// ตั้ง zoom และ pan ให้สี่เหลี่ยม (พิกัด canvas) อยู่กึ่งกลาง viewport และพอดีภายใน padding คืน transform ใหม่
+    pub fn fit_rect(&self, rect: Bounds, viewport_width: f64, viewport_height: f64, padding: f64) -> String {
+        let Bounds { x, y, width, height } = rect;
+        if !utils::all_finite(&[x, y, width, height, viewport_width, viewport_height, padding])
+            || viewport_width <= 0.0
+            || viewport_height <= 0.0
+            || padding < 0.0
+        {
+            return self.get_transform_state();
+        }
+
+        // Padding larger than the viewport leaves at least one pixel to fit into
+        let available_width = (viewport_width - 2.0 * padding).max(1.0);
+        let available_height = (viewport_height - 2.0 * padding).max(1.0);
+        let fit_zoom = |available: f64, size: f64| if size > 0.0 { available / size } else { f64::INFINITY };
+        let zoom = fit_zoom(available_width, width).min(fit_zoom(available_height, height)).clamp(0.1, 5.0);
+
+        let pan_x = viewport_width / 2.0 - (x + width / 2.0) * zoom;
+        let pan_y = viewport_height / 2.0 - (y + height / 2.0) * zoom;
+        self.set_transform(zoom, pan_x, pan_y)
     }
 
     /// ได้ค่า zoom ปัจจุบัน
@@ -88,6 +142,9 @@ impl TransformManager {
 
     /// ตั้งค่า zoom
     pub fn set_zoom(&self, zoom: f64) -> f64 {
+        if !zoom.is_finite() {
+            return self.get_zoom();
+        }
         let clamped_zoom = zoom.max(0.1).min(5.0);
         if let Ok(mut zoom_mutex) = self.zoom.lock() {
             *zoom_mutex = clamped_zoom;
@@ -115,6 +172,9 @@ impl TransformManager {
 
     /// ตั้งค่า pan
     pub fn set_pan(&self, pan_x: f64, pan_y: f64) {
+        if !utils::all_finite(&[pan_x, pan_y]) {
+            return;
+        }
         if let Ok(mut pan_x_mutex) = self.pan_x.lock() {
             *pan_x_mutex = pan_x;
         }
@@ -125,11 +185,26 @@ impl TransformManager {
 
     /// ได้ transform state ทั้งหมด
     pub fn get_transform_state(&self) -> String {
-        let zoom = self.get_zoom();
-        let pan_x = self.get_pan_x();
-        let pan_y = self.get_pan_y();
-        
-        format!(r#"{{"zoom":{},"pan_x":{},"pan_y":{}}}"#, zoom, pan_x, pan_y)
+        let state = self.state();
+        format!(r#"{{"zoom":{},"pan_x":{},"pan_y":{}}}"#, state.zoom, state.pan_x, state.pan_y)
+    }
+
+    /// Current zoom and pan
+    pub fn state(&self) -> TransformState {
+        TransformState {
+            zoom: self.get_zoom(),
+            pan_x: self.get_pan_x(),
+            pan_y: self.get_pan_y(),
+        }
+    }
+
+    /// Restore a saved zoom and pan (zoom is clamped, non-finite values are rejected)
+    pub fn restore(&self, state: TransformState) -> bool {
+        if !utils::all_finite(&[state.zoom, state.pan_x, state.pan_y]) {
+            return false;
+        }
+        self.set_transform(state.zoom, state.pan_x, state.pan_y);
+        true
     }
 
     /// Reset transform to default values
@@ -169,3 +244,41 @@ impl TransformManager {
         (transformed_x, transformed_y)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_finite_input_leaves_transform_unchanged() {
+        let transform_manager = TransformManager::new();
+        transform_manager.set_transform(2.0, 10.0, 20.0);
+
+        transform_manager.set_transform(f64::NAN, 0.0, 0.0);
+        assert_eq!(transform_manager.set_zoom(f64::INFINITY), 2.0);
+        assert_eq!(transform_manager.zoom_to_point(f64::NAN, 0.0, 0.5), r#"{"zoom":2,"pan_x":10,"pan_y":20}"#);
+        transform_manager.set_pan(f64::NEG_INFINITY, 0.0);
+
+        assert_eq!(transform_manager.get_zoom(), 2.0);
+        assert_eq!((transform_manager.get_pan_x(), transform_manager.get_pan_y()), (10.0, 20.0));
+    }
+
+    #[test]
+    fn test_zoom_to_point_clamps_extreme_deltas() {
+        let transform_manager = TransformManager::new();
+        transform_manager.set_transform(1.0, 100.0, 50.0);
+
+        // -100% is capped at -90%, then clamped to the minimum zoom
+        let state: serde_json::Value = serde_json::from_str(&transform_manager.zoom_to_point(400.0, 300.0, -1.0)).unwrap();
+        assert_eq!(state["zoom"], 0.1);
+        assert!((state["pan_x"].as_f64().unwrap() - 370.0).abs() < 1e-9);
+        assert!((state["pan_y"].as_f64().unwrap() - 275.0).abs() < 1e-9);
+
+        // A huge step lands on the maximum zoom, with pan scaled by the clamped ratio
+        transform_manager.set_transform(1.0, 100.0, 50.0);
+        let state: serde_json::Value = serde_json::from_str(&transform_manager.zoom_to_point(400.0, 300.0, 100.0)).unwrap();
+        assert_eq!(state["zoom"], 5.0);
+        assert_eq!((state["pan_x"].as_f64(), state["pan_y"].as_f64()), (Some(-1100.0), Some(-950.0)));
+        assert_eq!(transform_manager.state(), TransformState { zoom: 5.0, pan_x: -1100.0, pan_y: -950.0 });
+    }
+}
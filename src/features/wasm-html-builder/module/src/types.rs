@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
+use crate::utils;
 
 /// Table cell structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +11,12 @@ pub struct TableCell {
     pub row_span: usize,
     pub col_span: usize,
     pub style: ElementStyle,
+    // Totals cells are recalculated from the table and cannot be edited
+    #[serde(default)]
+    pub computed: bool,
+    // Element rendered inside the cell in place of its text, e.g. a checkbox in a form layout
+    #[serde(default)]
+    pub element_id: Option<String>,
 }
 
 impl Default for TableCell {
@@ -20,6 +27,8 @@ impl Default for TableCell {
             row_span: 1,
             col_span: 1,
             style: ElementStyle::default(),
+            computed: false,
+            element_id: None,
         }
     }
 }
@@ -54,6 +63,95 @@ pub struct TableData {
     pub column_widths: Vec<f64>,
     pub border_collapse: bool,
     pub table_style: ElementStyle,
+    // Defaults for newly added rows/columns, also used as export fallbacks
+    #[serde(default = "default_cell_padding")]
+    pub default_cell_padding: f64,
+    #[serde(default = "default_row_height")]
+    pub default_row_height: f64,
+    #[serde(default = "default_column_width")]
+    pub default_column_width: f64,
+    // Last row/column holds computed sums
+    #[serde(default)]
+    pub totals_row: bool,
+    #[serde(default)]
+    pub totals_col: bool,
+    // Column numbering data rows from sequence_start, renumbered when rows change
+    #[serde(default)]
+    pub sequence_col: Option<usize>,
+    #[serde(default)]
+    pub sequence_start: i32,
+    // Separators numeric body cells are shown with in export; cell content stays a plain number
+    #[serde(default)]
+    pub number_format: Option<NumberFormat>,
+}
+
+/// Locale number format, e.g. `1,234.50` (US) or `1.234,50` (European)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NumberFormat {
+    pub thousands_separator: String,
+    pub decimal_separator: String,
+    pub decimals: Option<usize>, // None keeps the value's own digits
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            thousands_separator: String::new(),
+            decimal_separator: ".".to_string(),
+            decimals: None,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Format a value with this format's separators; negative zero prints without a sign
+    pub fn format(&self, value: f64) -> String {
+        let fixed = match self.decimals {
+            Some(decimals) => format!("{:.*}", decimals, value),
+            None => value.to_string(),
+        };
+        let (sign, digits) = match fixed.strip_prefix('-') {
+            Some(unsigned) if unsigned.chars().any(|c| c.is_ascii_digit() && c != '0') => ("-", unsigned),
+            Some(unsigned) => ("", unsigned),
+            None => ("", fixed.as_str()),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+
+        let mut formatted = sign.to_string();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                formatted.push_str(&self.thousands_separator);
+            }
+            formatted.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            formatted.push_str(&self.decimal_separator);
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+
+    /// Formatted text of numeric cell content, or `None` when the content is not a plain number
+    pub fn format_content(&self, content: &str) -> Option<String> {
+        let value = content.trim().parse::<f64>().ok().filter(|value| value.is_finite())?;
+        Some(self.format(value))
+    }
+}
+
+fn default_cell_padding() -> f64 {
+    8.0
+}
+
+fn default_row_height() -> f64 {
+    20.0 // Excel-like row height
+}
+
+fn default_column_width() -> f64 {
+    64.0 // Excel-like column width
 }
 
 impl Default for TableData {
@@ -66,10 +164,82 @@ impl Default for TableData {
             column_widths: vec![150.0; 3],
             border_collapse: true,
             table_style: ElementStyle::default(),
+            default_cell_padding: default_cell_padding(),
+            default_row_height: default_row_height(),
+            default_column_width: default_column_width(),
+            totals_row: false,
+            totals_col: false,
+            sequence_col: None,
+            sequence_start: 1,
+            number_format: None,
         }
     }
 }
 
+impl TableData {
+    /// Text a cell shows: numeric body cells use the table's number format,
+    /// header rows and the sequence column are shown as stored
+    pub fn display_content<'a>(&self, row_index: usize, col_index: usize, cell: &'a TableCell) -> std::borrow::Cow<'a, str> {
+        let formatted = self
+            .number_format
+            .as_ref()
+            .filter(|_| row_index >= self.header_rows && self.sequence_col != Some(col_index))
+            .and_then(|number_format| number_format.format_content(&cell.content));
+        match formatted {
+            Some(formatted) => std::borrow::Cow::Owned(formatted),
+            None => std::borrow::Cow::Borrowed(&cell.content),
+        }
+    }
+
+    /// Empty cell using the table's default padding
+    pub fn new_cell(&self) -> TableCell {
+        let mut cell = TableCell::default();
+        cell.style.padding = self.default_cell_padding;
+        cell
+    }
+
+    /// Column width for export, falling back to the table default
+    pub fn column_width(&self, col_index: usize) -> f64 {
+        self.column_widths.get(col_index).copied().unwrap_or(self.default_column_width)
+    }
+
+    /// Rows that user data can be inserted before (the totals row stays last)
+    fn data_row_count(&self) -> usize {
+        self.rows.len() - usize::from(self.totals_row && !self.rows.is_empty())
+    }
+
+    /// Columns that user data can be inserted before (the totals column stays last)
+    fn data_column_count(&self) -> usize {
+        self.column_widths.len() - usize::from(self.totals_col && !self.column_widths.is_empty())
+    }
+
+    /// Whether a merged range spans any row (`vertical`) or column in `first..=last`;
+    /// moving rows/columns through such a range would tear it apart
+    fn merge_overlaps(&self, first: usize, last: usize, vertical: bool) -> bool {
+        self.rows.iter().enumerate().any(|(row_index, row)| {
+            row.cells.iter().enumerate().any(|(col_index, cell)| {
+                let (start, span) = if vertical { (row_index, cell.row_span) } else { (col_index, cell.col_span) };
+                span > 1 && start <= last && start + span > first
+            })
+        })
+    }
+}
+
+/// Cell content and style captured by a range copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRangeCell {
+    pub content: String,
+    pub style: ElementStyle,
+}
+
+/// Block of cells copied from a table range (row-major)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRangeBlock {
+    pub rows: Vec<Vec<TableRangeCell>>,
+}
+
 /// Fill style for shapes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -99,6 +269,10 @@ pub struct StrokeStyle {
     pub position: String, // "center", "inside", "outside"
     pub style: String,    // "solid", "dashed", "dotted"
     pub enabled: bool,
+    #[serde(default)]
+    pub line_cap: Option<String>, // SVG stroke-linecap, one of LINE_CAPS
+    #[serde(default)]
+    pub line_join: Option<String>, // SVG stroke-linejoin, one of LINE_JOINS
 }
 
 impl Default for StrokeStyle {
@@ -110,10 +284,18 @@ impl Default for StrokeStyle {
             position: "center".to_string(),
             style: "solid".to_string(),
             enabled: true,
+            line_cap: None,
+            line_join: None,
         }
     }
 }
 
+/// Supported SVG `stroke-linecap` values
+pub const LINE_CAPS: &[&str] = &["butt", "round", "square"];
+
+/// Supported SVG `stroke-linejoin` values
+pub const LINE_JOINS: &[&str] = &["miter", "round", "bevel"];
+
 /// Element style structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -132,6 +314,86 @@ pub struct ElementStyle {
     // Shape-specific styles
     pub fill: FillStyle,
     pub stroke: StrokeStyle,
+    #[serde(default)]
+    pub blend_mode: Option<String>, // CSS mix-blend-mode, one of BLEND_MODES
+    #[serde(default)]
+    pub vertical_align: Option<String>, // Table cells only, one of VERTICAL_ALIGNS
+    #[serde(default)]
+    pub direction: Option<String>, // Text direction, one of TEXT_DIRECTIONS
+    #[serde(default)]
+    pub line_height: Option<f64>, // Multiple of the font size; unset keeps the browser default
+    #[serde(default)]
+    pub letter_spacing: Option<f64>, // Extra space between letters, in pixels
+    #[serde(default)]
+    pub overflow: Option<String>, // Content past the element box, one of OVERFLOW_MODES
+}
+
+/// Supported CSS `mix-blend-mode` values
+pub const BLEND_MODES: &[&str] = &[
+    "normal", "multiply", "screen", "overlay", "darken", "lighten", "color-dodge", "color-burn",
+    "hard-light", "soft-light", "difference", "exclusion", "hue", "saturation", "color", "luminosity",
+];
+
+pub fn is_valid_blend_mode(mode: &str) -> bool {
+    BLEND_MODES.contains(&mode)
+}
+
+/// Supported table cell `vertical-align` values
+pub const VERTICAL_ALIGNS: &[&str] = &["top", "middle", "bottom"];
+
+/// Supported text `direction` values
+pub const TEXT_DIRECTIONS: &[&str] = &["ltr", "rtl"];
+
+/// Supported overflow modes; `"ellipsis"` clips to one line ending in "…"
+pub const OVERFLOW_MODES: &[&str] = &["visible", "hidden", "ellipsis"];
+
+/// Cell colors and border of a whole-table theme
+struct TableTheme {
+    header_background: &'static str,
+    header_color: &'static str,
+    body_background: &'static str,
+    stripe_background: &'static str, // Every second data row
+    border_width: f64,
+    border_color: &'static str,
+}
+
+fn table_theme(theme: &str) -> Option<TableTheme> {
+    let theme = match theme {
+        "striped" => TableTheme {
+            header_background: "#f3f4f6",
+            header_color: "#000000",
+            body_background: "#ffffff",
+            stripe_background: "#f9fafb",
+            border_width: 1.0,
+            border_color: "#e5e7eb",
+        },
+        "bordered" => TableTheme {
+            header_background: "#f3f4f6",
+            header_color: "#000000",
+            body_background: "#ffffff",
+            stripe_background: "#ffffff",
+            border_width: 1.0,
+            border_color: "#374151",
+        },
+        "minimal" => TableTheme {
+            header_background: "transparent",
+            header_color: "#000000",
+            body_background: "transparent",
+            stripe_background: "transparent",
+            border_width: 0.0,
+            border_color: "#cccccc",
+        },
+        "header-dark" => TableTheme {
+            header_background: "#1f2937",
+            header_color: "#ffffff",
+            body_background: "#ffffff",
+            stripe_background: "#ffffff",
+            border_width: 1.0,
+            border_color: "#d1d5db",
+        },
+        _ => return None,
+    };
+    Some(theme)
 }
 
 impl Default for ElementStyle {
@@ -150,10 +412,59 @@ impl Default for ElementStyle {
             border_color: "#cccccc".to_string(),
             fill: FillStyle::default(),
             stroke: StrokeStyle::default(),
+            blend_mode: None,
+            vertical_align: None,
+            direction: None,
+            line_height: None,
+            letter_spacing: None,
+            overflow: None,
         }
     }
 }
 
+fn default_content(element_type: &str) -> &'static str {
+    match element_type {
+        "heading" => "Heading Text",
+        "paragraph" => "This is a paragraph text. Click to edit.",
+        "button" => "Click Me",
+        "image" => "Image",
+        "table" => "Table",
+        "form_field" => r#"{"label":"Label:","value":"","labelWidth":30,"valueWidth":70,"gap":8,"showLabel":true,"underlineStyle":"solid"}"#,
+        "checkbox" => r#"{"label":"Checkbox","checked":true,"showLabel":true,"labelPosition":"right","checkboxStyle":"square","boxSize":15,"fontSize":12,"labelGap":4}"#,
+        "rectangle" => "Rectangle",
+        "circle" => "Circle",
+        "qrcode" => "https://example.com",
+        "barcode" => "123456789012",
+        "chart" => r#"{"sourceTableId":"","column":1,"kind":"bar"}"#,
+        "line" => r#"{"lineType":"straight","startX":0,"startY":0,"endX":100,"endY":0,"arrowStart":false,"arrowEnd":false}"#,
+        _ => "Text Content",
+    }
+}
+
+fn default_size(element_type: &str) -> (f64, f64) {
+    match element_type {
+        "heading" => (300.0, 60.0),
+        "paragraph" => (400.0, 100.0),
+        "button" => (120.0, 40.0),
+        "image" => (200.0, 150.0),
+        "table" => (450.0, 200.0), // Default table size
+        "form_field" => (400.0, 40.0), // Default form field size
+        "checkbox" => (150.0, 30.0), // Default checkbox size
+        "rectangle" => (150.0, 100.0), // Default rectangle size
+        "circle" => (120.0, 120.0), // Default circle size (square for perfect circle)
+        "qrcode" => (120.0, 120.0), // QR codes are square
+        "barcode" => (240.0, 80.0),
+        "chart" => (300.0, 200.0),
+        "line" => (200.0, 2.0), // Default line size (width x height)
+        _ => (200.0, 50.0),
+    }
+}
+
+/// Circles and QR codes keep proportions by default
+fn default_lock_aspect(element_type: &str) -> bool {
+    matches!(element_type, "circle" | "qrcode")
+}
+
 /// Core element structure for WASM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -170,37 +481,49 @@ pub struct Element {
     pub content: String,
     pub style: ElementStyle,
     pub table_data: Option<TableData>, // For table elements
+    #[serde(default)]
+    pub slot_key: Option<String>, // Template slot this element fills
+    #[serde(default)]
+    pub lock_aspect: bool, // Keep proportions when resized
+    #[serde(default)]
+    pub tab_index: Option<i32>, // Keyboard focus order of interactive elements in export
+    // Reflow anchors: follow the paper's right/bottom edge or center when the paper is resized
+    #[serde(default)]
+    pub anchor_right: bool,
+    #[serde(default)]
+    pub anchor_bottom: bool,
+    #[serde(default)]
+    pub anchor_center: bool,
+    #[serde(default)]
+    pub created_at: f64, // ms timestamp set when created through the engine, 0 if unknown
+    #[serde(default)]
+    pub relative_layout: Option<RelativeLayout>, // Position/size as percentages of a paper
+    // Print page breaks around the element in flowing documents, independent of paper pagination
+    #[serde(default)]
+    pub page_break_before: bool,
+    #[serde(default)]
+    pub page_break_after: bool,
+    #[serde(default)]
+    pub page_break_inside_avoid: bool,
+    #[serde(default)]
+    pub linked_from: Option<String>, // Element whose content this one mirrors, copied on each source update
+}
+
+/// Element geometry as percentages (0-100) of a paper, reapplied when the paper is resized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelativeLayout {
+    pub paper_id: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
 }
 
 impl Element {
     pub fn new(id: String, component_id: String, element_type: String) -> Element {
-        let default_content = match element_type.as_str() {
-            "heading" => "Heading Text".to_string(),
-            "paragraph" => "This is a paragraph text. Click to edit.".to_string(),
-            "button" => "Click Me".to_string(),
-            "image" => "Image".to_string(),
-            "table" => "Table".to_string(),
-            "form_field" => r#"{"label":"Label:","value":"","labelWidth":30,"valueWidth":70,"gap":8,"showLabel":true,"underlineStyle":"solid"}"#.to_string(),
-            "checkbox" => r#"{"label":"Checkbox","checked":true,"showLabel":true,"labelPosition":"right","checkboxStyle":"square","boxSize":15,"fontSize":12,"labelGap":4}"#.to_string(),
-            "rectangle" => "Rectangle".to_string(),
-            "circle" => "Circle".to_string(),
-            "line" => r#"{"lineType":"straight","startX":0,"startY":0,"endX":100,"endY":0,"arrowStart":false,"arrowEnd":false}"#.to_string(),
-            _ => "Text Content".to_string(),
-        };
-
-        let (width, height) = match element_type.as_str() {
-            "heading" => (300.0, 60.0),
-            "paragraph" => (400.0, 100.0),
-            "button" => (120.0, 40.0),
-            "image" => (200.0, 150.0),
-            "table" => (450.0, 200.0), // Default table size
-            "form_field" => (400.0, 40.0), // Default form field size
-            "checkbox" => (150.0, 30.0), // Default checkbox size
-            "rectangle" => (150.0, 100.0), // Default rectangle size
-            "circle" => (120.0, 120.0), // Default circle size (square for perfect circle)
-            "line" => (200.0, 2.0), // Default line size (width x height)
-            _ => (200.0, 50.0),
-        };
+        let default_content = default_content(&element_type).to_string();
+        let (width, height) = default_size(&element_type);
 
         // Create table data for table elements
         let table_data = if element_type == "table" {
@@ -208,6 +531,7 @@ impl Element {
         } else {
             None
         };
+        let lock_aspect = default_lock_aspect(&element_type);
 
         Element {
             id,
@@ -222,6 +546,78 @@ impl Element {
             content: default_content,
             style: ElementStyle::default(),
             table_data,
+            slot_key: None,
+            lock_aspect,
+            tab_index: None,
+            anchor_right: false,
+            anchor_bottom: false,
+            anchor_center: false,
+            created_at: 0.0,
+            relative_layout: None,
+            page_break_before: false,
+            page_break_after: false,
+            page_break_inside_avoid: false,
+            linked_from: None,
+        }
+    }
+
+    /// Reinitialize as a fresh element of the given type, reusing the string buffers
+    pub fn reset(&mut self, id: &str, element_type: &str) {
+        fn assign(target: &mut String, value: &str) {
+            target.clear();
+            target.push_str(value);
+        }
+
+        assign(&mut self.id, id);
+        assign(&mut self.component_id, element_type);
+        assign(&mut self.element_type, element_type);
+        assign(&mut self.content, default_content(element_type));
+        let (width, height) = default_size(element_type);
+        self.x = 0.0;
+        self.y = 0.0;
+        self.width = width;
+        self.height = height;
+        self.z_index = 0;
+        self.visible = true;
+        self.style = ElementStyle::default();
+        self.table_data = if element_type == "table" { Some(TableData::default()) } else { None };
+        self.slot_key = None;
+        self.lock_aspect = default_lock_aspect(element_type);
+        self.tab_index = None;
+        self.anchor_right = false;
+        self.anchor_bottom = false;
+        self.anchor_center = false;
+        self.created_at = 0.0;
+        self.relative_layout = None;
+        self.page_break_before = false;
+        self.page_break_after = false;
+        self.page_break_inside_avoid = false;
+        self.linked_from = None;
+    }
+
+    /// Export classes for the element's print page breaks, matched by the `@media print` rules
+    pub fn page_break_classes(&self) -> Vec<&'static str> {
+        [
+            (self.page_break_before, "page-break-before"),
+            (self.page_break_after, "page-break-after"),
+            (self.page_break_inside_avoid, "page-break-inside-avoid"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, class)| enabled.then_some(class))
+        .collect()
+    }
+
+    /// Set position and size from the element's percentage layout and the paper's current dimensions
+    pub fn apply_relative_layout(&mut self, paper: &Paper) -> bool {
+        match &self.relative_layout {
+            Some(layout) if layout.paper_id == paper.id => {
+                self.x = paper.x + paper.width * layout.x / 100.0;
+                self.y = paper.y + paper.height * layout.y / 100.0;
+                self.width = paper.width * layout.width / 100.0;
+                self.height = paper.height * layout.height / 100.0;
+                true
+            }
+            _ => false,
         }
     }
 
@@ -233,6 +629,26 @@ impl Element {
         self.height = height.max(10.0); // Minimum height
     }
 
+    /// Resize, keeping the current aspect ratio when `lock_aspect` is set
+    pub fn set_size(&mut self, width: f64, height: f64) {
+        let (width, height) = if self.lock_aspect && self.width > 0.0 && self.height > 0.0 {
+            let aspect_ratio = utils::calculate_aspect_ratio(self.width, self.height);
+            // The dimension that changed most drives the resize; leaving the other
+            // unbounded makes constrain_to_aspect_ratio derive it from the driver
+            let width_change = (width / self.width - 1.0).abs();
+            let height_change = (height / self.height - 1.0).abs();
+            if width_change >= height_change {
+                utils::constrain_to_aspect_ratio(width, f64::MAX, aspect_ratio)
+            } else {
+                utils::constrain_to_aspect_ratio(f64::MAX, height, aspect_ratio)
+            }
+        } else {
+            (width, height)
+        };
+        self.set_width(width);
+        self.set_height(height);
+    }
+
     pub fn get_bounds(&self) -> Bounds {
         Bounds {
             x: self.x,
@@ -246,6 +662,23 @@ impl Element {
         x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
     }
 
+    /// Connector snap points: corners and edge midpoints, in canvas coordinates
+    pub fn anchor_points(&self) -> [(&'static str, Point); 8] {
+        let (left, top) = (self.x, self.y);
+        let (center_x, center_y) = (self.x + self.width / 2.0, self.y + self.height / 2.0);
+        let (right, bottom) = (self.x + self.width, self.y + self.height);
+        [
+            ("top-left", Point::new(left, top)),
+            ("top", Point::new(center_x, top)),
+            ("top-right", Point::new(right, top)),
+            ("left", Point::new(left, center_y)),
+            ("right", Point::new(right, center_y)),
+            ("bottom-left", Point::new(left, bottom)),
+            ("bottom", Point::new(center_x, bottom)),
+            ("bottom-right", Point::new(right, bottom)),
+        ]
+    }
+
     // Table-specific methods
     pub fn is_table(&self) -> bool {
         self.element_type == "table"
@@ -260,6 +693,16 @@ impl Element {
         self.element_type == "rectangle"
     }
 
+    /// Stable total order for listing and export: by z_index, then by id
+    pub fn stable_cmp(&self, other: &Element) -> std::cmp::Ordering {
+        self.z_index.cmp(&other.z_index).then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// Element types that take keyboard focus in exported HTML
+    pub fn is_interactive(&self) -> bool {
+        matches!(self.element_type.as_str(), "input" | "button" | "checkbox" | "form_field")
+    }
+
     pub fn is_circle(&self) -> bool {
         self.element_type == "circle"
     }
@@ -281,8 +724,8 @@ impl Element {
             
             let mut sum = 0.0;
             for row in &table_data.rows {
-                if col_index < row.cells.len() {
-                    if let Ok(value) = row.cells[col_index].content.parse::<f64>() {
+                if let Some(cell) = row.cells.get(col_index).filter(|cell| !cell.computed) {
+                    if let Ok(value) = cell.content.parse::<f64>() {
                         sum += value;
                     }
                 }
@@ -301,7 +744,9 @@ impl Element {
             
             let row = &table_data.rows[row_index];
             let mut sum = 0.0;
-            for cell in &row.cells {
+            // Row numbers are not data
+            let cells = row.cells.iter().enumerate().filter(|(col, _)| table_data.sequence_col != Some(*col));
+            for (_, cell) in cells.filter(|(_, cell)| !cell.computed) {
                 if let Ok(value) = cell.content.parse::<f64>() {
                     sum += value;
                 }
@@ -338,17 +783,16 @@ impl Element {
             for col_index in 0..table_data.column_widths.len() {
                 let mut max_width: f64 = 64.0; // Minimum width
                 
-                // Check header if exists
-                if col_index < table_data.rows[0].cells.len() {
-                    let header_content = &table_data.rows[0].cells[col_index].content;
-                    let header_width = (header_content.len() as f64 * 7.0).max(64.0); // More accurate character width
-                    max_width = max_width.max(header_width);
-                }
-                
-                // Check all cells in column
-                for row in &table_data.rows {
-                    if col_index < row.cells.len() {
-                        let cell_width = (row.cells[col_index].content.len() as f64 * 7.0).max(64.0); // More accurate character width
+                // Check all cells in column (header rows are measured as bold)
+                for (row_index, row) in table_data.rows.iter().enumerate() {
+                    if let Some(cell) = row.cells.get(col_index) {
+                        let font_weight = if row_index < table_data.header_rows { "bold" } else { cell.style.font_weight.as_str() };
+                        let cell_width = utils::estimate_text_width(
+                            &cell.content,
+                            cell.style.font_size,
+                            &cell.style.font_family,
+                            font_weight,
+                        );
                         max_width = max_width.max(cell_width);
                     }
                 }
@@ -380,6 +824,7 @@ impl Element {
                 border_collapse: true,
                 table_style: ElementStyle::default(),
                 rows: Vec::new(),
+                ..TableData::default()
             };
 
             // Create header row
@@ -409,29 +854,189 @@ impl Element {
         }
     }
 
+    pub fn set_table_defaults(&mut self, cell_padding: f64, row_height: f64, col_width: f64) -> bool {
+        let valid = cell_padding.is_finite() && row_height.is_finite() && col_width.is_finite()
+            && cell_padding >= 0.0 && row_height > 0.0 && col_width > 0.0;
+        if !valid {
+            return false;
+        }
+        if let Some(ref mut table_data) = self.table_data {
+            table_data.default_cell_padding = cell_padding;
+            table_data.default_row_height = row_height;
+            table_data.default_column_width = col_width;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Show or hide the computed totals row (column sums) and totals column (row sums)
+    pub fn set_table_totals(&mut self, totals_row: bool, totals_col: bool) -> bool {
+        let Some(table_data) = self.table_data.as_mut() else {
+            return false;
+        };
+
+        if table_data.totals_col != totals_col {
+            if totals_col {
+                table_data.totals_col = false;
+                self.add_table_column(None);
+            } else {
+                let last = table_data.column_widths.len().saturating_sub(1);
+                if !self.remove_table_column(last) {
+                    return false;
+                }
+            }
+        }
+        let Some(table_data) = self.table_data.as_mut() else {
+            return false;
+        };
+        table_data.totals_col = totals_col;
+
+        if table_data.totals_row != totals_row {
+            if totals_row {
+                table_data.totals_row = false;
+                self.add_table_row(None);
+            } else {
+                let last = table_data.rows.len().saturating_sub(1);
+                if !self.remove_table_row(last) {
+                    return false;
+                }
+            }
+        }
+        if let Some(table_data) = self.table_data.as_mut() {
+            table_data.totals_row = totals_row;
+        }
+
+        self.refresh_table_totals();
+        true
+    }
+
+    /// Recalculate the totals row/column and mark their cells as computed
+    fn refresh_table_totals(&mut self) {
+        let Some(table_data) = self.table_data.as_ref() else {
+            return;
+        };
+        let (totals_row, totals_col) = (table_data.totals_row, table_data.totals_col);
+        if !totals_row && !totals_col {
+            return;
+        }
+        let row_count = table_data.rows.len();
+        let col_count = table_data.column_widths.len();
+        let header_rows = table_data.header_rows;
+        let sequence_col = table_data.sequence_col;
+
+        let row_sums: Vec<f64> = (0..row_count).map(|row| self.calculate_row_sum(row)).collect();
+        let col_sums: Vec<f64> = (0..col_count).map(|col| self.calculate_column_sum(col)).collect();
+        let data_col_count = col_count - usize::from(totals_col);
+        let grand_total: f64 = col_sums[..data_col_count]
+            .iter()
+            .enumerate()
+            .filter(|(col, _)| sequence_col != Some(*col))
+            .map(|(_, sum)| sum)
+            .sum();
+
+        let Some(table_data) = self.table_data.as_mut() else {
+            return;
+        };
+        for (row_index, row) in table_data.rows.iter_mut().enumerate() {
+            let is_totals_row = totals_row && row_index == row_count - 1;
+            for (col_index, cell) in row.cells.iter_mut().enumerate() {
+                let is_totals_col = totals_col && col_index == col_count - 1;
+                if !is_totals_row && !is_totals_col {
+                    cell.computed = false;
+                    continue;
+                }
+                if !cell.computed {
+                    cell.computed = true;
+                    cell.style.font_weight = "bold".to_string();
+                    cell.style.background_color = "#f3f4f6".to_string();
+                }
+                cell.content = if is_totals_row && is_totals_col {
+                    grand_total.to_string()
+                } else if is_totals_row && sequence_col == Some(col_index) {
+                    String::new()
+                } else if is_totals_row {
+                    col_sums[col_index].to_string()
+                } else if row_index < header_rows {
+                    "Total".to_string()
+                } else {
+                    row_sums[row_index].to_string()
+                };
+            }
+        }
+    }
+
+    /// Set or clear (`None`) the number format numeric cells are exported with
+    pub fn set_table_number_format(&mut self, number_format: Option<NumberFormat>) -> bool {
+        match self.table_data.as_mut() {
+            Some(table_data) => {
+                table_data.number_format = number_format;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert a column numbering the data rows start, start + 1, ... (at_index defaults to the first column);
+    /// a table has at most one sequence column
+    pub fn add_table_sequence_column(&mut self, start: i32, at_index: Option<usize>) -> bool {
+        if self.table_data.as_ref().is_none_or(|table_data| table_data.sequence_col.is_some()) {
+            return false;
+        }
+        let index = at_index.unwrap_or(0);
+        if !self.add_table_column(Some(index)) {
+            return false;
+        }
+        if let Some(table_data) = self.table_data.as_mut() {
+            table_data.sequence_col = Some(index);
+            table_data.sequence_start = start;
+            for row in table_data.rows.iter_mut().take(table_data.header_rows) {
+                if let Some(cell) = row.cells.get_mut(index) {
+                    cell.content = "#".to_string();
+                }
+            }
+        }
+        self.refresh_sequence_column();
+        self.refresh_table_totals();
+        true
+    }
+
+    /// Renumber the sequence column's data rows (header and totals rows are skipped)
+    fn refresh_sequence_column(&mut self) {
+        let Some(table_data) = self.table_data.as_mut() else {
+            return;
+        };
+        let Some(sequence_col) = table_data.sequence_col else {
+            return;
+        };
+        let data_end = table_data.data_row_count();
+        let data_rows = table_data.header_rows.min(data_end)..data_end;
+        let start = i64::from(table_data.sequence_start);
+        for (number, row) in (start..).zip(&mut table_data.rows[data_rows]) {
+            if let Some(cell) = row.cells.get_mut(sequence_col) {
+                cell.content = number.to_string();
+            }
+        }
+    }
+
     pub fn add_table_row(&mut self, at_index: Option<usize>) -> bool {
         if let Some(ref mut table_data) = self.table_data {
             let mut new_row = TableRow::default();
-            new_row.height = 20.0; // Set proper row height (Excel-like)
+            new_row.height = table_data.default_row_height;
             
             // Add cells for each column
             for _ in 0..table_data.columns {
-                new_row.cells.push(TableCell::default());
+                new_row.cells.push(table_data.new_cell());
             }
             
-            if let Some(index) = at_index {
-                if index <= table_data.rows.len() {
-                    table_data.rows.insert(index, new_row);
-                    // Update element height based on actual row heights
-                    let total_height: f64 = table_data.rows.iter().map(|row| row.height.max(20.0)).sum();
-                    self.height = (total_height + 32.0).max(self.height).max(100.0);
-                    return true;
-                }
-            } else {
-                table_data.rows.push(new_row);
+            let index = at_index.unwrap_or_else(|| table_data.data_row_count());
+            if index <= table_data.data_row_count() {
+                table_data.rows.insert(index, new_row);
                 // Update element height based on actual row heights
                 let total_height: f64 = table_data.rows.iter().map(|row| row.height.max(20.0)).sum();
                 self.height = (total_height + 32.0).max(self.height).max(100.0);
+                self.refresh_sequence_column();
+                self.refresh_table_totals();
                 return true;
             }
         }
@@ -441,10 +1046,18 @@ impl Element {
     pub fn remove_table_row(&mut self, index: usize) -> bool {
         if let Some(ref mut table_data) = self.table_data {
             if index < table_data.rows.len() && table_data.rows.len() > 1 {
+                if table_data.totals_row && index == table_data.rows.len() - 1 {
+                    table_data.totals_row = false;
+                }
+                if index < table_data.header_rows {
+                    table_data.header_rows -= 1;
+                }
                 table_data.rows.remove(index);
                 // Update element height based on actual row heights
                 let total_height: f64 = table_data.rows.iter().map(|row| row.height.max(20.0)).sum();
                 self.height = (total_height + 32.0).max(100.0);
+                self.refresh_sequence_column();
+                self.refresh_table_totals();
                 return true;
             }
         }
@@ -453,34 +1066,25 @@ impl Element {
 
     pub fn add_table_column(&mut self, at_index: Option<usize>) -> bool {
         if let Some(ref mut table_data) = self.table_data {
-            let new_width = 64.0; // Excel-like default column width
-            if let Some(index) = at_index {
-                if index <= table_data.column_widths.len() {
-                    table_data.column_widths.insert(index, new_width);
-                    table_data.columns += 1;
-                    
-                    // Add cell to each row
-                    for row in &mut table_data.rows {
-                        let new_cell = TableCell::default();
-                        row.cells.insert(index, new_cell);
-                    }
-                    // Update element width based on actual column widths
-                    let total_width: f64 = table_data.column_widths.iter().map(|w| w.max(64.0)).sum();
-                    self.width = (total_width + 32.0).max(self.width).max(200.0);
-                    return true;
-                }
-            } else {
-                table_data.column_widths.push(new_width);
+            let new_width = table_data.default_column_width;
+            let index = at_index.unwrap_or_else(|| table_data.data_column_count());
+            if index <= table_data.data_column_count() {
+                table_data.column_widths.insert(index, new_width);
                 table_data.columns += 1;
+                if let Some(sequence_col) = table_data.sequence_col.as_mut().filter(|sequence_col| index <= **sequence_col) {
+                    *sequence_col += 1;
+                }
                 
                 // Add cell to each row
+                let new_cell = table_data.new_cell();
                 for row in &mut table_data.rows {
-                    let new_cell = TableCell::default();
-                    row.cells.push(new_cell);
+                    let new_cell = new_cell.clone();
+                    row.cells.insert(index.min(row.cells.len()), new_cell);
                 }
                 // Update element width based on actual column widths
                 let total_width: f64 = table_data.column_widths.iter().map(|w| w.max(64.0)).sum();
                 self.width = (total_width + 32.0).max(self.width).max(200.0);
+                self.refresh_table_totals();
                 return true;
             }
         }
@@ -490,8 +1094,16 @@ impl Element {
     pub fn remove_table_column(&mut self, index: usize) -> bool {
         if let Some(ref mut table_data) = self.table_data {
             if index < table_data.column_widths.len() && table_data.columns > 1 {
+                if table_data.totals_col && index == table_data.column_widths.len() - 1 {
+                    table_data.totals_col = false;
+                }
                 table_data.column_widths.remove(index);
                 table_data.columns -= 1;
+                table_data.sequence_col = match table_data.sequence_col {
+                    Some(sequence_col) if sequence_col == index => None,
+                    Some(sequence_col) if index < sequence_col => Some(sequence_col - 1),
+                    sequence_col => sequence_col,
+                };
                 
                 // Remove cell from each row
                 for row in &mut table_data.rows {
@@ -502,20 +1114,107 @@ impl Element {
                 // Update element width based on actual column widths
                 let total_width: f64 = table_data.column_widths.iter().map(|w| w.max(64.0)).sum();
                 self.width = (total_width + 32.0).max(200.0);
+                self.refresh_table_totals();
                 return true;
             }
         }
         false
     }
 
+    /// Restyle every cell with a theme ("striped", "bordered", "minimal", "header-dark"): header rows bold on the theme's header band,
+    /// data rows on the body (or stripe) color, all cells with the theme's border; computed totals keep their look
+    pub fn apply_table_theme(&mut self, theme: &str) -> bool {
+        let (Some(theme), Some(table_data)) = (table_theme(theme), self.table_data.as_mut()) else {
+            return false;
+        };
+        let header_rows = table_data.header_rows;
+        for (row_index, row) in table_data.rows.iter_mut().enumerate() {
+            for cell in row.cells.iter_mut().filter(|cell| !cell.computed) {
+                let style = &mut cell.style;
+                if row_index < header_rows {
+                    style.background_color = theme.header_background.to_string();
+                    style.color = theme.header_color.to_string();
+                    style.font_weight = "bold".to_string();
+                } else {
+                    let striped = (row_index - header_rows) % 2 == 1;
+                    let background = if striped { theme.stripe_background } else { theme.body_background };
+                    style.background_color = background.to_string();
+                    style.color = "#000000".to_string();
+                }
+                style.border_width = theme.border_width;
+                style.border_color = theme.border_color.to_string();
+            }
+        }
+        true
+    }
+
+    /// Move a row (cells, styles and height) from one position to another; the totals row stays last
+    /// and rows inside or crossed by a vertical merge cannot move
+    pub fn move_table_row(&mut self, from: usize, to: usize) -> bool {
+        let Some(table_data) = self.table_data.as_mut() else {
+            return false;
+        };
+        let data_rows = table_data.data_row_count();
+        if from >= data_rows || to >= data_rows || table_data.merge_overlaps(from.min(to), from.max(to), true) {
+            return false;
+        }
+        let row = table_data.rows.remove(from);
+        table_data.rows.insert(to, row);
+        self.refresh_sequence_column();
+        self.refresh_table_totals();
+        true
+    }
+
+    /// Move a column (cells, styles and width) from one position to another; the totals column stays last
+    /// and columns inside or crossed by a horizontal merge cannot move
+    pub fn move_table_column(&mut self, from: usize, to: usize) -> bool {
+        let Some(table_data) = self.table_data.as_mut() else {
+            return false;
+        };
+        let data_columns = table_data.data_column_count();
+        if from >= data_columns || to >= data_columns || table_data.merge_overlaps(from.min(to), from.max(to), false) {
+            return false;
+        }
+        let width = table_data.column_widths.remove(from);
+        table_data.column_widths.insert(to, width);
+        for row in &mut table_data.rows {
+            if from < row.cells.len() && to < row.cells.len() {
+                let cell = row.cells.remove(from);
+                row.cells.insert(to, cell);
+            }
+        }
+        table_data.sequence_col = table_data.sequence_col.map(|sequence_col| {
+            if sequence_col == from {
+                to
+            } else if from < sequence_col && sequence_col <= to {
+                sequence_col - 1
+            } else if to <= sequence_col && sequence_col < from {
+                sequence_col + 1
+            } else {
+                sequence_col
+            }
+        });
+        self.refresh_table_totals();
+        true
+    }
+
     pub fn update_table_cell(&mut self, row: usize, col: usize, content: String) -> bool {
         if let Some(ref mut table_data) = self.table_data {
             if row < table_data.rows.len() && col < table_data.rows[row].cells.len() {
-                table_data.rows[row].cells[col].content = content.clone();
+                if table_data.rows[row].cells[col].computed {
+                    return false;
+                }
+                let cell = &mut table_data.rows[row].cells[col];
+                cell.content = content;
                 
                 // Auto-calculate cell width based on content (Excel-like behavior)
-                let content_length = content.len() as f64;
-                let estimated_width = (content_length * 8.0).max(64.0).min(300.0);
+                let estimated_width = utils::estimate_text_width(
+                    &cell.content,
+                    cell.style.font_size,
+                    &cell.style.font_family,
+                    &cell.style.font_weight,
+                )
+                .clamp(64.0, 300.0);
                 
                 if col < table_data.column_widths.len() {
                     let current_width = table_data.column_widths[col];
@@ -527,6 +1226,7 @@ impl Element {
                     }
                 }
                 
+                self.refresh_table_totals();
                 return true;
             }
         }
@@ -578,6 +1278,110 @@ impl Element {
         false
     }
 
+    /// Clear cell contents in a range (merged-cell placeholders are left untouched)
+    pub fn clear_table_range(&mut self, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> bool {
+        self.set_table_range_content(start_row, start_col, end_row, end_col, "")
+    }
+
+    /// Set every cell in a range to the same value (merged-cell placeholders are skipped)
+    pub fn fill_table_range(&mut self, start_row: usize, start_col: usize, end_row: usize, end_col: usize, value: &str) -> bool {
+        self.set_table_range_content(start_row, start_col, end_row, end_col, value)
+    }
+
+    fn set_table_range_content(&mut self, start_row: usize, start_col: usize, end_row: usize, end_col: usize, value: &str) -> bool {
+        if let Some(ref mut table_data) = self.table_data {
+            if start_row > end_row || start_col > end_col || end_row >= table_data.rows.len() {
+                return false;
+            }
+
+            for row in &mut table_data.rows[start_row..=end_row] {
+                if end_col >= row.cells.len() {
+                    return false;
+                }
+                for cell in &mut row.cells[start_col..=end_col] {
+                    // Placeholders covered by a merged cell must stay empty
+                    if (cell.row_span == 0 && cell.col_span == 0) || cell.computed {
+                        continue;
+                    }
+                    cell.content = value.to_string();
+                }
+            }
+            self.refresh_table_totals();
+            return true;
+        }
+        false
+    }
+
+    /// Copy contents and styles of a cell range
+    pub fn copy_table_range(&self, start_row: usize, start_col: usize, end_row: usize, end_col: usize) -> Option<TableRangeBlock> {
+        let table_data = self.table_data.as_ref()?;
+        if start_row > end_row || start_col > end_col || end_row >= table_data.rows.len() {
+            return None;
+        }
+
+        let mut rows = Vec::with_capacity(end_row - start_row + 1);
+        for row in &table_data.rows[start_row..=end_row] {
+            if end_col >= row.cells.len() {
+                return None;
+            }
+            rows.push(
+                row.cells[start_col..=end_col]
+                    .iter()
+                    .map(|cell| TableRangeCell {
+                        content: cell.content.clone(),
+                        style: cell.style.clone(),
+                    })
+                    .collect(),
+            );
+        }
+
+        Some(TableRangeBlock { rows })
+    }
+
+    /// Paste a copied block anchored at (target_row, target_col), growing the table if needed
+    pub fn paste_table_range(&mut self, target_row: usize, target_col: usize, block: &TableRangeBlock) -> bool {
+        if self.table_data.is_none() {
+            return false;
+        }
+
+        let block_rows = block.rows.len();
+        let block_cols = block.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if block_rows == 0 || block_cols == 0 {
+            return false;
+        }
+
+        // Grow the table so the whole block fits above the totals row and left of the totals column
+        while self.table_data.as_ref().map_or(0, |data| data.data_row_count()) < target_row + block_rows {
+            if !self.add_table_row(None) {
+                return false;
+            }
+        }
+        while self.table_data.as_ref().map_or(0, |data| data.data_column_count()) < target_col + block_cols {
+            if !self.add_table_column(None) {
+                return false;
+            }
+        }
+
+        if let Some(ref mut table_data) = self.table_data {
+            for (row_offset, block_row) in block.rows.iter().enumerate() {
+                let row = &mut table_data.rows[target_row + row_offset];
+                for (col_offset, block_cell) in block_row.iter().enumerate() {
+                    if let Some(cell) = row.cells.get_mut(target_col + col_offset) {
+                        // Placeholders covered by a merged cell must stay empty
+                        if (cell.row_span == 0 && cell.col_span == 0) || cell.computed {
+                            continue;
+                        }
+                        cell.content = block_cell.content.clone();
+                        cell.style = block_cell.style.clone();
+                    }
+                }
+            }
+            self.refresh_table_totals();
+            return true;
+        }
+        false
+    }
+
     /// Unmerge table cells (split merged cell back to individual cells)
     pub fn unmerge_table_cells(&mut self, row: usize, col: usize) -> bool {
         if let Some(ref mut table_data) = self.table_data {
@@ -621,6 +1425,53 @@ impl Element {
         false
     }
 
+    /// Next editable cell from (row, col) for keyboard navigation ("right", "left", "up", "down", "tab"),
+    /// skipping computed totals; "up"/"down" into a merged area land on its main cell, the other
+    /// directions skip placeholders. "tab" wraps to the start of the next row
+    pub fn next_table_cell(&self, row: usize, col: usize, direction: &str) -> Option<(usize, usize)> {
+        let table_data = self.table_data.as_ref()?;
+        let current = table_data.rows.get(row)?.cells.get(col)?;
+        let is_editable = |(r, c): (usize, usize)| {
+            table_data.rows.get(r).and_then(|row| row.cells.get(c)).is_some_and(|cell| {
+                (cell.row_span != 0 || cell.col_span != 0) && !cell.computed
+            })
+        };
+
+        // A merged cell is left from its far edge
+        let mut step = match direction {
+            "right" | "tab" => current.col_span.max(1),
+            "down" => current.row_span.max(1),
+            _ => 1,
+        };
+        let mut position = (row, col);
+        loop {
+            let (r, c) = position;
+            position = match direction {
+                "right" => (r, c + step),
+                "left" => (r, c.checked_sub(step)?),
+                "down" => (r + step, c),
+                "up" => (r.checked_sub(step)?, c),
+                "tab" if c + step < table_data.rows[r].cells.len() => (r, c + step),
+                "tab" => (r + 1, 0),
+                _ => return None,
+            };
+            step = 1;
+            let (r, c) = position;
+            if r >= table_data.rows.len() || c >= table_data.rows[r].cells.len() {
+                return None;
+            }
+            if is_editable(position) {
+                return Some(position);
+            }
+            if matches!(direction, "up" | "down") {
+                let main = self.get_main_cell_for_merged_area(r, c).filter(|&main| main != (row, col));
+                if let Some(main) = main.filter(|&main| is_editable(main)) {
+                    return Some(main);
+                }
+            }
+        }
+    }
+
     /// Get the main cell for a merged cell area
     pub fn get_main_cell_for_merged_area(&self, row: usize, col: usize) -> Option<(usize, usize)> {
         if let Some(ref table_data) = self.table_data {
@@ -710,6 +1561,18 @@ impl Point {
     }
 }
 
+/// Screen resolution that paper sizes have always been laid out at
+pub const DEFAULT_DPI: f64 = 96.0;
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// Unprintable strip most printers leave at each paper edge, in millimetres
+pub const DEFAULT_PRINTER_MARGIN_MM: f64 = 5.0;
+
+fn default_dpi() -> f64 {
+    DEFAULT_DPI
+}
+
 /// Paper size enum
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum PaperSize {
@@ -717,6 +1580,16 @@ pub enum PaperSize {
     A5,
 }
 
+impl PaperSize {
+    /// Physical (width, height) in millimetres, portrait
+    pub fn dimensions_mm(&self) -> (f64, f64) {
+        match self {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A5 => (148.0, 210.0),
+        }
+    }
+}
+
 /// Paper orientation enum
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum PaperOrientation {
@@ -735,12 +1608,19 @@ pub struct Paper {
     pub width: f64,
     pub height: f64,
     pub title: Option<String>,
+    #[serde(default = "default_dpi")]
+    pub dpi: f64,
 }
 
 impl Paper {
     /// Create a new paper with specified size and orientation
     pub fn new(id: String, size: PaperSize, orientation: PaperOrientation, x: f64, y: f64) -> Paper {
-        let (width, height) = Self::get_dimensions(size, orientation);
+        Self::new_with_dpi(id, size, orientation, x, y, DEFAULT_DPI)
+    }
+
+    /// Create a new paper laid out at the given resolution
+    pub fn new_with_dpi(id: String, size: PaperSize, orientation: PaperOrientation, x: f64, y: f64, dpi: f64) -> Paper {
+        let (width, height) = Self::get_dimensions(size, orientation, dpi);
         let title = Some(format!("{:?} {:?}", size, orientation));
         
         Paper {
@@ -752,27 +1632,28 @@ impl Paper {
             width,
             height,
             title,
+            dpi,
         }
     }
 
-    /// Get paper dimensions based on size and orientation
-    fn get_dimensions(size: PaperSize, orientation: PaperOrientation) -> (f64, f64) {
-        match size {
-            PaperSize::A4 => {
-                match orientation {
-                    PaperOrientation::Portrait => (794.0, 1123.0),   // A4 portrait at 96 DPI (standard web size)
-                    PaperOrientation::Landscape => (1123.0, 794.0),  // A4 landscape at 96 DPI (standard web size)
-                }
-            }
-            PaperSize::A5 => {
-                match orientation {
-                    PaperOrientation::Portrait => (559.0, 794.0),    // A5 portrait at 96 DPI (standard web size)
-                    PaperOrientation::Landscape => (794.0, 559.0),   // A5 landscape at 96 DPI (standard web size)
-                }
-            }
+    /// Get paper dimensions in pixels from the physical size at the given DPI
+    fn get_dimensions(size: PaperSize, orientation: PaperOrientation, dpi: f64) -> (f64, f64) {
+        let (width_mm, height_mm) = size.dimensions_mm();
+        // Whole pixels at 96 DPI (794x1123 for A4), scaled so layouts stay proportional
+        let to_px = |mm: f64| (mm / MM_PER_INCH * DEFAULT_DPI).round() * (dpi / DEFAULT_DPI);
+        let (width, height) = (to_px(width_mm), to_px(height_mm));
+        match orientation {
+            PaperOrientation::Portrait => (width, height),
+            PaperOrientation::Landscape => (height, width),
         }
     }
 
+    /// Area inside the printer margin (millimetres, converted at the paper's DPI) on every side
+    pub fn printable_area(&self, margin_mm: f64) -> Bounds {
+        let inset = (margin_mm / MM_PER_INCH * self.dpi).clamp(0.0, self.width.min(self.height) / 2.0);
+        Bounds::new(self.x + inset, self.y + inset, self.width - inset * 2.0, self.height - inset * 2.0)
+    }
+
     pub fn get_bounds(&self) -> Bounds {
         Bounds {
             x: self.x,
@@ -1,3 +1,4 @@
+use std::sync::{Mutex, MutexGuard};
 use crate::types::*;
 
 /// Utility functions for HTML Builder WASM module
@@ -5,16 +6,72 @@ use crate::types::*;
 /// Generate unique ID for elements
 pub fn generate_id() -> String {
     let timestamp = get_performance_now() as u64;
+    #[cfg(target_arch = "wasm32")]
     let random = (js_sys::Math::random() * 1000000.0) as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    let random = {
+        use std::hash::{BuildHasher, Hasher};
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(now_ms().to_bits());
+        hasher.finish() % 1000000
+    };
     format!("element-{}-{}", timestamp, random)
 }
 
-/// Get performance timestamp
+/// Lock a mutex, recovering the data if a previous holder panicked
+///
+/// A poisoned lock would otherwise make every later call panic and brick the engine.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log_warning("recovered from a poisoned lock");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Log a warning (browser console in WASM, stderr natively)
+pub fn log_warning(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::console::warn_1(&message.into());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        eprintln!("warning: {}", message);
+    }
+}
+
+/// Get wall-clock time in milliseconds (Date.now() in the browser, system clock natively)
+pub fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Get performance timestamp (performance.now() in the browser, process-relative clock natively)
 pub fn get_performance_now() -> f64 {
-    web_sys::window()
-        .and_then(|window| window.performance())
-        .map(|performance| performance.now())
-        .unwrap_or(0.0)
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map(|performance| performance.now())
+            .unwrap_or(0.0)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
 }
 
 /// Get default size for component types
@@ -36,6 +93,11 @@ pub fn get_default_size(component_type: &str) -> (f64, f64) {
     }
 }
 
+/// True when every value is a finite number (no NaN or infinity from a caller bug)
+pub fn all_finite(values: &[f64]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
 /// Clamp value between min and max
 pub fn clamp(value: f64, min: f64, max: f64) -> f64 {
     if value < min {
@@ -152,6 +214,154 @@ pub fn constrain_to_aspect_ratio(
     }
 }
 
+/// Line height used for text estimates, as a multiple of the font size
+const LINE_HEIGHT_FACTOR: f64 = 1.2;
+
+/// Average glyph width as a fraction of the font size, by font family
+fn char_width_factor(font_family: &str) -> f64 {
+    let family = font_family.to_ascii_lowercase();
+    if ["mono", "courier", "consolas"].iter().any(|name| family.contains(name)) {
+        0.6
+    } else if ["verdana", "tahoma", "segoe"].iter().any(|name| family.contains(name)) {
+        0.55
+    } else if ["times", "georgia", "garamond"].iter().any(|name| family.contains(name))
+        || (family.contains("serif") && !family.contains("sans"))
+    {
+        0.45
+    } else {
+        0.5 // Arial, Helvetica and other sans-serif fonts
+    }
+}
+
+/// Full-width scripts (CJK, Hangul) take about one em per glyph
+fn is_wide_char(c: char) -> bool {
+    matches!(c as u32, 0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFF00..=0xFF60)
+}
+
+/// Estimate the rendered width in pixels of the widest line of `text`
+pub fn estimate_text_width(text: &str, font_size: f64, font_family: &str, font_weight: &str) -> f64 {
+    let factor = char_width_factor(font_family);
+    // Bold glyphs are roughly 10% wider
+    let weight_factor = match font_weight {
+        "bold" | "bolder" | "600" | "700" | "800" | "900" => 1.1,
+        _ => 1.0,
+    };
+    text.lines()
+        .map(|line| {
+            line.chars()
+                .map(|c| if is_wide_char(c) { 1.0 } else { factor * weight_factor })
+                .sum::<f64>()
+                * font_size
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Estimate the rendered height in pixels of `text`, wrapping lines longer than `max_width` (when positive)
+pub fn estimate_text_height(text: &str, font_size: f64, font_family: &str, font_weight: &str, max_width: f64) -> f64 {
+    let line_count: f64 = text
+        .split('\n')
+        .map(|line| {
+            let width = estimate_text_width(line, font_size, font_family, font_weight);
+            if max_width > 0.0 && width > max_width {
+                (width / max_width).ceil()
+            } else {
+                1.0
+            }
+        })
+        .sum();
+    line_count * font_size * LINE_HEIGHT_FACTOR
+}
+
+/// Check for a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` hex color
+pub fn is_valid_hex_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+/// CSS named colors, plus `transparent` and `currentcolor`
+pub const CSS_NAMED_COLORS: [&str; 150] = [
+    "aliceblue", "antiquewhite", "aqua", "aquamarine", "azure", "beige", "bisque", "black",
+    "blanchedalmond", "blue", "blueviolet", "brown", "burlywood", "cadetblue", "chartreuse", "chocolate",
+    "coral", "cornflowerblue", "cornsilk", "crimson", "cyan", "darkblue", "darkcyan", "darkgoldenrod",
+    "darkgray", "darkgreen", "darkgrey", "darkkhaki", "darkmagenta", "darkolivegreen", "darkorange", "darkorchid",
+    "darkred", "darksalmon", "darkseagreen", "darkslateblue", "darkslategray", "darkslategrey", "darkturquoise", "darkviolet",
+    "deeppink", "deepskyblue", "dimgray", "dimgrey", "dodgerblue", "firebrick", "floralwhite", "forestgreen",
+    "fuchsia", "gainsboro", "ghostwhite", "gold", "goldenrod", "gray", "green", "greenyellow",
+    "grey", "honeydew", "hotpink", "indianred", "indigo", "ivory", "khaki", "lavender",
+    "lavenderblush", "lawngreen", "lemonchiffon", "lightblue", "lightcoral", "lightcyan", "lightgoldenrodyellow", "lightgray",
+    "lightgreen", "lightgrey", "lightpink", "lightsalmon", "lightseagreen", "lightskyblue", "lightslategray", "lightslategrey",
+    "lightsteelblue", "lightyellow", "lime", "limegreen", "linen", "magenta", "maroon", "mediumaquamarine",
+    "mediumblue", "mediumorchid", "mediumpurple", "mediumseagreen", "mediumslateblue", "mediumspringgreen", "mediumturquoise", "mediumvioletred",
+    "midnightblue", "mintcream", "mistyrose", "moccasin", "navajowhite", "navy", "oldlace", "olive",
+    "olivedrab", "orange", "orangered", "orchid", "palegoldenrod", "palegreen", "paleturquoise", "palevioletred",
+    "papayawhip", "peachpuff", "peru", "pink", "plum", "powderblue", "purple", "rebeccapurple",
+    "red", "rosybrown", "royalblue", "saddlebrown", "salmon", "sandybrown", "seagreen", "seashell",
+    "sienna", "silver", "skyblue", "slateblue", "slategray", "slategrey", "snow", "springgreen",
+    "steelblue", "tan", "teal", "thistle", "tomato", "turquoise", "violet", "wheat",
+    "white", "whitesmoke", "yellow", "yellowgreen", "transparent", "currentcolor",
+];
+
+/// Check for a CSS color: hex, `rgb()`/`rgba()`/`hsl()`/`hsla()` or a named color
+pub fn is_valid_css_color(color: &str) -> bool {
+    let color = color.trim().to_ascii_lowercase();
+    if color.starts_with('#') {
+        return is_valid_hex_color(&color);
+    }
+    let Some((function, rest)) = color.split_once('(') else {
+        return CSS_NAMED_COLORS.contains(&color.as_str());
+    };
+    let Some(arguments) = rest.strip_suffix(')') else {
+        return false;
+    };
+    if !matches!(function, "rgb" | "rgba" | "hsl" | "hsla") {
+        return false;
+    }
+    // Comma or space separated, with an optional `/ alpha`
+    let parts: Vec<&str> = arguments
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    matches!(parts.len(), 3 | 4)
+        && parts.iter().all(|part| {
+            let number = part.strip_suffix('%').or_else(|| part.strip_suffix("deg")).unwrap_or(part);
+            number.parse::<f64>().is_ok_and(f64::is_finite)
+        })
+}
+
+/// First hue of `generate_palette`, so the same `n` always yields the same colors
+const PALETTE_START_HUE: f64 = 210.0;
+
+/// `n` distinguishable `#rrggbb` colors with evenly spaced hues (for chart/pivot categories)
+pub fn generate_palette(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|index| {
+            let hue = (PALETTE_START_HUE + 360.0 * index as f64 / n as f64) % 360.0;
+            let (r, g, b) = hsl_to_rgb(hue, 0.65, 0.5);
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        })
+        .collect()
+}
+
+/// Convert HSL (hue in degrees, saturation and lightness 0-1) to RGB
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let secondary = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, secondary, 0.0),
+        1 => (secondary, chroma, 0.0),
+        2 => (0.0, chroma, secondary),
+        3 => (0.0, secondary, chroma),
+        4 => (secondary, 0.0, chroma),
+        _ => (chroma, 0.0, secondary),
+    };
+    let offset = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
 /// Generate color from string (for consistent element colors)
 pub fn string_to_color(s: &str) -> (u8, u8, u8) {
     let mut hash: u32 = 0;
@@ -205,11 +415,16 @@ pub struct Throttler {
 impl Throttler {
     pub fn new(interval_ms: f64) -> Self {
         Throttler {
-            last_execution_time: 0.0,
+            // The first call always executes
+            last_execution_time: f64::NEG_INFINITY,
             interval: interval_ms,
         }
     }
 
+    pub fn set_interval(&mut self, interval_ms: f64) {
+        self.interval = interval_ms;
+    }
+
     pub fn should_execute(&mut self) -> bool {
         let current_time = get_performance_now();
         if current_time - self.last_execution_time >= self.interval {
@@ -306,3 +521,47 @@ impl MemoryTracker {
         &self.allocations
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_color_validation() {
+        for color in ["#abc", "#abcd", "#a1b2c3", "#a1b2c3d4", "rgba(0,0,0,0.5)", "rgb(255 0 0 / 50%)", "hsl(120deg, 50%, 50%)", "red", "Transparent"] {
+            assert!(is_valid_css_color(color), "{}", color);
+        }
+        for color in ["", "#abcde", "#ggg", "rgba(0,0)", "rgb(a,b,c)", "rgb(0,0,0", "url(x)", "notacolor"] {
+            assert!(!is_valid_css_color(color), "{}", color);
+        }
+    }
+
+    #[test]
+    fn test_generate_palette_is_distinct_and_valid() {
+        let palette = generate_palette(5);
+        assert_eq!(palette.len(), 5);
+        assert!(palette.iter().all(|color| color.len() == 7 && is_valid_hex_color(color)));
+        let unique: std::collections::HashSet<&String> = palette.iter().collect();
+        assert_eq!(unique.len(), 5);
+
+        assert_eq!(generate_palette(5), palette);
+        assert!(generate_palette(0).is_empty());
+        assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), (255, 0, 0));
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), (0, 0, 255));
+    }
+
+    #[test]
+    fn test_estimate_text_width_scales_with_size_and_weight() {
+        let text = "Quarterly revenue";
+        let small = estimate_text_width(text, 10.0, "Arial, sans-serif", "normal");
+        let large = estimate_text_width(text, 20.0, "Arial, sans-serif", "normal");
+        assert!(large > small);
+        assert!(estimate_text_width(text, 10.0, "Arial, sans-serif", "bold") > small);
+        assert!(estimate_text_width(text, 10.0, "Courier New", "normal") > small);
+
+        // The widest line determines the width; each line adds height
+        assert_eq!(estimate_text_width("ab\nabcd", 10.0, "Arial", "normal"), estimate_text_width("abcd", 10.0, "Arial", "normal"));
+        assert_eq!(estimate_text_height("one\ntwo", 10.0, "Arial", "normal", 0.0), 24.0);
+        assert_eq!(estimate_text_height("abcdefgh", 10.0, "Arial", "normal", 20.0), 24.0);
+    }
+}
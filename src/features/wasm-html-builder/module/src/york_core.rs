@@ -0,0 +1,493 @@
+//! Native Rust API for the HTML builder engine
+//!
+//! The `#[wasm_bindgen]` methods on `HTMLBuilderEngine` exchange JSON strings with
+//! JavaScript. The methods here return the typed values instead, so the layout,
+//! table and spatial logic can be used from a native binary (e.g. a server-side renderer).
+
+pub use crate::export::{EmbeddedFont, ExportMetadata, ExportOptions, ExportResult, ExportWarning, PaperAssignMode};
+pub use crate::types::{
+    A4Paper, Bounds, CollisionResult, Element, ElementStyle, NumberFormat, Paper, PaperOrientation, PaperSize,
+    TableCell, TableData, TableRangeBlock, TableRangeCell, TableRow,
+};
+pub use crate::transform::TransformState;
+pub use crate::HTMLBuilderEngine;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+
+/// Version written by `export_document`
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// Saved document: papers, elements and the view they were last edited in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    pub papers: Vec<Paper>,
+    pub elements: Vec<Element>,
+    #[serde(default)]
+    pub transform: TransformState, // Documents saved without a view open at the default zoom/pan
+}
+
+/// Version written by `engine_snapshot`
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Editor settings that are not part of the document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorSettings {
+    pub size_snap: f64,
+    pub size_match_tolerance: f64,
+    pub drag_axis_lock: String, // "none", "x" or "y"
+    pub spatial_bounds: (f64, f64, f64, f64),
+    pub spatial_cell_size: f64,
+    #[serde(default = "default_printer_margin")]
+    pub printer_margin_mm: f64, // Snapshots written before the margin was saved use the default
+}
+
+fn default_printer_margin() -> f64 {
+    crate::types::DEFAULT_PRINTER_MARGIN_MM
+}
+
+/// Full engine state: the document, editor settings and style history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    pub version: u32,
+    pub document: Document,
+    pub settings: EditorSettings,
+    pub style_history: String, // As written by `export_style_history`
+}
+
+/// Rectangle in screen coordinates (after zoom and pan)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ScreenRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Element visible in the viewport, with its on-screen rectangle
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedElement {
+    pub element: Element,
+    pub screen: ScreenRect,
+}
+
+/// Paper visible in the viewport, with its on-screen rectangle
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedPaper {
+    pub paper: Paper,
+    pub screen: ScreenRect,
+}
+
+/// Everything needed to draw one frame
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderSnapshot {
+    pub zoom: f64,
+    pub papers: Vec<RenderedPaper>,
+    pub elements: Vec<RenderedElement>, // Back to front (z_index, then id)
+}
+
+impl HTMLBuilderEngine {
+    /// Create an element and return it
+    pub fn add_element(&self, component_type: &str, x: f64, y: f64) -> Element {
+        self.element_manager.create(component_type, x, y)
+    }
+
+    /// Create an element with a caller-chosen ID, or `None` if the ID is empty or taken
+    pub fn add_element_with_id(&self, element_id: &str, component_type: &str, x: f64, y: f64) -> Option<Element> {
+        self.element_manager.create_with_id(element_id, component_type, x, y)
+    }
+
+    /// Look up an element by ID
+    pub fn element(&self, element_id: &str) -> Option<Element> {
+        self.element_manager.find(element_id)
+    }
+
+    /// Snapshot of all elements, ordered by z_index then id
+    pub fn elements(&self) -> Vec<Element> {
+        self.element_manager.all_sorted()
+    }
+
+    /// Create a paper and return it
+    pub fn add_paper(&self, id: &str, size: &str, orientation: &str, x: f64, y: f64) -> Paper {
+        self.paper_manager.create(id, size, orientation, x, y)
+    }
+
+    /// Snapshot of all papers
+    pub fn papers(&self) -> Vec<Paper> {
+        self.paper_manager.all()
+    }
+
+    /// Overlapping elements, flagged out of bounds when the element is not fully inside any paper
+    pub fn element_collisions(&self, element_id: &str) -> Option<CollisionResult> {
+        let mut result = self.element_manager.collisions(element_id)?;
+        let element = self.element_manager.find(element_id)?;
+        let papers = self.paper_manager.all();
+        result.is_out_of_bounds = !papers.is_empty() && !papers.iter().any(|paper| paper.contains_element(&element));
+        Some(result)
+    }
+
+    /// Elements in a region, using the spatial index
+    pub fn elements_in_region(&self, x: f64, y: f64, width: f64, height: f64) -> Vec<Element> {
+        self.spatial_index_manager.elements_in_region(x, y, width, height)
+    }
+
+    /// Elements under a point, using the spatial index
+    pub fn elements_at_point(&self, x: f64, y: f64) -> Vec<Element> {
+        self.spatial_index_manager.elements_at_point(x, y)
+    }
+
+    /// Table data of a table element
+    pub fn table(&self, element_id: &str) -> Option<TableData> {
+        self.table_manager.table_data(element_id)
+    }
+
+    /// Visible papers and elements for a screen-space viewport, in screen coordinates
+    pub fn render_snapshot(&self, viewport_x: f64, viewport_y: f64, viewport_width: f64, viewport_height: f64) -> RenderSnapshot {
+        let zoom = self.transform_manager.get_zoom();
+        let (canvas_x, canvas_y) = self.transform_manager.apply_transform(viewport_x, viewport_y);
+        let view = Bounds::new(canvas_x, canvas_y, viewport_width / zoom, viewport_height / zoom);
+        let to_screen = |bounds: Bounds| {
+            let (x, y) = self.transform_manager.apply_inverse_transform(bounds.x, bounds.y);
+            ScreenRect { x, y, width: bounds.width * zoom, height: bounds.height * zoom }
+        };
+
+        // Read from the element manager rather than the spatial index, which is only
+        // rebuilt on request and may not know about elements created or moved since
+        let mut elements: Vec<Element> = self
+            .element_manager
+            .all()
+            .into_iter()
+            .filter(|element| element.visible && view.intersects(&element.get_bounds()))
+            .collect();
+        elements.sort_by(|a, b| a.stable_cmp(b));
+
+        RenderSnapshot {
+            zoom,
+            papers: self
+                .paper_manager
+                .all()
+                .into_iter()
+                .filter(|paper| view.intersects(&paper.get_bounds()))
+                .map(|paper| RenderedPaper { screen: to_screen(paper.get_bounds()), paper })
+                .collect(),
+            elements: elements
+                .into_iter()
+                .map(|element| RenderedElement { screen: to_screen(element.get_bounds()), element })
+                .collect(),
+        }
+    }
+
+    /// Snapshot of the document, including the current zoom and pan
+    pub fn document(&self) -> Document {
+        Document {
+            version: DOCUMENT_VERSION,
+            papers: self.paper_manager.all(),
+            elements: self.element_manager.all(),
+            transform: self.transform_manager.state(),
+        }
+    }
+
+    /// Replace the document, restore its saved view and rebuild the spatial index; the loaded document
+    /// has no unsaved changes. Documents from a newer version or with repeated element/paper IDs are rejected
+    pub fn load_document(&self, document: Document) -> bool {
+        if document.version > DOCUMENT_VERSION
+            || has_duplicates(document.papers.iter().map(|paper| paper.id.as_str()))
+            || has_duplicates(document.elements.iter().map(|element| element.id.as_str()))
+        {
+            return false;
+        }
+        if !self.transform_manager.restore(document.transform) {
+            return false;
+        }
+        let (bounds, cell_size) = self.spatial_index_manager.grid_geometry();
+        self.spatial_index_manager.rebuild(&document.elements, bounds, cell_size);
+        self.paper_manager.replace_all(document.papers);
+        self.element_manager.replace_all(document.elements);
+        self.mark_saved();
+        true
+    }
+
+    /// Snapshot of everything `restore_snapshot` rebuilds
+    pub fn engine_snapshot(&self) -> EngineSnapshot {
+        let (spatial_bounds, spatial_cell_size) = self.spatial_index_manager.grid_geometry();
+        EngineSnapshot {
+            version: SNAPSHOT_VERSION,
+            document: self.document(),
+            settings: EditorSettings {
+                size_snap: self.element_manager.size_snap(),
+                size_match_tolerance: self.element_manager.size_match_tolerance(),
+                drag_axis_lock: self.drag_manager.drag_axis_lock().to_string(),
+                spatial_bounds,
+                spatial_cell_size,
+                printer_margin_mm: self.paper_manager.get_printer_margin(),
+            },
+            style_history: self.export_style_history(),
+        }
+    }
+
+    /// Replace the whole engine state and rebuild the spatial index; nothing changes when any part is invalid
+    /// or the snapshot is from a newer version. The open transaction, op log and pending changes are
+    /// dropped: they describe the state being replaced
+    pub fn restore_snapshot(&self, snapshot: EngineSnapshot) -> bool {
+        let settings = &snapshot.settings;
+        let valid_setting = |value: f64| value.is_finite() && value >= 0.0;
+        if snapshot.version > SNAPSHOT_VERSION
+            || !valid_setting(settings.size_snap)
+            || !valid_setting(settings.size_match_tolerance)
+            || !valid_setting(settings.printer_margin_mm)
+            || !matches!(settings.drag_axis_lock.as_str(), "none" | "x" | "y")
+        {
+            return false;
+        }
+        let mut style_history = utils::lock(&self.style_history).clone();
+        if style_history.import_from_base64(&snapshot.style_history).is_err() {
+            return false;
+        }
+        if !self.load_document(snapshot.document) {
+            return false;
+        }
+
+        self.element_manager.set_size_snap(settings.size_snap);
+        self.element_manager.set_size_match_tolerance(settings.size_match_tolerance);
+        self.paper_manager.set_printer_margin(settings.printer_margin_mm);
+        self.drag_manager.clear();
+        self.drag_manager.set_drag_axis_lock(&settings.drag_axis_lock);
+        *utils::lock(&self.style_history) = style_history;
+        self.spatial_index_manager.rebuild(&self.element_manager.all(), settings.spatial_bounds, settings.spatial_cell_size);
+        *utils::lock(&self.transaction_snapshot) = None;
+        self.clear_op_log();
+        utils::lock(&self.change_stream).drain();
+        true
+    }
+
+    /// Render the document to HTML and CSS
+    pub fn render_html(&self, options: &ExportOptions) -> ExportResult {
+        self.export_manager.render(options)
+    }
+}
+
+fn has_duplicates<'a>(mut ids: impl Iterator<Item = &'a str>) -> bool {
+    let mut seen = std::collections::HashSet::new();
+    ids.any(|id| !seen.insert(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_move_and_export_natively() {
+        let engine = HTMLBuilderEngine::new();
+        let paper = engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        assert_eq!(engine.papers().len(), 1);
+
+        let heading = engine.add_element("heading", 20.0, 20.0);
+        assert!(engine.update_element_position(&heading.id, 40.0, 60.0));
+        assert!(engine.update_element_content(&heading.id, "Invoice"));
+
+        let moved = engine.element(&heading.id).unwrap();
+        assert_eq!((moved.x, moved.y), (40.0, 60.0));
+        assert!(paper.contains_element(&moved));
+
+        let result = engine.render_html(&ExportOptions::default());
+        assert!(result.html.contains("<div class=\"a4-paper\" id=\"page-1\">"));
+        assert!(result.html.contains("Invoice"));
+        assert_eq!(result.metadata.total_elements, 1);
+    }
+
+    #[test]
+    fn test_element_collisions() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let first = engine.add_element("button", 10.0, 10.0);
+        let second = engine.add_element("button", 50.0, 20.0);
+        let outside = engine.add_element("button", 2000.0, 2000.0);
+
+        let result = engine.element_collisions(&first.id).unwrap();
+        assert_eq!(result.colliding_elements, vec![second.id.clone()]);
+        assert!(!result.is_out_of_bounds);
+
+        let result = engine.element_collisions(&outside.id).unwrap();
+        assert!(result.colliding_elements.is_empty());
+        assert!(result.is_out_of_bounds);
+
+        assert!(engine.element_collisions("missing").is_none());
+    }
+
+    #[test]
+    fn test_table_data() {
+        let engine = HTMLBuilderEngine::new();
+        let table = engine.add_element("table", 0.0, 0.0);
+        assert!(engine.update_table_cell(&table.id, 1, 2, "42"));
+
+        let data = engine.table(&table.id).unwrap();
+        assert_eq!(data.rows.len(), 3);
+        assert_eq!(data.rows[1].cells[2].content, "42");
+        assert!(engine.table("missing").is_none());
+    }
+
+    #[test]
+    fn test_table_cell_vertical_align() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let table = engine.add_element("table", 20.0, 20.0);
+        assert!(engine.update_table_cell(&table.id, 1, 1, "Centered"));
+        assert!(engine.update_table_cell_style(&table.id, 1, 1, r#"{"verticalAlign":"middle"}"#));
+        assert!(engine.update_table_cell_style(&table.id, 1, 0, r#"{"verticalAlign":"baseline"}"#));
+
+        let data = engine.table(&table.id).unwrap();
+        assert_eq!(data.rows[1].cells[1].style.vertical_align.as_deref(), Some("middle"));
+        assert_eq!(data.rows[1].cells[0].style.vertical_align, None);
+
+        let html = engine.render_html(&ExportOptions::default()).html;
+        let cell = html.lines().find(|line| line.contains(">Centered</td>")).unwrap();
+        assert!(cell.contains("vertical-align: middle;"));
+        assert_eq!(html.matches("vertical-align: middle;").count(), 1);
+    }
+
+    #[test]
+    fn test_document_round_trip_restores_transform() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let heading = engine.add_element("heading", 20.0, 20.0);
+        engine.set_transform(1.5, 20.0, 30.0);
+        let saved = engine.export_document();
+
+        let reopened = HTMLBuilderEngine::new();
+        assert!(reopened.import_document(&saved));
+        assert_eq!(reopened.get_zoom(), 1.5);
+        assert_eq!(reopened.document().transform, TransformState { zoom: 1.5, pan_x: 20.0, pan_y: 30.0 });
+        assert_eq!(reopened.papers().len(), 1);
+        assert_eq!(reopened.element(&heading.id).unwrap().x, 20.0);
+
+        assert!(!reopened.import_document("not json"));
+        assert_eq!(reopened.elements().len(), 1);
+        // Loaded elements are found by region queries without a manual rebuild
+        assert_eq!(reopened.elements_in_region(0.0, 0.0, 100.0, 100.0).len(), 1);
+
+        let mut newer = reopened.document();
+        newer.version = DOCUMENT_VERSION + 1;
+        assert!(!reopened.import_document(&serde_json::to_string(&newer).unwrap()));
+        let mut repeated = reopened.document();
+        repeated.elements.push(repeated.elements[0].clone());
+        assert!(!reopened.import_document(&serde_json::to_string(&repeated).unwrap()));
+        let mut repeated = reopened.document();
+        repeated.papers.push(repeated.papers[0].clone());
+        repeated.transform.zoom = 3.0;
+        assert!(!reopened.import_document(&serde_json::to_string(&repeated).unwrap()));
+        assert_eq!(reopened.get_zoom(), 1.5);
+        assert_eq!(reopened.papers().len(), 1);
+    }
+
+    #[test]
+    fn test_document_reloads_after_removing_and_adding_papers() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        engine.add_paper("page", "A4", "Portrait", 0.0, 1200.0);
+        assert!(engine.remove_paper("page-0"));
+        // The paper count is back to one, but "page-1" is still taken
+        let added = engine.add_paper("page", "A4", "Portrait", 0.0, 2400.0);
+        assert_ne!(added.id, "page-1");
+        let saved = engine.export_document();
+
+        let reopened = HTMLBuilderEngine::new();
+        assert!(reopened.import_document(&saved));
+        assert_eq!(reopened.papers().len(), 2);
+    }
+
+    #[test]
+    fn test_engine_snapshot_round_trip() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let heading = engine.add_element("heading", 20.0, 20.0);
+        let table = engine.add_element("table", 100.0, 300.0);
+        assert!(engine.update_table_cell(&table.id, 1, 1, "42"));
+        assert!(engine.update_element_style(&heading.id, r#"{"fontSize":32}"#));
+        engine.set_transform(1.5, 20.0, 30.0);
+        assert!(engine.set_size_snap(8.0));
+        assert!(engine.set_drag_axis_lock("x"));
+        assert!(engine.set_printer_margin(8.0));
+        engine.rebuild_spatial_index(100.0);
+        let snapshot = engine.snapshot();
+
+        let restored = HTMLBuilderEngine::new();
+        assert!(restored.restore(&snapshot));
+        assert_eq!(restored.snapshot(), snapshot);
+        assert_eq!(restored.export_document(), engine.export_document());
+        assert_eq!(restored.get_style_history_count(), engine.get_style_history_count());
+        assert_eq!(restored.get_zoom(), 1.5);
+        assert_eq!(restored.get_printer_margin(), 8.0);
+        // The spatial index is rebuilt, so region queries match
+        let ids = |engine: &HTMLBuilderEngine| -> Vec<String> {
+            let mut ids: Vec<String> = engine.elements_in_region(0.0, 0.0, 500.0, 500.0).into_iter().map(|element| element.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&restored), ids(&engine));
+        assert_eq!(ids(&restored).len(), 2);
+
+        assert!(!restored.restore("not json"));
+        assert_eq!(restored.elements().len(), 2);
+
+        let mut newer: EngineSnapshot = serde_json::from_str(&snapshot).unwrap();
+        newer.version = SNAPSHOT_VERSION + 1;
+        assert!(!restored.restore(&serde_json::to_string(&newer).unwrap()));
+    }
+
+    #[test]
+    fn test_restore_drops_pending_edit_state() {
+        let engine = HTMLBuilderEngine::new();
+        let heading = engine.add_element("heading", 20.0, 20.0);
+        let snapshot = engine.snapshot();
+
+        engine.enable_op_log(true);
+        engine.enable_change_stream(true);
+        assert!(engine.begin_transaction());
+        assert!(engine.update_element_position(&heading.id, 40.0, 60.0));
+        assert!(engine.is_dirty());
+
+        assert!(engine.restore(&snapshot));
+        assert!(!engine.is_dirty());
+        assert_eq!(engine.get_op_log(), "[]");
+        assert_eq!(engine.drain_change_stream(), "[]");
+        // Rolling back would undo the restore
+        assert!(!engine.rollback_transaction());
+        assert_eq!(engine.element(&heading.id).unwrap().x, 20.0);
+    }
+
+    #[test]
+    fn test_render_snapshot() {
+        let engine = HTMLBuilderEngine::new();
+        engine.add_paper("page", "A4", "Portrait", 0.0, 0.0);
+        let front = engine.add_element("button", 100.0, 100.0);
+        let back = engine.add_element("button", 150.0, 120.0);
+        let offscreen = engine.add_element("button", 1500.0, 1500.0);
+        // Created first but drawn last
+        let mut elements = engine.elements();
+        elements.iter_mut().filter(|element| element.id == front.id).for_each(|element| element.z_index = 5);
+        engine.element_manager.replace_all(elements);
+        engine.rebuild_spatial_index(100.0);
+        engine.set_transform(2.0, 10.0, 20.0);
+
+        // 800x600 screen pixels at 2x zoom show canvas (-5, -10) to (395, 290)
+        let snapshot = engine.render_snapshot(0.0, 0.0, 800.0, 600.0);
+        let ids: Vec<&str> = snapshot.elements.iter().map(|rendered| rendered.element.id.as_str()).collect();
+        assert_eq!(ids, vec![back.id.as_str(), front.id.as_str()]);
+        assert!(!ids.contains(&offscreen.id.as_str()));
+        assert_eq!(snapshot.elements[1].screen, ScreenRect { x: 210.0, y: 220.0, width: 240.0, height: 80.0 });
+        assert_eq!(snapshot.papers.len(), 1);
+        assert_eq!((snapshot.papers[0].screen.x, snapshot.papers[0].screen.y), (10.0, 20.0));
+    }
+
+    #[test]
+    fn test_render_snapshot_sees_unindexed_changes() {
+        let engine = HTMLBuilderEngine::new();
+        let button = engine.add_element("button", 100.0, 100.0);
+        let snapshot = engine.render_snapshot(0.0, 0.0, 800.0, 600.0);
+        assert_eq!(snapshot.elements.len(), 1);
+        assert_eq!(snapshot.elements[0].element.id, button.id);
+
+        assert!(engine.update_element_position(&button.id, 1500.0, 1500.0));
+        assert!(engine.render_snapshot(0.0, 0.0, 800.0, 600.0).elements.is_empty());
+    }
+}
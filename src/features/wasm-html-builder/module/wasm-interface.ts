@@ -16,6 +16,8 @@ export interface StrokeStyle {
   position: 'inside' | 'outside' | 'center';
   style: 'solid' | 'dashed' | 'dotted';
   enabled: boolean;
+  lineCap?: 'butt' | 'round' | 'square' | null;
+  lineJoin?: 'miter' | 'round' | 'bevel' | null;
 }
 
 export interface ElementStyle {
@@ -33,6 +35,13 @@ export interface ElementStyle {
   // Shape-specific styles
   fill?: FillStyle;
   stroke?: StrokeStyle;
+  blendMode?: 'normal' | 'multiply' | 'screen' | 'overlay' | 'darken' | 'lighten' | 'color-dodge' | 'color-burn'
+    | 'hard-light' | 'soft-light' | 'difference' | 'exclusion' | 'hue' | 'saturation' | 'color' | 'luminosity' | null;
+  verticalAlign?: 'top' | 'middle' | 'bottom' | null;
+  direction?: 'ltr' | 'rtl' | null;
+  lineHeight?: number | null;
+  letterSpacing?: number | null;
+  overflow?: 'visible' | 'hidden' | 'ellipsis' | null;
 }
 
 export interface TableCell {
@@ -41,6 +50,8 @@ export interface TableCell {
   rowSpan: number;
   colSpan: number;
   style: ElementStyle;
+  computed?: boolean;
+  elementId?: string;
 }
 
 export interface TableRow {
@@ -57,6 +68,20 @@ export interface TableData {
   columnWidths: number[];
   borderCollapse: boolean;
   tableStyle: ElementStyle;
+  defaultCellPadding?: number;
+  defaultRowHeight?: number;
+  defaultColumnWidth?: number;
+  totalsRow?: boolean;
+  totalsCol?: boolean;
+  sequenceCol?: number | null;
+  sequenceStart?: number;
+  numberFormat?: NumberFormat | null; // Applied to numeric body cells in export
+}
+
+export interface NumberFormat {
+  thousandsSeparator?: string; // e.g. "," (US) or "." (European)
+  decimalSeparator?: string; // Default "."
+  decimals?: number | null; // Default keeps the value's own digits
 }
 
 export interface Element {
@@ -72,6 +97,26 @@ export interface Element {
   content: string;
   style: ElementStyle;
   tableData?: TableData; // For table elements
+  slotKey?: string | null; // Template slot this element fills
+  lockAspect?: boolean; // Keep proportions when resized
+  tabIndex?: number | null; // Keyboard focus order in export
+  anchorRight?: boolean; // Follow the paper's right edge on reflow
+  anchorBottom?: boolean; // Follow the paper's bottom edge on reflow
+  anchorCenter?: boolean; // Stay centered on reflow
+  createdAt?: number; // ms timestamp, 0 if unknown
+  relativeLayout?: RelativeLayout | null; // Percentages of a paper, reapplied on reflow
+  pageBreakBefore?: boolean; // Start a new printed page before the element (flowLayout export)
+  pageBreakAfter?: boolean; // Start a new printed page after the element (flowLayout export)
+  pageBreakInsideAvoid?: boolean; // Keep the element on one printed page (flowLayout export)
+  linkedFrom?: string | null; // Element whose content this one mirrors
+}
+
+export interface RelativeLayout {
+  paperId: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
 }
 
 export interface A4Paper {
@@ -83,6 +128,7 @@ export interface A4Paper {
   width: number;
   height: number;
   title: string | null;
+  dpi: number;
 }
 
 export interface Point {
@@ -129,6 +175,17 @@ export interface ExportOptions {
   include_tailwind: boolean;
   css_framework: 'None' | 'Bootstrap' | 'Tailwind' | { Custom: string };
   export_format: 'Html' | 'React' | 'Vue' | 'Angular';
+  assignMode?: 'firstPaper' | 'centerPaper' | 'allPapers';
+  autoTabOrder?: boolean;
+  showPageTitles?: boolean;
+  batchShapes?: boolean;
+  highlightQuery?: string;
+  bleedMm?: number; // Skipped unless every paper is A4 portrait
+  cropMarks?: boolean;
+  printableGuide?: boolean; // Dashed outline of the printable area, not in print mode
+  printerMarginMm?: number; // Override; default is the engine's set_printer_margin value (5)
+  applyFillStroke?: boolean; // Use fill/stroke as background/border on non-shape elements
+  flowLayout?: boolean; // Stack elements in reading order; page-break flags only apply here
 }
 
 // Spatial Indexing interfaces
@@ -159,15 +216,35 @@ export interface ExportResult {
     css_classes_count: number;
     export_timestamp: number;
     framework_used: string;
+    warnings: ExportWarning[];
   };
 }
 
+export interface ExportWarning {
+  element_id: string;
+  kind: 'invalid_content' | 'invalid_color' | 'inconsistent_table' | 'outside_paper';
+  message: string;
+}
+
 export interface PositionUpdate {
   element_id: string;
   x: number;
   y: number;
 }
 
+export interface TransformState {
+  zoom: number;
+  pan_x: number;
+  pan_y: number;
+}
+
+export interface YorkDocument {
+  version: number;
+  papers: A4Paper[];
+  elements: Element[];
+  transform?: TransformState;
+}
+
 export interface WasmEngineState {
   isLoaded: boolean;
   isLoading: boolean;
@@ -402,16 +479,16 @@ export class HtmlBuilderWasm {
     return JSON.parse(result);
   }
 
-  zoomToPoint(screenX: number, screenY: number, zoomDelta: number): number {
+  zoomToPoint(screenX: number, screenY: number, zoomDelta: number): { zoom: number; pan_x: number; pan_y: number } {
     if (!this.isInitialized || !this.engine) {
       console.warn('WASM engine not initialized, cannot zoom to point');
-      return 1.0;
+      return { zoom: 1.0, pan_x: 0, pan_y: 0 };
     }
     try {
-      return this.engine.zoom_to_point(screenX, screenY, zoomDelta);
+      return JSON.parse(this.engine.zoom_to_point(screenX, screenY, zoomDelta));
     } catch (error) {
       console.warn('Error in zoomToPoint:', error);
-      return 1.0;
+      return { zoom: 1.0, pan_x: 0, pan_y: 0 };
     }
   }
 